@@ -14,8 +14,63 @@
  * limitations under the License.
  */
 
+use std::borrow::Cow;
+use std::collections::HashSet;
+use std::sync::Mutex;
+
+use iceberg::TableIdent;
 use mixtrics::metrics::{BoxedCounterVec, BoxedHistogramVec, BoxedRegistry, Buckets};
+use serde::Deserialize;
+
+pub(crate) mod sizing;
+
+/// Label value used for tables beyond `MetricsLabelConfig::max_cardinality`.
+pub const OVERFLOW_LABEL_VALUE: &str = "other";
+
+/// Which labels are attached to compaction metrics.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum MetricsLabels {
+    /// `catalog_name` and `table_ident`.
+    #[default]
+    CatalogAndTable,
+    /// `catalog_name` and `namespace`, aggregating all tables of a namespace.
+    CatalogAndNamespace,
+    /// `catalog_name` only.
+    Catalog,
+}
+
+impl MetricsLabels {
+    fn label_names(&self, tenant: bool) -> &'static [&'static str] {
+        match (self, tenant) {
+            (MetricsLabels::CatalogAndTable, false) => &["catalog_name", "table_ident"],
+            (MetricsLabels::CatalogAndNamespace, false) => &["catalog_name", "namespace"],
+            (MetricsLabels::Catalog, false) => &["catalog_name"],
+            (MetricsLabels::CatalogAndTable, true) => &["tenant", "catalog_name", "table_ident"],
+            (MetricsLabels::CatalogAndNamespace, true) => &["tenant", "catalog_name", "namespace"],
+            (MetricsLabels::Catalog, true) => &["tenant", "catalog_name"],
+        }
+    }
+}
+
+/// Controls metric labels so compacting thousands of tables doesn't explode series cardinality.
+#[derive(Debug, Default, Clone, Deserialize)]
+pub struct MetricsLabelConfig {
+    pub labels: MetricsLabels,
+    /// Maximum distinct table or namespace label values, and separately tenant label values;
+    /// later ones are reported as `OVERFLOW_LABEL_VALUE`. Unlimited when `None`.
+    pub max_cardinality: Option<usize>,
+    /// Also attach a `tenant` label, set per compaction with `CompactionBuilder::with_tenant`.
+    /// Compactions without a tenant report an empty one.
+    #[serde(default)]
+    pub tenant: bool,
+}
 
+/// Compaction metrics, named following the Prometheus conventions: an `iceberg_compaction_`
+/// prefix, base units in the name and a `_total` suffix for counters.
+///
+/// Metrics are registered once per registry, so compactions reporting to the same registry
+/// should share one `Metrics` with `CompactionBuilder::with_metrics`. The cardinality cap then
+/// also applies across them.
 pub struct Metrics {
     pub compaction_commit_counter: BoxedCounterVec,
     pub compaction_duration: BoxedHistogramVec,
@@ -29,69 +84,82 @@ pub struct Metrics {
 
     pub compaction_commit_failed_counter: BoxedCounterVec,
     pub compaction_executor_error_counter: BoxedCounterVec,
+
+    label_config: MetricsLabelConfig,
+    seen_label_values: Mutex<HashSet<String>>,
+    seen_tenants: Mutex<HashSet<String>>,
 }
 
 impl Metrics {
     pub fn new(registry: BoxedRegistry) -> Self {
+        Self::new_with_label_config(registry, MetricsLabelConfig::default())
+    }
+
+    pub fn new_with_label_config(
+        registry: BoxedRegistry,
+        label_config: MetricsLabelConfig,
+    ) -> Self {
+        let label_names = label_config.labels.label_names(label_config.tenant);
+
         let compaction_commit_counter = registry.register_counter_vec(
-            "compaction_commit_counter".into(),
+            "iceberg_compaction_commits_total".into(),
             "iceberg-compaction compaction total commit counts".into(),
-            &["catalog_name", "table_ident"],
+            label_names,
         );
 
         let compaction_duration = registry.register_histogram_vec_with_buckets(
-            "compaction_duration".into(),
+            "iceberg_compaction_duration_seconds".into(),
             "iceberg-compaction compaction duration in seconds".into(),
-            &["catalog_name", "table_ident"],
+            label_names,
             Buckets::exponential(
                 1.0, 2.0, 20, // Start at 1 second, double each bucket, up to 20 buckets
             ),
         );
 
         let compaction_rewritten_bytes = registry.register_counter_vec(
-            "compaction_rewritten_bytes".into(),
+            "iceberg_compaction_rewritten_bytes_total".into(),
             "iceberg-compaction compaction rewritten bytes".into(),
-            &["catalog_name", "table_ident"],
+            label_names,
         );
 
         let compaction_rewritten_files_count = registry.register_counter_vec(
-            "compaction_rewritten_files_count".into(),
+            "iceberg_compaction_rewritten_files_total".into(),
             "iceberg-compaction compaction rewritten files count".into(),
-            &["catalog_name", "table_ident"],
+            label_names,
         );
 
         let compaction_added_files_count = registry.register_counter_vec(
-            "compaction_added_files_count".into(),
+            "iceberg_compaction_added_files_total".into(),
             "iceberg-compaction compaction added files count".into(),
-            &["catalog_name", "table_ident"],
+            label_names,
         );
 
         let compaction_failed_data_files_count = registry.register_counter_vec(
-            "compaction_failed_data_files_count".into(),
+            "iceberg_compaction_failed_data_files_total".into(),
             "iceberg-compaction compaction failed data files count".into(),
-            &["catalog_name", "table_ident"],
+            label_names,
         );
 
         // 10ms 100ms 1s 10s 100s
         let compaction_commit_duration = registry.register_histogram_vec_with_buckets(
-            "compaction_commit_duration".into(),
-            "iceberg-compaction compaction commit duration in milliseconds".into(),
-            &["catalog_name", "table_ident"],
+            "iceberg_compaction_commit_duration_seconds".into(),
+            "iceberg-compaction compaction commit duration in seconds".into(),
+            label_names,
             Buckets::exponential(
-                10.0, 10.0, 5, // Start at 10ms, multiply each bucket by 10, up to 5 buckets
+                0.01, 10.0, 5, // Start at 10ms, multiply each bucket by 10, up to 5 buckets
             ),
         );
 
         let compaction_commit_failed_counter = registry.register_counter_vec(
-            "compaction_commit_failed_counter".into(),
+            "iceberg_compaction_commit_failures_total".into(),
             "iceberg-compaction compaction commit failed counts".into(),
-            &["catalog_name", "table_ident"],
+            label_names,
         );
 
         let compaction_executor_error_counter = registry.register_counter_vec(
-            "compaction_executor_error_counter".into(),
+            "iceberg_compaction_executor_errors_total".into(),
             "iceberg-compaction compaction executor error counts".into(),
-            &["catalog_name", "table_ident"],
+            label_names,
         );
 
         Self {
@@ -104,6 +172,172 @@ impl Metrics {
             compaction_commit_duration,
             compaction_commit_failed_counter,
             compaction_executor_error_counter,
+            label_config,
+            seen_label_values: Mutex::new(HashSet::new()),
+            seen_tenants: Mutex::new(HashSet::new()),
+        }
+    }
+
+    /// Label values for a table, matching the label names the metrics were registered with.
+    /// `tenant` is ignored unless `MetricsLabelConfig::tenant` is set.
+    pub fn label_values(
+        &self,
+        catalog_name: &str,
+        table_ident: &TableIdent,
+        tenant: Option<&str>,
+    ) -> Vec<Cow<'static, str>> {
+        let mut label_values = vec![];
+        if self.label_config.tenant {
+            let tenant = tenant.unwrap_or_default().to_owned();
+            label_values.push(Cow::Owned(self.cap_cardinality(&self.seen_tenants, tenant)));
+        }
+        label_values.push(Cow::Owned(catalog_name.to_owned()));
+        let scoped_value = match self.label_config.labels {
+            MetricsLabels::CatalogAndTable => Some(table_ident.to_string()),
+            MetricsLabels::CatalogAndNamespace => Some(table_ident.namespace().as_ref().join(".")),
+            MetricsLabels::Catalog => None,
+        };
+        if let Some(scoped_value) = scoped_value {
+            label_values.push(Cow::Owned(
+                self.cap_cardinality(&self.seen_label_values, scoped_value),
+            ));
+        }
+        label_values
+    }
+
+    fn cap_cardinality(&self, seen_label_values: &Mutex<HashSet<String>>, value: String) -> String {
+        let Some(max_cardinality) = self.label_config.max_cardinality else {
+            return value;
+        };
+        let mut seen_label_values = seen_label_values.lock().unwrap();
+        if seen_label_values.contains(&value) || seen_label_values.len() < max_cardinality {
+            seen_label_values.insert(value.clone());
+            value
+        } else {
+            OVERFLOW_LABEL_VALUE.to_owned()
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use iceberg::NamespaceIdent;
+    use mixtrics::registry::noop::NoopMetricsRegistry;
+
+    fn table_ident(namespace: &str, name: &str) -> TableIdent {
+        TableIdent::new(NamespaceIdent::new(namespace.to_owned()), name.to_owned())
+    }
+
+    #[test]
+    fn test_label_values_per_label_set() {
+        let metrics = Metrics::new(Box::new(NoopMetricsRegistry));
+        assert_eq!(
+            metrics.label_values("catalog", &table_ident("ns", "t1"), None),
+            vec!["catalog", "ns.t1"]
+        );
+
+        let metrics = Metrics::new_with_label_config(
+            Box::new(NoopMetricsRegistry),
+            MetricsLabelConfig {
+                labels: MetricsLabels::CatalogAndNamespace,
+                max_cardinality: None,
+                tenant: false,
+            },
+        );
+        assert_eq!(
+            metrics.label_values("catalog", &table_ident("ns", "t1"), None),
+            vec!["catalog", "ns"]
+        );
+
+        let metrics = Metrics::new_with_label_config(
+            Box::new(NoopMetricsRegistry),
+            MetricsLabelConfig {
+                labels: MetricsLabels::Catalog,
+                max_cardinality: None,
+                tenant: false,
+            },
+        );
+        assert_eq!(
+            metrics.label_values("catalog", &table_ident("ns", "t1"), None),
+            vec!["catalog"]
+        );
+
+        let metrics = Metrics::new_with_label_config(
+            Box::new(NoopMetricsRegistry),
+            MetricsLabelConfig {
+                labels: MetricsLabels::CatalogAndTable,
+                max_cardinality: None,
+                tenant: true,
+            },
+        );
+        assert_eq!(
+            metrics.label_values("catalog", &table_ident("ns", "t1"), Some("acme")),
+            vec!["acme", "catalog", "ns.t1"]
+        );
+        assert_eq!(
+            metrics.label_values("catalog", &table_ident("ns", "t1"), None),
+            vec!["", "catalog", "ns.t1"]
+        );
+    }
+
+    #[test]
+    fn test_label_values_cardinality_cap() {
+        let metrics = Metrics::new_with_label_config(
+            Box::new(NoopMetricsRegistry),
+            MetricsLabelConfig {
+                labels: MetricsLabels::CatalogAndTable,
+                max_cardinality: Some(2),
+                tenant: false,
+            },
+        );
+
+        assert_eq!(
+            metrics.label_values("c", &table_ident("ns", "t1"), None)[1],
+            "ns.t1"
+        );
+        assert_eq!(
+            metrics.label_values("c", &table_ident("ns", "t2"), None)[1],
+            "ns.t2"
+        );
+        assert_eq!(
+            metrics.label_values("c", &table_ident("ns", "t3"), None)[1],
+            OVERFLOW_LABEL_VALUE
+        );
+        // Tables seen before the cap was reached keep their own series.
+        assert_eq!(
+            metrics.label_values("c", &table_ident("ns", "t1"), None)[1],
+            "ns.t1"
+        );
+    }
+
+    #[test]
+    fn test_tenant_label_cardinality_cap() {
+        let metrics = Metrics::new_with_label_config(
+            Box::new(NoopMetricsRegistry),
+            MetricsLabelConfig {
+                labels: MetricsLabels::CatalogAndTable,
+                max_cardinality: Some(2),
+                tenant: true,
+            },
+        );
+
+        // Tenants are capped apart from tables, so both keep two values of their own.
+        assert_eq!(
+            metrics.label_values("c", &table_ident("ns", "t1"), Some("a")),
+            vec!["a", "c", "ns.t1"]
+        );
+        assert_eq!(
+            metrics.label_values("c", &table_ident("ns", "t2"), Some("b")),
+            vec!["b", "c", "ns.t2"]
+        );
+        assert_eq!(
+            metrics.label_values("c", &table_ident("ns", "t1"), Some("c")),
+            vec![OVERFLOW_LABEL_VALUE, "c", "ns.t1"]
+        );
+        assert_eq!(
+            metrics.label_values("c", &table_ident("ns", "t2"), Some("a")),
+            vec!["a", "c", "ns.t2"]
+        );
+    }
+}
@@ -0,0 +1,141 @@
+/*
+ * Copyright 2025 iceberg-compaction
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Estimates of the output of a rewrite, shared by the planner and the executors.
+
+use std::collections::HashSet;
+
+use iceberg::scan::FileScanTask;
+
+/// Number of files of `target_file_size` that `input_size` bytes of live rows are expected to be
+/// rewritten into, at least one.
+pub fn expected_output_files(input_size: u64, target_file_size: u64) -> usize {
+    input_size.div_ceil(target_file_size.max(1)).max(1) as usize
+}
+
+/// Bytes of `data_files` left once their deleted rows are dropped, estimated from the fraction
+/// of deleted rows of each file, see `estimated_delete_ratio`.
+pub fn estimated_live_bytes<'a>(data_files: impl IntoIterator<Item = &'a FileScanTask>) -> u64 {
+    let data_files: Vec<&FileScanTask> = data_files.into_iter().collect();
+    let shared_delete_file_paths = shared_delete_file_paths(data_files.iter().copied());
+    data_files
+        .into_iter()
+        .map(|task| {
+            let delete_ratio = estimated_delete_ratio(task, &shared_delete_file_paths);
+            (task.file_size_in_bytes as f64 * (1.0 - delete_ratio)).ceil() as u64
+        })
+        .sum()
+}
+
+/// Paths of the delete files applying to more than one of `data_files`, such as equality deletes
+/// and partition-wide position deletes.
+pub fn shared_delete_file_paths<'a>(
+    data_files: impl IntoIterator<Item = &'a FileScanTask>,
+) -> HashSet<&'a str> {
+    let mut seen = HashSet::new();
+    let mut shared = HashSet::new();
+    for delete in data_files.into_iter().flat_map(|task| &task.deletes) {
+        let path = delete.data_file_path.as_str();
+        if !seen.insert(path) {
+            shared.insert(path);
+        }
+    }
+    shared
+}
+
+/// Estimates the fraction of deleted rows of a data file from the record counts of the delete
+/// files applying only to it, capped at 1.
+///
+/// How many rows of `shared_delete_file_paths` fall into the file is unknown, so they are left
+/// out rather than counted in full against every file they apply to. For the other delete files
+/// the estimate is an upper bound.
+pub fn estimated_delete_ratio(
+    task: &FileScanTask,
+    shared_delete_file_paths: &HashSet<&str>,
+) -> f64 {
+    let delete_records: Vec<u64> = task
+        .deletes
+        .iter()
+        .filter(|delete| !shared_delete_file_paths.contains(delete.data_file_path.as_str()))
+        .map(|delete| delete.record_count.unwrap_or(0))
+        .collect();
+    if delete_records.is_empty() {
+        return 0.0;
+    }
+    let data_records = task.record_count.unwrap_or(0);
+    if data_records == 0 {
+        return 1.0;
+    }
+    (delete_records.iter().sum::<u64>() as f64 / data_records as f64).min(1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::data_file_with_deletes;
+
+    #[test]
+    fn test_expected_output_files() {
+        assert_eq!(expected_output_files(0, 30), 1);
+        assert_eq!(expected_output_files(61, 30), 3);
+        assert_eq!(expected_output_files(61, 0), 61);
+    }
+
+    #[test]
+    fn test_estimated_live_bytes() {
+        let data_files = vec![
+            data_file_with_deletes("clean", 100, 10, &[]),
+            data_file_with_deletes("half-deleted", 100, 10, &[2, 3]),
+            data_file_with_deletes("deleted", 100, 10, &[20]),
+        ];
+
+        assert_eq!(estimated_delete_ratio(&data_files[1], &HashSet::new()), 0.5);
+        assert_eq!(estimated_live_bytes(&data_files), 150);
+        assert_eq!(
+            expected_output_files(estimated_live_bytes(&data_files), 100),
+            2
+        );
+    }
+
+    #[test]
+    fn test_shared_deletes_left_out_of_estimates() {
+        let mut data_files = vec![
+            data_file_with_deletes("a", 100, 10, &[5]),
+            data_file_with_deletes("b", 100, 10, &[]),
+        ];
+        // An equality delete file of 10 rows applies to both data files.
+        let shared_delete = FileScanTask {
+            data_file_path: "shared-delete".to_owned(),
+            record_count: Some(10),
+            ..data_files[0].deletes[0].clone()
+        };
+        for data_file in &mut data_files {
+            data_file.deletes.push(shared_delete.clone());
+        }
+
+        let shared_delete_file_paths = shared_delete_file_paths(&data_files);
+        assert_eq!(shared_delete_file_paths, HashSet::from(["shared-delete"]));
+        assert_eq!(
+            estimated_delete_ratio(&data_files[0], &shared_delete_file_paths),
+            0.5
+        );
+        assert_eq!(
+            estimated_delete_ratio(&data_files[1], &shared_delete_file_paths),
+            0.0
+        );
+        assert_eq!(estimated_live_bytes(&data_files), 150);
+    }
+}
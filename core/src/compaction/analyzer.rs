@@ -0,0 +1,224 @@
+/*
+ * Copyright 2025 iceberg-compaction
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Table health statistics computed from manifests alone, to decide whether a compaction is
+//! worth running before reading any data.
+
+use std::collections::HashMap;
+
+use futures::{StreamExt, TryStreamExt};
+use iceberg::spec::{DataContentType, Struct};
+use iceberg::table::Table;
+
+use crate::error::Result;
+use crate::CompactionConfig;
+
+/// Data file sizes at a few percentiles, using the nearest-rank method.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FileSizePercentiles {
+    pub p50: u64,
+    pub p90: u64,
+    pub p99: u64,
+}
+
+/// Statistics of the live files of a snapshot.
+#[derive(Debug, Clone, Default)]
+pub struct TableHealth {
+    pub snapshot_id: Option<i64>,
+    pub data_files_count: usize,
+    /// Data files smaller than `CompactionConfig::small_file_threshold`.
+    pub small_files_count: usize,
+    pub data_bytes: u64,
+    pub average_file_size: u64,
+    pub file_size_percentiles: FileSizePercentiles,
+    pub position_delete_files_count: usize,
+    pub equality_delete_files_count: usize,
+    /// Records of all delete files over records of all data files, capped at 1. An upper bound
+    /// of the fraction of deleted rows, since deletes may match no row.
+    pub delete_ratio: f64,
+    pub partitions_count: usize,
+    /// Data bytes of the largest partition over the average of all partitions, 1 when data is
+    /// spread evenly.
+    pub partition_skew: f64,
+}
+
+/// Computes the `TableHealth` of a table from the manifests of its current snapshot.
+pub struct TableAnalyzer<'a> {
+    table: &'a Table,
+    small_file_threshold: u64,
+    parallelism: usize,
+}
+
+/// The fields of a live manifest entry the analysis needs.
+struct FileStats {
+    content: DataContentType,
+    size: u64,
+    records: u64,
+    partition: (i32, Struct),
+}
+
+impl<'a> TableAnalyzer<'a> {
+    /// Uses the small file threshold and manifest IO parallelism of `config`.
+    pub fn new(table: &'a Table, config: &CompactionConfig) -> Self {
+        Self {
+            table,
+            small_file_threshold: config.small_file_threshold,
+            parallelism: config.manifest_io_parallelism,
+        }
+    }
+
+    pub async fn analyze(&self) -> Result<TableHealth> {
+        let Some(snapshot) = self.table.metadata().current_snapshot() else {
+            return Ok(TableHealth::default());
+        };
+        let file_io = self.table.file_io();
+        let manifest_list = snapshot
+            .load_manifest_list(file_io, self.table.metadata())
+            .await?;
+        let mut manifests = futures::stream::iter(manifest_list.entries().iter().cloned())
+            .map(|manifest_file| async move {
+                let spec_id = manifest_file.partition_spec_id;
+                Ok::<_, iceberg::Error>((spec_id, manifest_file.load_manifest(file_io).await?))
+            })
+            .buffer_unordered(self.parallelism.max(1));
+
+        let mut files = vec![];
+        while let Some((spec_id, manifest)) = manifests.try_next().await? {
+            for entry in manifest.entries().iter().filter(|entry| entry.is_alive()) {
+                let data_file = entry.data_file();
+                files.push(FileStats {
+                    content: entry.content_type(),
+                    size: data_file.file_size_in_bytes(),
+                    records: data_file.record_count(),
+                    partition: (spec_id, data_file.partition().clone()),
+                });
+            }
+        }
+
+        Ok(TableHealth {
+            snapshot_id: Some(snapshot.snapshot_id()),
+            ..summarize(files, self.small_file_threshold)
+        })
+    }
+}
+
+fn summarize(files: Vec<FileStats>, small_file_threshold: u64) -> TableHealth {
+    let mut health = TableHealth::default();
+    let mut data_sizes = vec![];
+    let mut data_records = 0;
+    let mut delete_records = 0;
+    let mut partition_bytes: HashMap<(i32, Struct), u64> = HashMap::new();
+    for file in files {
+        match file.content {
+            DataContentType::Data => {
+                data_sizes.push(file.size);
+                data_records += file.records;
+                *partition_bytes.entry(file.partition).or_default() += file.size;
+            }
+            DataContentType::PositionDeletes => {
+                health.position_delete_files_count += 1;
+                delete_records += file.records;
+            }
+            DataContentType::EqualityDeletes => {
+                health.equality_delete_files_count += 1;
+                delete_records += file.records;
+            }
+        }
+    }
+
+    data_sizes.sort_unstable();
+    health.data_files_count = data_sizes.len();
+    health.small_files_count = data_sizes
+        .iter()
+        .filter(|size| **size < small_file_threshold)
+        .count();
+    health.data_bytes = data_sizes.iter().sum();
+    if !data_sizes.is_empty() {
+        health.average_file_size = health.data_bytes / data_sizes.len() as u64;
+        health.file_size_percentiles = FileSizePercentiles {
+            p50: percentile(&data_sizes, 0.5),
+            p90: percentile(&data_sizes, 0.9),
+            p99: percentile(&data_sizes, 0.99),
+        };
+    }
+    health.delete_ratio = match data_records {
+        0 if delete_records > 0 => 1.0,
+        0 => 0.0,
+        _ => (delete_records as f64 / data_records as f64).min(1.0),
+    };
+    health.partitions_count = partition_bytes.len();
+    if health.data_bytes > 0 {
+        let largest = partition_bytes.values().copied().max().unwrap_or(0);
+        let average = health.data_bytes as f64 / partition_bytes.len() as f64;
+        health.partition_skew = largest as f64 / average;
+    }
+    health
+}
+
+/// Nearest-rank percentile of non-empty `sorted` values.
+fn percentile(sorted: &[u64], fraction: f64) -> u64 {
+    let rank = (fraction * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use iceberg::spec::Literal;
+
+    fn file_stats(content: DataContentType, size: u64, records: u64, partition: i32) -> FileStats {
+        FileStats {
+            content,
+            size,
+            records,
+            partition: (0, Struct::from_iter([Some(Literal::int(partition))])),
+        }
+    }
+
+    #[test]
+    fn test_summarize() {
+        let mut files: Vec<FileStats> = (1..=10)
+            .map(|i| file_stats(DataContentType::Data, i * 10, 100, 0))
+            .collect();
+        files.push(file_stats(DataContentType::Data, 450, 100, 1));
+        files.push(file_stats(DataContentType::PositionDeletes, 5, 55, 0));
+        files.push(file_stats(DataContentType::EqualityDeletes, 5, 11, 1));
+
+        let health = summarize(files, 50);
+
+        assert_eq!(health.data_files_count, 11);
+        assert_eq!(health.small_files_count, 4);
+        assert_eq!(health.data_bytes, 1000);
+        assert_eq!(health.average_file_size, 90);
+        assert_eq!(
+            health.file_size_percentiles,
+            FileSizePercentiles {
+                p50: 60,
+                p90: 100,
+                p99: 450,
+            }
+        );
+        assert_eq!(health.position_delete_files_count, 1);
+        assert_eq!(health.equality_delete_files_count, 1);
+        assert_eq!(health.delete_ratio, 0.06);
+        assert_eq!(health.partitions_count, 2);
+        assert_eq!(health.partition_skew, 1.1);
+
+        let health = summarize(vec![], 50);
+        assert_eq!(health.data_files_count, 0);
+        assert_eq!(health.partition_skew, 0.0);
+    }
+}
@@ -0,0 +1,70 @@
+/*
+ * Copyright 2025 iceberg-compaction
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Callbacks around the commits of a compaction, for integrators to add their own validation,
+//! metrics or notifications.
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use iceberg::spec::Snapshot;
+use iceberg::table::Table;
+use iceberg::transaction::Transaction;
+
+use crate::error::{CompactionError, Result};
+
+/// Hook called around every rewrite commit of a `Compaction`, registered with
+/// `CompactionBuilder::with_commit_hook`. This includes the commits of
+/// `Compaction::remove_dangling_delete_files` and `Compaction::repair_duplicate_data_files`,
+/// but not the commit of `Compaction::expire_snapshot`, which only removes snapshots and so
+/// creates none to pass to `after_commit`; neither hook method is called for it.
+#[async_trait]
+pub trait CommitHook: Send + Sync {
+    /// Called with the reloaded table and the transaction about to be committed to it, once per
+    /// commit attempt. An error aborts the commit without retrying it, and is returned wrapped
+    /// in `CompactionError::CommitHook`.
+    async fn before_commit(&self, _table: &Table, _transaction: &Transaction<'_>) -> Result<()> {
+        Ok(())
+    }
+
+    /// Called once the commit succeeded, with the snapshot it created.
+    async fn after_commit(&self, _table: &Table, _snapshot: &Snapshot) {}
+}
+
+/// Calls `before_commit` of each of `commit_hooks` in order, stopping at the first error.
+pub(crate) async fn before_commit(
+    commit_hooks: &[Arc<dyn CommitHook>],
+    table: &Table,
+    transaction: &Transaction<'_>,
+) -> Result<()> {
+    for commit_hook in commit_hooks {
+        commit_hook
+            .before_commit(table, transaction)
+            .await
+            .map_err(|e| CompactionError::CommitHook(Box::new(e)))?;
+    }
+    Ok(())
+}
+
+/// Calls `after_commit` of each of `commit_hooks` with the current snapshot of the committed
+/// `table`.
+pub(crate) async fn after_commit(commit_hooks: &[Arc<dyn CommitHook>], table: &Table) {
+    if let Some(snapshot) = table.metadata().current_snapshot() {
+        for commit_hook in commit_hooks {
+            commit_hook.after_commit(table, snapshot).await;
+        }
+    }
+}
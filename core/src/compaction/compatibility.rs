@@ -0,0 +1,217 @@
+/*
+ * Copyright 2025 iceberg-compaction
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::collections::HashMap;
+
+use iceberg::spec::{FormatVersion, TableMetadata, Transform};
+
+use crate::error::{CompactionError, Result};
+use crate::executor::datafusion::datafusion_processor::is_order_preserving;
+
+const ENCRYPTION_PROPERTY_PREFIX: &str = "encryption.";
+const NAME_MAPPING_PROPERTY: &str = "schema.name-mapping.default";
+const WRITE_FORMAT_PROPERTY: &str = "write.format.default";
+
+/// A table feature compaction does not fully handle.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompatibilityIssue {
+    pub feature: String,
+    pub detail: String,
+    /// Whether compaction must refuse to run, rather than run with degraded behavior.
+    pub blocking: bool,
+}
+
+/// Features of a table that compaction relies on or cannot handle.
+#[derive(Debug, Clone)]
+pub struct CompatibilityReport {
+    pub format_version: FormatVersion,
+    pub issues: Vec<CompatibilityIssue>,
+}
+
+impl CompatibilityReport {
+    pub fn is_compatible(&self) -> bool {
+        !self.issues.iter().any(|issue| issue.blocking)
+    }
+
+    /// Fails with `UnsupportedTableFeature` listing every blocking issue.
+    pub fn ensure_compatible(&self) -> Result<()> {
+        let blocking: Vec<String> = self
+            .issues
+            .iter()
+            .filter(|issue| issue.blocking)
+            .map(|issue| format!("{}: {}", issue.feature, issue.detail))
+            .collect();
+        if blocking.is_empty() {
+            Ok(())
+        } else {
+            Err(CompactionError::UnsupportedTableFeature(blocking.join("; ")))
+        }
+    }
+}
+
+/// Checks the table metadata against the features compaction supports.
+pub fn check_table_compatibility(metadata: &TableMetadata) -> CompatibilityReport {
+    let mut issues = check_properties(metadata.properties());
+    issues.extend(check_format_version(metadata.format_version() as u8));
+    issues.extend(check_partition_transforms(
+        metadata
+            .default_partition_spec()
+            .fields()
+            .iter()
+            .map(|field| &field.transform),
+    ));
+    let sort_order = metadata.default_sort_order();
+    if !sort_order.is_unsorted()
+        && sort_order
+            .fields
+            .iter()
+            .any(|field| !is_order_preserving(&field.transform))
+    {
+        issues.push(CompatibilityIssue {
+            feature: "sort order".to_owned(),
+            detail: "output is only sorted up to the first bucket or unknown transform"
+                .to_owned(),
+            blocking: false,
+        });
+    }
+
+    CompatibilityReport {
+        format_version: metadata.format_version(),
+        issues,
+    }
+}
+
+/// Format versions above 2 add deletion vectors and row lineage, which compaction would drop.
+fn check_format_version(format_version: u8) -> Option<CompatibilityIssue> {
+    (format_version > FormatVersion::V2 as u8).then(|| CompatibilityIssue {
+        feature: "format version".to_owned(),
+        detail: format!(
+            "format version {} deletion vectors and row lineage are not supported",
+            format_version
+        ),
+        blocking: true,
+    })
+}
+
+/// Output is partitioned by the default spec, so each of its transforms must be computable.
+fn check_partition_transforms<'a>(
+    transforms: impl IntoIterator<Item = &'a Transform>,
+) -> Option<CompatibilityIssue> {
+    transforms
+        .into_iter()
+        .any(|transform| *transform == Transform::Unknown)
+        .then(|| CompatibilityIssue {
+            feature: "partition transform".to_owned(),
+            detail: "output can't be partitioned by an unknown transform".to_owned(),
+            blocking: true,
+        })
+}
+
+fn check_properties(properties: &HashMap<String, String>) -> Vec<CompatibilityIssue> {
+    let mut issues = vec![];
+    if properties
+        .keys()
+        .any(|key| key.starts_with(ENCRYPTION_PROPERTY_PREFIX))
+    {
+        issues.push(CompatibilityIssue {
+            feature: "encryption".to_owned(),
+            detail: "encrypted tables can't be read or written".to_owned(),
+            blocking: true,
+        });
+    }
+    if properties.contains_key(NAME_MAPPING_PROPERTY) {
+        issues.push(CompatibilityIssue {
+            feature: "name mapping".to_owned(),
+            detail: "data files without field ids are not resolved by name".to_owned(),
+            blocking: false,
+        });
+    }
+    if let Some(format) = properties.get(WRITE_FORMAT_PROPERTY) {
+        if !format.eq_ignore_ascii_case("parquet") {
+            issues.push(CompatibilityIssue {
+                feature: "file format".to_owned(),
+                detail: format!("output is written as parquet, not '{}'", format),
+                blocking: false,
+            });
+        }
+    }
+    issues
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_properties() {
+        let properties: HashMap<String, String> = [
+            ("encryption.key-id".to_owned(), "key".to_owned()),
+            (WRITE_FORMAT_PROPERTY.to_owned(), "orc".to_owned()),
+        ]
+        .into_iter()
+        .collect();
+        let report = CompatibilityReport {
+            format_version: FormatVersion::V2,
+            issues: check_properties(&properties),
+        };
+
+        assert_eq!(report.issues.len(), 2);
+        assert!(!report.is_compatible());
+        assert!(matches!(
+            report.ensure_compatible(),
+            Err(CompactionError::UnsupportedTableFeature(_))
+        ));
+
+        let report = CompatibilityReport {
+            format_version: FormatVersion::V2,
+            issues: check_properties(&HashMap::new()),
+        };
+        assert!(report.is_compatible());
+    }
+
+    #[test]
+    fn test_check_format_version() {
+        assert!(check_format_version(FormatVersion::V1 as u8).is_none());
+        assert!(check_format_version(FormatVersion::V2 as u8).is_none());
+
+        let issue = check_format_version(3).unwrap();
+        assert!(issue.blocking);
+        let report = CompatibilityReport {
+            format_version: FormatVersion::V2,
+            issues: vec![issue],
+        };
+        assert!(matches!(
+            report.ensure_compatible(),
+            Err(CompactionError::UnsupportedTableFeature(_))
+        ));
+    }
+
+    #[test]
+    fn test_check_partition_transforms() {
+        let supported = [
+            Transform::Identity,
+            Transform::Bucket(16),
+            Transform::Truncate(4),
+            Transform::Day,
+            Transform::Void,
+        ];
+        assert!(check_partition_transforms(&supported).is_none());
+        assert!(check_partition_transforms(&[]).is_none());
+
+        let issue = check_partition_transforms(&[Transform::Identity, Transform::Unknown]).unwrap();
+        assert!(issue.blocking);
+    }
+}
@@ -0,0 +1,139 @@
+/*
+ * Copyright 2025 iceberg-compaction
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Golden-file checks of the table metadata produced by compaction.
+//!
+//! A digest of the current snapshot is compared with `testdata/golden/<name>.golden`. Run the
+//! tests with `UPDATE_GOLDEN=1` to rewrite the expectations after an intended change.
+
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::path::PathBuf;
+
+use iceberg::spec::{Datum, ManifestStatus};
+use iceberg::table::Table;
+
+/// Field id of the `file_path` column of position delete files, whose bounds are paths.
+const DELETE_FILE_PATH_FIELD_ID: i32 = 2147483546;
+
+/// Describes the current snapshot of `table` without anything that changes between runs, such
+/// as paths, ids, timestamps and file sizes.
+pub async fn table_digest(table: &Table) -> String {
+    let metadata = table.metadata();
+    let snapshot = metadata.current_snapshot().unwrap();
+    let mut digest = String::new();
+    writeln!(digest, "format-version: {:?}", metadata.format_version()).unwrap();
+    writeln!(digest, "snapshots: {}", metadata.snapshots().count()).unwrap();
+    writeln!(digest, "operation: {:?}", snapshot.summary().operation).unwrap();
+
+    let manifest_list = snapshot
+        .load_manifest_list(table.file_io(), metadata)
+        .await
+        .unwrap();
+    let mut manifests = vec![];
+    let mut files = vec![];
+    for manifest_file in manifest_list.entries() {
+        let manifest = manifest_file.load_manifest(table.file_io()).await.unwrap();
+        let (entries, _) = manifest.into_parts();
+        let status_count = |status: ManifestStatus| {
+            entries
+                .iter()
+                .filter(|entry| entry.status() == status)
+                .count()
+        };
+        manifests.push(format!(
+            "{:?} added={} existing={} deleted={}",
+            manifest_file.content,
+            status_count(ManifestStatus::Added),
+            status_count(ManifestStatus::Existing),
+            status_count(ManifestStatus::Deleted),
+        ));
+        for entry in entries.iter().filter(|entry| entry.is_alive()) {
+            let data_file = entry.data_file();
+            files.push(format!(
+                "{:?} records={} sequence-number={:?} value-counts={} null-value-counts={} \
+                 lower-bounds={} upper-bounds={}",
+                entry.content_type(),
+                data_file.record_count(),
+                entry.sequence_number(),
+                sorted_counts(data_file.value_counts()),
+                sorted_counts(data_file.null_value_counts()),
+                sorted_bounds(data_file.lower_bounds()),
+                sorted_bounds(data_file.upper_bounds()),
+            ));
+        }
+    }
+    manifests.sort();
+    writeln!(digest, "manifests:").unwrap();
+    for manifest in manifests {
+        writeln!(digest, "  {}", manifest).unwrap();
+    }
+    files.sort();
+    writeln!(digest, "live files:").unwrap();
+    for file in files {
+        writeln!(digest, "  {}", file).unwrap();
+    }
+    digest
+}
+
+fn sorted_counts(counts: &HashMap<i32, u64>) -> String {
+    let mut counts: Vec<_> = counts.iter().collect();
+    counts.sort();
+    let counts: Vec<String> = counts
+        .into_iter()
+        .map(|(field_id, count)| format!("{}:{}", field_id, count))
+        .collect();
+    format!("[{}]", counts.join(","))
+}
+
+fn sorted_bounds(bounds: &HashMap<i32, Datum>) -> String {
+    let mut bounds: Vec<_> = bounds
+        .iter()
+        .filter(|(field_id, _)| **field_id != DELETE_FILE_PATH_FIELD_ID)
+        .collect();
+    bounds.sort_by_key(|(field_id, _)| **field_id);
+    let bounds: Vec<String> = bounds
+        .into_iter()
+        .map(|(field_id, bound)| format!("{}:{}", field_id, bound))
+        .collect();
+    format!("[{}]", bounds.join(","))
+}
+
+/// Compares `actual` with the checked-in golden file `name`, or rewrites it when
+/// `UPDATE_GOLDEN` is set.
+pub fn assert_golden(name: &str, actual: &str) {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("testdata/golden")
+        .join(format!("{}.golden", name));
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, actual).unwrap();
+        return;
+    }
+    let expected = std::fs::read_to_string(&path).unwrap_or_else(|e| {
+        panic!(
+            "failed to read golden file {}: {}, run with UPDATE_GOLDEN=1 to create it",
+            path.display(),
+            e
+        )
+    });
+    assert_eq!(
+        expected,
+        actual,
+        "table metadata differs from {}, run with UPDATE_GOLDEN=1 if the change is intended",
+        path.display()
+    );
+}
@@ -0,0 +1,89 @@
+/*
+ * Copyright 2025 iceberg-compaction
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Rewrites of a table, read back from the replace snapshots in its metadata.
+//!
+//! Replace snapshots don't record who committed them, so these may also be rewrites made by
+//! other engines, e.g. Spark's `rewrite_data_files`, and only the standard summary properties
+//! are available.
+
+use std::collections::HashMap;
+
+use iceberg::spec::{Operation, TableMetadata};
+
+const ADDED_DATA_FILES: &str = "added-data-files";
+const DELETED_DATA_FILES: &str = "deleted-data-files";
+const REMOVED_DELETE_FILES: &str = "removed-delete-files";
+const ADDED_FILES_SIZE: &str = "added-files-size";
+const REMOVED_FILES_SIZE: &str = "removed-files-size";
+
+/// A replace snapshot of a table, committed by this crate or any other engine.
+#[derive(Debug, Clone)]
+pub struct ReplaceSnapshot {
+    pub snapshot_id: i64,
+    pub parent_snapshot_id: Option<i64>,
+    pub timestamp_ms: i64,
+    /// Every property of the snapshot summary besides the operation.
+    pub summary: HashMap<String, String>,
+}
+
+impl ReplaceSnapshot {
+    pub fn added_data_files(&self) -> Option<u64> {
+        self.summary_value(ADDED_DATA_FILES)
+    }
+
+    pub fn deleted_data_files(&self) -> Option<u64> {
+        self.summary_value(DELETED_DATA_FILES)
+    }
+
+    pub fn removed_delete_files(&self) -> Option<u64> {
+        self.summary_value(REMOVED_DELETE_FILES)
+    }
+
+    pub fn added_files_size(&self) -> Option<u64> {
+        self.summary_value(ADDED_FILES_SIZE)
+    }
+
+    pub fn removed_files_size(&self) -> Option<u64> {
+        self.summary_value(REMOVED_FILES_SIZE)
+    }
+
+    fn summary_value(&self, key: &str) -> Option<u64> {
+        self.summary.get(key)?.parse().ok()
+    }
+}
+
+/// Returns the replace snapshots among the ancestors of the current snapshot, newest first,
+/// whichever engine committed them.
+///
+/// Snapshots already expired from the metadata are not included.
+pub fn replace_snapshots(metadata: &TableMetadata) -> Vec<ReplaceSnapshot> {
+    let mut replace_snapshots = vec![];
+    let mut snapshot_id = metadata.current_snapshot_id();
+    while let Some(snapshot) = snapshot_id.and_then(|id| metadata.snapshot_by_id(id)) {
+        let summary = snapshot.summary();
+        if summary.operation == Operation::Replace {
+            replace_snapshots.push(ReplaceSnapshot {
+                snapshot_id: snapshot.snapshot_id(),
+                parent_snapshot_id: snapshot.parent_snapshot_id(),
+                timestamp_ms: snapshot.timestamp_ms(),
+                summary: summary.additional_properties.clone(),
+            });
+        }
+        snapshot_id = snapshot.parent_snapshot_id();
+    }
+    replace_snapshots
+}
@@ -14,36 +14,168 @@
  * limitations under the License.
  */
 
-use iceberg::spec::DataFile;
+use iceberg::spec::{DataFile, Datum, PrimitiveLiteral, TableMetadata};
 use iceberg::{Catalog, ErrorKind, TableIdent};
 use mixtrics::metrics::BoxedRegistry;
 use mixtrics::registry::noop::NoopMetricsRegistry;
 
-use crate::common::Metrics;
+use crate::common::sizing::{estimated_live_bytes, expected_output_files};
+use crate::common::{Metrics, MetricsLabelConfig};
+use crate::compaction::commit_hook::CommitHook;
+use crate::compaction::compatibility::CompatibilityReport;
 use crate::compaction::validator::CompactionValidator;
+use crate::config::validation::{check_config, check_schema_width};
+use crate::config::{DanglingDeletePolicy, UnsupportedContentTypePolicy};
+use crate::executor::datafusion::delete_file_rewriter::DeleteFileRewriter;
 use crate::executor::{
     create_compaction_executor, ExecutorType, InputFileScanTasks, RewriteFilesRequest,
     RewriteFilesResponse, RewriteFilesStat,
 };
+use crate::maintenance::{
+    collect_expired_files, find_dangling_delete_files, find_duplicate_data_files,
+    DanglingDeleteFiles, DuplicateDataFiles, ExpireSnapshotsOptions, ExpiredFiles,
+};
 use crate::CompactionError;
 use crate::Result;
 use crate::{CompactionConfig, CompactionExecutor};
+use futures::channel::mpsc::{unbounded, UnboundedSender};
+use futures::{FutureExt, Stream, StreamExt, TryStreamExt};
 use futures_async_stream::for_await;
+use iceberg::expr::Predicate;
 use iceberg::scan::FileScanTask;
 use iceberg::table::Table;
 use iceberg::transaction::Transaction;
 use iceberg::writer::file_writer::location_generator::DefaultLocationGenerator;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::sync::Arc;
 use std::time::Duration;
 
 use backon::ExponentialBuilder;
 use backon::Retryable;
 
+pub mod analyzer;
+pub mod commit_hook;
+pub mod compatibility;
+#[cfg(test)]
+mod golden;
+pub mod history;
+mod output_check;
+pub mod planner;
+pub mod policy;
+pub(crate) mod preflight;
+pub mod sql_filter;
+pub mod table_gate;
 mod validator;
 
 pub enum CompactionType {
     Full,
+    /// Bin-packs data files smaller than `CompactionConfig::small_file_threshold` towards the
+    /// target file size, leaving larger files untouched.
+    SmallFiles,
+    /// Rewrites the tiers of similarly sized data files holding at least
+    /// `CompactionConfig::size_tier_fan_in` files, see `policy::select_size_tiers`.
+    SizeTiered,
+    /// Only rewrites data files added by the snapshots after `from_snapshot_id` up to and
+    /// including `to_snapshot_id`, which must descend from `from_snapshot_id`.
+    Incremental {
+        from_snapshot_id: i64,
+        to_snapshot_id: i64,
+    },
+    /// Only rewrites data files added by snapshots at least `min_file_age` old, leaving fresh
+    /// files that writers may still compact themselves.
+    OlderThan {
+        min_file_age: Duration,
+    },
+    /// Bin-packs the data files smaller than `CompactionConfig::small_file_threshold` appended
+    /// by the last `snapshots` snapshots, with one set of file groups per partition. Cheaper
+    /// than `SmallFiles` for tables ingesting frequent micro-batches, as only the manifests
+    /// written since the oldest of those snapshots are read. Delete files are left in place and
+    /// the filter of the compaction is ignored.
+    RecentAppends {
+        snapshots: usize,
+    },
+    /// Only rewrites data files whose estimated fraction of deleted rows exceeds
+    /// `CompactionConfig::delete_ratio_threshold`, see `policy::select_by_delete_ratio`.
+    DeleteRatio,
+    /// Only rewrites the given data files, together with the delete files applying to them,
+    /// bypassing the selection policies. Every path must be a live data file of the table.
+    Files {
+        data_file_paths: Vec<String>,
+    },
+    /// Merges position and equality delete files without rewriting data files, dropping
+    /// position deletes of data files that are no longer live. Only unpartitioned tables are
+    /// supported, and only while all delete files were written under the current spec.
+    DeleteFiles,
+}
+
+/// Why a compaction finished without rewriting anything.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SkipReason {
+    /// The table has no snapshot yet.
+    NoSnapshot,
+    /// Compaction is disabled by the table properties.
+    Disabled(String),
+    /// No files were selected for rewriting.
+    NoFilesSelected,
+    /// The only selected data file has no deletes, so it would just be copied.
+    SingleFile,
+    /// Every selected data file already reaches the target file size and has no deletes.
+    FilesAtTargetSize,
+}
+
+impl std::fmt::Display for SkipReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SkipReason::NoSnapshot => write!(f, "the table has no snapshot"),
+            SkipReason::Disabled(reason) => write!(f, "compaction is disabled: {}", reason),
+            SkipReason::NoFilesSelected => write!(f, "no files were selected"),
+            SkipReason::SingleFile => {
+                write!(f, "the only selected data file has no deletes")
+            }
+            SkipReason::FilesAtTargetSize => write!(
+                f,
+                "every selected data file reaches the target file size and has no deletes"
+            ),
+        }
+    }
+}
+
+/// Why a live file is left in place by a compaction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RetainReason {
+    /// Skipped by `CompactionConfig::unsupported_content_type_policy`, or a delete file of
+    /// such a data file.
+    UnsupportedContentType,
+    /// Pruned by the filter of the compaction, or a delete file that may apply to such a file.
+    Filtered,
+    /// Not selected by the compaction type, or a delete file still applying to such a file.
+    NotSelected,
+    /// Beyond `CompactionConfig::max_rewrite_bytes`, left for later runs.
+    OverBudget,
+}
+
+impl std::fmt::Display for RetainReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RetainReason::UnsupportedContentType => write!(f, "unsupported content type"),
+            RetainReason::Filtered => write!(f, "pruned by the filter"),
+            RetainReason::NotSelected => write!(f, "not selected"),
+            RetainReason::OverBudget => write!(f, "beyond the rewrite budget"),
+        }
+    }
+}
+
+/// What a `CompactionPlan` does with a live file of the table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileDecision {
+    /// Rewritten by the file group at this index of `CompactionPlan::file_groups`.
+    Rewritten {
+        file_group: usize,
+    },
+    /// Removed by the commit without being rewritten, like delete files applying only to
+    /// rewritten data files.
+    Removed,
+    Retained(RetainReason),
 }
 
 /// Builder for creating Compaction instances with flexible configuration
@@ -52,10 +184,16 @@ pub struct CompactionBuilder {
     executor_type: ExecutorType,
     catalog: Option<Arc<dyn Catalog>>,
     registry: BoxedRegistry,
+    metrics_label_config: MetricsLabelConfig,
+    metrics: Option<Arc<Metrics>>,
+    tenant: Option<String>,
     table_ident: Option<TableIdent>,
     compaction_type: Option<CompactionType>,
+    filter: Option<Predicate>,
+    sql_filter: Option<String>,
     catalog_name: Option<String>,
     commit_retry_config: RewriteDataFilesCommitManagerRetryConfig,
+    commit_hooks: Vec<Arc<dyn CommitHook>>,
 }
 
 impl CompactionBuilder {
@@ -66,16 +204,22 @@ impl CompactionBuilder {
             executor_type: ExecutorType::DataFusion, // Default executor type
             catalog: None,
             registry: Box::new(NoopMetricsRegistry),
+            metrics_label_config: MetricsLabelConfig::default(),
+            metrics: None,
+            tenant: None,
             table_ident: None,
             compaction_type: None,
+            filter: None,
+            sql_filter: None,
             catalog_name: None,
             commit_retry_config: RewriteDataFilesCommitManagerRetryConfig::default(),
+            commit_hooks: vec![],
         }
     }
 
-    /// Set the compaction configuration
-    pub fn with_config(mut self, config: Arc<CompactionConfig>) -> Self {
-        self.config = Some(config);
+    /// Set the compaction configuration, either owned or already shared behind an `Arc`
+    pub fn with_config(mut self, config: impl Into<Arc<CompactionConfig>>) -> Self {
+        self.config = Some(config.into());
         self
     }
 
@@ -91,12 +235,38 @@ impl CompactionBuilder {
         self
     }
 
+    /// Set the catalog from an owned value, for callers that don't keep it behind an `Arc`
+    pub fn with_owned_catalog(mut self, catalog: impl Catalog + 'static) -> Self {
+        self.catalog = Some(Arc::new(catalog));
+        self
+    }
+
     /// Set the metrics registry (optional, defaults to NoopMetricsRegistry)
     pub fn with_registry(mut self, registry: BoxedRegistry) -> Self {
         self.registry = registry;
         self
     }
 
+    /// Set which labels are attached to metrics and how many distinct tables they may track
+    pub fn with_metrics_label_config(mut self, metrics_label_config: MetricsLabelConfig) -> Self {
+        self.metrics_label_config = metrics_label_config;
+        self
+    }
+
+    /// Report to metrics shared with other compactions, so that they are registered once and
+    /// the cardinality cap counts the tables of all of them. Takes precedence over
+    /// `with_registry` and `with_metrics_label_config`.
+    pub fn with_metrics(mut self, metrics: Arc<Metrics>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Set the value of the `tenant` metrics label, see `MetricsLabelConfig::tenant`
+    pub fn with_tenant(mut self, tenant: impl Into<String>) -> Self {
+        self.tenant = Some(tenant.into());
+        self
+    }
+
     pub fn with_table_ident(mut self, table_ident: TableIdent) -> Self {
         self.table_ident = Some(table_ident);
         self
@@ -107,6 +277,22 @@ impl CompactionBuilder {
         self
     }
 
+    /// Only rewrites data files whose metrics may match `filter`. Matching files are rewritten
+    /// whole, and delete files are kept since they may still apply to pruned files. Ignored by
+    /// `CompactionType::DeleteFiles` and `CompactionType::RecentAppends`.
+    pub fn with_filter(mut self, filter: Predicate) -> Self {
+        self.filter = Some(filter);
+        self
+    }
+
+    /// Like `with_filter`, but takes a SQL expression over the table's columns, e.g.
+    /// `event_date >= '2025-01-01'`. It is parsed against the current schema when building, and
+    /// combined with a filter set by `with_filter` if both are given.
+    pub fn with_sql_filter(mut self, sql_filter: impl Into<String>) -> Self {
+        self.sql_filter = Some(sql_filter.into());
+        self
+    }
+
     pub fn with_catalog_name(mut self, catalog_name: String) -> Self {
         self.catalog_name = Some(catalog_name);
         self
@@ -120,6 +306,12 @@ impl CompactionBuilder {
         self
     }
 
+    /// Registers a hook called around every rewrite commit, after the hooks registered before it.
+    pub fn with_commit_hook(mut self, commit_hook: Arc<dyn CommitHook>) -> Self {
+        self.commit_hooks.push(commit_hook);
+        self
+    }
+
     /// Build the Compaction instance
     pub async fn build(self) -> Result<Compaction> {
         let config = self.config.ok_or_else(|| {
@@ -142,9 +334,25 @@ impl CompactionBuilder {
             ));
         }
 
+        let mut filter = self.filter;
+        if let Some(sql_filter) = self.sql_filter {
+            let table = catalog.load_table(&table_ident).await?;
+            let sql_predicate =
+                sql_filter::parse_sql_filter(&sql_filter, table.metadata().current_schema())?;
+            filter = Some(match filter {
+                Some(filter) => filter.and(sql_predicate),
+                None => sql_predicate,
+            });
+        }
+
         let executor = create_compaction_executor(self.executor_type);
 
-        let metrics = Arc::new(Metrics::new(self.registry));
+        let metrics = self.metrics.unwrap_or_else(|| {
+            Arc::new(Metrics::new_with_label_config(
+                self.registry,
+                self.metrics_label_config,
+            ))
+        });
 
         let catalog_name = self.catalog_name.unwrap_or_default();
 
@@ -157,8 +365,13 @@ impl CompactionBuilder {
             metrics,
             table_ident,
             compaction_type,
+            filter,
             catalog_name,
             commit_retry_config,
+            commit_hooks: self.commit_hooks,
+            tenant: self.tenant,
+            #[cfg(test)]
+            now_ms: None,
         })
     }
 }
@@ -177,9 +390,45 @@ pub struct Compaction {
     pub metrics: Arc<Metrics>,
     pub table_ident: TableIdent,
     pub compaction_type: CompactionType,
+    pub filter: Option<Predicate>,
     pub catalog_name: String,
 
     pub commit_retry_config: RewriteDataFilesCommitManagerRetryConfig,
+    pub commit_hooks: Vec<Arc<dyn CommitHook>>,
+    /// Value of the `tenant` metrics label.
+    pub tenant: Option<String>,
+    /// Planning time in milliseconds since the Unix epoch, used instead of the wall clock.
+    #[cfg(test)]
+    now_ms: Option<i64>,
+}
+
+/// Progress events emitted by `Compaction::compact_stream`.
+#[derive(Debug, Clone)]
+pub enum CompactionEvent {
+    /// Input files have been selected for rewriting.
+    Planned {
+        data_files_count: usize,
+        position_delete_files_count: usize,
+        equality_delete_files_count: usize,
+        file_groups_count: usize,
+    },
+    /// The executor finished rewriting a file group.
+    Rewritten(RewriteFilesStat),
+    /// The rewrite was committed as a new snapshot.
+    Committed { snapshot_id: Option<i64> },
+    /// The committed output passed validation.
+    Validated,
+    /// Final report, always the last event of a successful compaction.
+    Completed(RewriteFilesStat),
+}
+
+type CompactionEventSender = UnboundedSender<Result<CompactionEvent>>;
+
+fn emit_event(events: Option<&CompactionEventSender>, event: CompactionEvent) {
+    if let Some(events) = events {
+        // The receiver may have been dropped by a consumer that lost interest.
+        let _ = events.unbounded_send(Ok(event));
+    }
 }
 
 struct CompactionResult {
@@ -188,6 +437,122 @@ struct CompactionResult {
     compaction_validator: Option<CompactionValidator>,
 }
 
+impl CompactionResult {
+    fn skipped(reason: SkipReason) -> Self {
+        Self {
+            stats: RewriteFilesStat::skipped(reason),
+            compaction_validator: None,
+        }
+    }
+}
+
+/// Files selected for rewriting and how they are grouped.
+struct TablePlan {
+    tasks: InputFileScanTasks,
+    /// Files of the table that must stay whatever is rewritten, with why.
+    retained_files: HashMap<String, RetainReason>,
+    file_groups: Vec<HashSet<String>>,
+    data_files: Vec<DataFile>,
+    delete_files: Vec<DataFile>,
+}
+
+/// Live files of a table, and those of them that must stay whatever is selected for rewriting.
+struct ScannedFiles {
+    retained_files: HashMap<String, RetainReason>,
+    data_files: Vec<DataFile>,
+    delete_files: Vec<DataFile>,
+}
+
+/// What a compaction would rewrite, returned by `Compaction::plan` without touching the table.
+#[derive(Debug, Clone, Default)]
+pub struct CompactionPlan {
+    /// Snapshot the plan was made against.
+    pub snapshot_id: Option<i64>,
+    /// Why nothing would be rewritten, in which case the plan is otherwise empty.
+    pub skipped: Option<SkipReason>,
+    pub file_groups: Vec<FileGroupPlan>,
+    pub position_delete_file_paths: Vec<String>,
+    pub equality_delete_file_paths: Vec<String>,
+    /// Bytes of the data and delete files read by the rewrite.
+    pub estimated_read_bytes: u64,
+    /// Bytes of the data files rewritten, an upper bound since deleted rows are not written.
+    pub estimated_write_bytes: u64,
+    pub expected_output_files_count: usize,
+    /// What happens to each live file of the table, by path.
+    pub file_decisions: BTreeMap<String, FileDecision>,
+}
+
+/// A file group of a `CompactionPlan`, rewritten by a single executor request.
+#[derive(Debug, Clone)]
+pub struct FileGroupPlan {
+    pub data_file_paths: Vec<String>,
+    pub data_bytes: u64,
+    pub expected_output_files_count: usize,
+}
+
+impl CompactionPlan {
+    fn skipped(snapshot_id: Option<i64>, reason: SkipReason) -> Self {
+        Self {
+            snapshot_id,
+            skipped: Some(reason),
+            ..Default::default()
+        }
+    }
+
+    pub fn data_files_count(&self) -> usize {
+        self.file_groups
+            .iter()
+            .map(|group| group.data_file_paths.len())
+            .sum()
+    }
+
+    /// Renders the plan as a tree of file groups with the files they rewrite, followed by the
+    /// files removed or retained and why, for logs and command line output.
+    pub fn explain(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl std::fmt::Display for CompactionPlan {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.snapshot_id {
+            Some(snapshot_id) => writeln!(f, "CompactionPlan of snapshot {}", snapshot_id)?,
+            None => writeln!(f, "CompactionPlan")?,
+        }
+        if let Some(reason) = &self.skipped {
+            return writeln!(f, "  skipped: {}", reason);
+        }
+        writeln!(
+            f,
+            "  estimated read bytes: {}, estimated write bytes: {}, expected output files: {}",
+            self.estimated_read_bytes, self.estimated_write_bytes, self.expected_output_files_count
+        )?;
+        for (file_group, group) in self.file_groups.iter().enumerate() {
+            writeln!(
+                f,
+                "  file group {}: {} data files, {} bytes, {} expected output files",
+                file_group,
+                group.data_file_paths.len(),
+                group.data_bytes,
+                group.expected_output_files_count
+            )?;
+            let mut data_file_paths: Vec<&String> = group.data_file_paths.iter().collect();
+            data_file_paths.sort();
+            for path in data_file_paths {
+                writeln!(f, "    rewrite {}", path)?;
+            }
+        }
+        for (path, decision) in &self.file_decisions {
+            match decision {
+                FileDecision::Rewritten { .. } => {}
+                FileDecision::Removed => writeln!(f, "  remove {}", path)?,
+                FileDecision::Retained(reason) => writeln!(f, "  retain {} ({})", path, reason)?,
+            }
+        }
+        Ok(())
+    }
+}
+
 impl Compaction {
     /// Create a new CompactionBuilder for flexible configuration
     pub fn builder() -> CompactionBuilder {
@@ -195,11 +560,163 @@ impl Compaction {
     }
 
     pub async fn compact(&self) -> Result<RewriteFilesStat> {
+        self.compact_with_events(&self.compaction_type, None).await
+    }
+
+    /// Rewrites again the data files of the file group whose failure `error` reports, as
+    /// returned by `compact` when rewriting a file group fails, and commits them on their own.
+    /// File groups that were not run because of the failure are left for the next compaction.
+    ///
+    /// The files are rewritten like a `CompactionType::Files` compaction, which fails if one of
+    /// them is no longer live. Fails with `InvalidRequest` if `error` reports no file group.
+    pub async fn retry_failed(&self, error: &CompactionError) -> Result<RewriteFilesStat> {
+        let CompactionError::FileGroup {
+            data_file_paths, ..
+        } = error
+        else {
+            return Err(CompactionError::InvalidRequest(format!(
+                "no failed file group to retry in: {}",
+                error
+            )));
+        };
+        let compaction_type = CompactionType::Files {
+            data_file_paths: data_file_paths.clone(),
+        };
+        self.compact_with_events(&compaction_type, None).await
+    }
+
+    /// Selects and groups the files `compaction_type` would rewrite, honoring the filter and
+    /// config of this compaction, without reading or writing any data. Only position delete files
+    /// are read, and only if `CompactionConfig::dangling_delete_policy` looks for dangling ones.
+    pub async fn plan(&self, compaction_type: &CompactionType) -> Result<CompactionPlan> {
+        let table = match self.load_table_to_compact().await? {
+            Ok(table) => table,
+            Err(reason) => return Ok(CompactionPlan::skipped(None, reason)),
+        };
+        let snapshot_id = table.metadata().current_snapshot_id();
+        let TablePlan {
+            tasks,
+            retained_files,
+            file_groups,
+            data_files,
+            delete_files,
+        } = match self.plan_table_files(&table, compaction_type).await? {
+            Ok(plan) => plan,
+            Err(reason) => return Ok(CompactionPlan::skipped(snapshot_id, reason)),
+        };
+
+        let mut file_decisions = BTreeMap::new();
+        for (file_group, group) in file_groups.iter().enumerate() {
+            for path in group {
+                file_decisions.insert(path.clone(), FileDecision::Rewritten { file_group });
+            }
+        }
+        for file in data_files.iter().chain(&delete_files) {
+            let decision = match retained_files.get(file.file_path()) {
+                Some(reason) => FileDecision::Retained(*reason),
+                None => FileDecision::Removed,
+            };
+            file_decisions
+                .entry(file.file_path().to_owned())
+                .or_insert(decision);
+        }
+
+        let file_groups: Vec<FileGroupPlan> = file_groups
+            .into_iter()
+            .map(|group| {
+                let data_bytes = tasks
+                    .data_files
+                    .iter()
+                    .filter(|task| group.contains(&task.data_file_path))
+                    .map(|task| task.file_size_in_bytes)
+                    .sum();
+                let live_bytes = estimated_live_bytes(
+                    tasks
+                        .data_files
+                        .iter()
+                        .filter(|task| group.contains(&task.data_file_path)),
+                );
+                FileGroupPlan {
+                    data_file_paths: group.into_iter().collect(),
+                    data_bytes,
+                    expected_output_files_count: expected_output_files(
+                        live_bytes,
+                        self.config.target_file_size,
+                    ),
+                }
+            })
+            .collect();
+        let estimated_write_bytes = file_groups.iter().map(|group| group.data_bytes).sum();
+        let delete_bytes: u64 = tasks
+            .position_delete_files
+            .iter()
+            .chain(&tasks.equality_delete_files)
+            .map(|task| task.file_size_in_bytes)
+            .sum();
+        Ok(CompactionPlan {
+            snapshot_id,
+            skipped: None,
+            expected_output_files_count: file_groups
+                .iter()
+                .map(|group| group.expected_output_files_count)
+                .sum(),
+            file_groups,
+            position_delete_file_paths: tasks
+                .position_delete_files
+                .into_iter()
+                .map(|task| task.data_file_path)
+                .collect(),
+            equality_delete_file_paths: tasks
+                .equality_delete_files
+                .into_iter()
+                .map(|task| task.data_file_path)
+                .collect(),
+            estimated_read_bytes: estimated_write_bytes + delete_bytes,
+            estimated_write_bytes,
+            file_decisions,
+        })
+    }
+
+    /// Runs the compaction and streams its progress, ending with `CompactionEvent::Completed`
+    /// on success or a single error item on failure.
+    pub fn compact_stream(&self) -> impl Stream<Item = Result<CompactionEvent>> + '_ {
+        let (events_tx, events_rx) = unbounded();
+        // All items flow through the channel so they stay ordered; the driver only runs the
+        // compaction and closes the channel when it finishes.
+        let driver = async move {
+            if let Err(e) = self
+                .compact_with_events(&self.compaction_type, Some(&events_tx))
+                .await
+            {
+                let _ = events_tx.unbounded_send(Err(e));
+            }
+        }
+        .into_stream()
+        .filter_map(|()| futures::future::ready(None));
+
+        futures::stream::select(events_rx, driver)
+    }
+
+    async fn compact_with_events(
+        &self,
+        compaction_type: &CompactionType,
+        events: Option<&CompactionEventSender>,
+    ) -> Result<RewriteFilesStat> {
         let CompactionResult {
             stats,
             compaction_validator,
-        } = match self.compaction_type {
-            CompactionType::Full => self.full_compact().await?,
+        } = match compaction_type {
+            CompactionType::Full
+            | CompactionType::SmallFiles
+            | CompactionType::SizeTiered
+            | CompactionType::Incremental { .. }
+            | CompactionType::OlderThan { .. }
+            | CompactionType::RecentAppends { .. }
+            | CompactionType::DeleteRatio
+            | CompactionType::Files { .. } => {
+                self.compact_table_files(compaction_type, events).await?
+            }
+            CompactionType::DeleteFiles => self.compact_delete_files(events).await?,
         };
 
         // validate
@@ -211,27 +728,346 @@ impl Compaction {
                 "Compaction validation completed successfully for table '{}'",
                 self.table_ident
             );
+            emit_event(events, CompactionEvent::Validated);
         }
 
+        if let Some(reason) = &stats.skipped {
+            tracing::info!(
+                "Skipping compaction for table '{}': {}",
+                self.table_ident,
+                reason
+            );
+        }
+        emit_event(events, CompactionEvent::Completed(stats.clone()));
         Ok(stats)
     }
 
-    async fn full_compact(&self) -> Result<CompactionResult> {
-        let table_label: std::borrow::Cow<'static, str> = self.table_ident.to_string().into();
-        let catalog_name_label: std::borrow::Cow<'static, str> = self.catalog_name.clone().into();
-        let label_vec: [std::borrow::Cow<'static, str>; 2] = [catalog_name_label, table_label];
-
-        let now = std::time::Instant::now();
-
+    /// Loads the table to compact, or returns why there is nothing to compact.
+    async fn load_table_to_compact(&self) -> Result<std::result::Result<Table, SkipReason>> {
         let table = self.catalog.load_table(&self.table_ident).await?;
+        if let Some(reason) =
+            table_gate::compaction_disabled_reason(table.metadata().properties(), now_ms()?)?
+        {
+            return Ok(Err(SkipReason::Disabled(reason)));
+        }
+        check_compatibility(&self.table_ident, table.metadata())?;
         if table.metadata().current_snapshot().is_none() {
-            return Ok(CompactionResult {
-                stats: RewriteFilesStat::default(),
-                compaction_validator: None,
-            });
+            return Ok(Err(SkipReason::NoSnapshot));
+        }
+        Ok(Ok(table))
+    }
+
+    /// Milliseconds since the Unix epoch at which files are planned.
+    fn planning_time_ms(&self) -> Result<i64> {
+        #[cfg(test)]
+        if let Some(now_ms) = self.now_ms {
+            return Ok(now_ms);
         }
+        now_ms()
+    }
+
+    /// Selects the files of `table` to rewrite for `compaction_type` and groups them, or returns
+    /// why nothing would be rewritten.
+    async fn plan_table_files(
+        &self,
+        table: &Table,
+        compaction_type: &CompactionType,
+    ) -> Result<std::result::Result<TablePlan, SkipReason>> {
+        // Files of different size tiers or partitions are planned into separate file groups.
+        let mut separate_groups = None;
+        let (scanned, (input_file_scan_tasks, retained_file_paths)) = match compaction_type {
+            CompactionType::Full => {
+                let (input_file_scan_tasks, scanned) = self.scan_table_files(table).await?;
+                (scanned, (input_file_scan_tasks, HashSet::new()))
+            }
+            CompactionType::DeleteFiles => {
+                return Err(CompactionError::Execution(
+                    "DeleteFiles compaction doesn't plan data files".to_owned(),
+                ));
+            }
+            CompactionType::SmallFiles => {
+                let (input_file_scan_tasks, scanned) = self.scan_table_files(table).await?;
+                let selected = select_tasks(input_file_scan_tasks, |task| {
+                    task.file_size_in_bytes < self.config.small_file_threshold
+                });
+                (scanned, selected)
+            }
+            CompactionType::SizeTiered => {
+                let (input_file_scan_tasks, scanned) = self.scan_table_files(table).await?;
+                let tiers = policy::select_size_tier_groups(
+                    &input_file_scan_tasks.data_files,
+                    self.config.target_file_size,
+                    self.config.size_tier_ratio,
+                    self.config.size_tier_fan_in,
+                );
+                let selected_file_paths: HashSet<String> =
+                    tiers.iter().flatten().cloned().collect();
+                separate_groups = Some(tiers);
+                let selected = select_tasks(input_file_scan_tasks, |task| {
+                    selected_file_paths.contains(&task.data_file_path)
+                });
+                (scanned, selected)
+            }
+            CompactionType::Incremental {
+                from_snapshot_id,
+                to_snapshot_id,
+            } => {
+                let added_file_paths =
+                    get_data_files_added_between(table, *from_snapshot_id, *to_snapshot_id).await?;
+                let (input_file_scan_tasks, scanned) = self.scan_table_files(table).await?;
+                let selected = select_tasks(input_file_scan_tasks, |task| {
+                    added_file_paths.contains(&task.data_file_path)
+                });
+                (scanned, selected)
+            }
+            CompactionType::OlderThan { min_file_age } => {
+                let cutoff_ms = cutoff_ms(self.planning_time_ms()?, *min_file_age)?;
+                let old_file_paths = get_data_files_added_before(table, cutoff_ms).await?;
+                let (input_file_scan_tasks, scanned) = self.scan_table_files(table).await?;
+                let selected = select_tasks(input_file_scan_tasks, |task| {
+                    old_file_paths.contains(&task.data_file_path)
+                });
+                (scanned, selected)
+            }
+            CompactionType::RecentAppends { snapshots } => {
+                // Planned from the manifests of the recent appends only, without a table scan.
+                let (tasks, partitions, data_files) =
+                    plan_recent_appends(table, *snapshots, self.config.small_file_threshold)
+                        .await?;
+                // Delete files are kept, as data files added before the planned ones may still
+                // need them. They no longer apply to the output, which takes a newer sequence
+                // number.
+                return Ok(self.group_planned_tasks(
+                    tasks,
+                    HashMap::new(),
+                    Some(partitions),
+                    false,
+                    data_files,
+                    vec![],
+                ));
+            }
+            CompactionType::DeleteRatio => {
+                let (input_file_scan_tasks, scanned) = self.scan_table_files(table).await?;
+                let selected_file_paths = policy::select_by_delete_ratio(
+                    &input_file_scan_tasks.data_files,
+                    self.config.delete_ratio_threshold,
+                );
+                let selected = select_tasks(input_file_scan_tasks, |task| {
+                    selected_file_paths.contains(&task.data_file_path)
+                });
+                (scanned, selected)
+            }
+            CompactionType::Files { data_file_paths } => {
+                let (input_file_scan_tasks, scanned) = self.scan_table_files(table).await?;
+                let live_file_paths: HashSet<&str> = input_file_scan_tasks
+                    .data_files
+                    .iter()
+                    .map(|task| task.data_file_path.as_str())
+                    .collect();
+                if let Some(missing_path) = data_file_paths
+                    .iter()
+                    .find(|path| !live_file_paths.contains(path.as_str()))
+                {
+                    return Err(CompactionError::Execution(format!(
+                        "Data file '{}' is not live in table '{}'",
+                        missing_path, self.table_ident
+                    )));
+                }
+                let requested_file_paths: HashSet<&str> =
+                    data_file_paths.iter().map(String::as_str).collect();
+                let selected = select_tasks(input_file_scan_tasks, |task| {
+                    requested_file_paths.contains(task.data_file_path.as_str())
+                });
+                (scanned, selected)
+            }
+        };
+        let ScannedFiles {
+            mut retained_files,
+            data_files,
+            delete_files,
+        } = scanned;
+        retain_files(
+            &mut retained_files,
+            retained_file_paths,
+            RetainReason::NotSelected,
+        );
+        Ok(self.group_planned_tasks(
+            input_file_scan_tasks,
+            retained_files,
+            separate_groups,
+            matches!(compaction_type, CompactionType::Full),
+            data_files,
+            delete_files,
+        ))
+    }
+
+    /// Loads the live files of `table` and plans scan tasks of its data files matching the
+    /// filter, without dangling position deletes. The files that must stay whatever is selected,
+    /// such as those pruned by the filter, are returned along with the live files.
+    async fn scan_table_files(&self, table: &Table) -> Result<(InputFileScanTasks, ScannedFiles)> {
         let (data_files, delete_files) = get_old_files_from_table(table.clone()).await?;
-        let mut input_file_scan_tasks = Some(get_tasks_from_table(table.clone()).await?);
+        let (mut input_file_scan_tasks, unsupported_file_paths) = get_tasks_from_table_with_filter(
+            table.clone(),
+            self.config.unsupported_content_type_policy,
+            self.filter.as_ref(),
+        )
+        .await?;
+        let mut retained_files = HashMap::new();
+        retain_files(
+            &mut retained_files,
+            unsupported_file_paths,
+            RetainReason::UnsupportedContentType,
+        );
+        let live_data_files: HashSet<&str> = data_files.iter().map(|f| f.file_path()).collect();
+        let dangling_delete_files = self
+            .check_dangling_position_deletes(
+                table,
+                &live_data_files,
+                &input_file_scan_tasks.position_delete_files,
+            )
+            .await?;
+        remove_delete_files(&mut input_file_scan_tasks, &dangling_delete_files);
+        if self.filter.is_some() {
+            // Files pruned by the filter are not planned but must stay, as must the delete files
+            // that may still apply to them.
+            let planned_file_paths: HashSet<&str> = input_file_scan_tasks
+                .data_files
+                .iter()
+                .map(|task| task.data_file_path.as_str())
+                .collect();
+            retain_files(
+                &mut retained_files,
+                data_files
+                    .iter()
+                    .map(|f| f.file_path())
+                    .filter(|path| !planned_file_paths.contains(path))
+                    .map(str::to_owned),
+                RetainReason::Filtered,
+            );
+            retain_files(
+                &mut retained_files,
+                delete_files
+                    .iter()
+                    .map(|f| f.file_path())
+                    .filter(|path| !dangling_delete_files.contains(*path))
+                    .map(str::to_owned),
+                RetainReason::Filtered,
+            );
+        }
+        Ok((
+            input_file_scan_tasks,
+            ScannedFiles {
+                retained_files,
+                data_files,
+                delete_files,
+            },
+        ))
+    }
+
+    /// Defers the planned tasks beyond the rewrite budget and groups the others, or returns why
+    /// nothing would be rewritten. Files of each of `separate_groups`, if set, are grouped apart.
+    fn group_planned_tasks(
+        &self,
+        input_file_scan_tasks: InputFileScanTasks,
+        mut retained_files: HashMap<String, RetainReason>,
+        separate_groups: Option<Vec<HashSet<String>>>,
+        full: bool,
+        data_files: Vec<DataFile>,
+        delete_files: Vec<DataFile>,
+    ) -> std::result::Result<TablePlan, SkipReason> {
+        // Files beyond the rewrite budget are left for later runs.
+        let within_budget = planner::limit_rewrite_bytes(
+            &input_file_scan_tasks.data_files,
+            self.config.max_rewrite_bytes,
+        );
+        let (input_file_scan_tasks, deferred_file_paths) =
+            select_tasks(input_file_scan_tasks, |task| {
+                within_budget.contains(&task.data_file_path)
+            });
+        retain_files(
+            &mut retained_files,
+            deferred_file_paths,
+            RetainReason::OverBudget,
+        );
+        if let Some(reason) = no_op_reason(&input_file_scan_tasks, &self.config, full) {
+            return Err(reason);
+        }
+        let file_groups = match &separate_groups {
+            Some(groups) => groups
+                .iter()
+                .flat_map(|group| {
+                    planner::plan_file_groups(
+                        input_file_scan_tasks
+                            .data_files
+                            .iter()
+                            .filter(|task| group.contains(&task.data_file_path)),
+                        self.config.max_group_size_bytes,
+                        self.config.max_files_per_group,
+                        self.config.target_file_size,
+                    )
+                })
+                .collect(),
+            None => planner::plan_file_groups(
+                &input_file_scan_tasks.data_files,
+                self.config.max_group_size_bytes,
+                self.config.max_files_per_group,
+                self.config.target_file_size,
+            ),
+        };
+        Ok(TablePlan {
+            tasks: input_file_scan_tasks,
+            retained_files,
+            file_groups,
+            data_files,
+            delete_files,
+        })
+    }
+
+    async fn compact_table_files(
+        &self,
+        compaction_type: &CompactionType,
+        events: Option<&CompactionEventSender>,
+    ) -> Result<CompactionResult> {
+        let label_vec = self.metrics.label_values(
+            &self.catalog_name,
+            &self.table_ident,
+            self.tenant.as_deref(),
+        );
+
+        let now = std::time::Instant::now();
+
+        let table = match self.load_table_to_compact().await? {
+            Ok(table) => table,
+            Err(reason) => return Ok(CompactionResult::skipped(reason)),
+        };
+        let TablePlan {
+            tasks: input_file_scan_tasks,
+            retained_files,
+            file_groups,
+            data_files,
+            delete_files,
+        } = match self.plan_table_files(&table, compaction_type).await? {
+            Ok(plan) => plan,
+            Err(reason) => return Ok(CompactionResult::skipped(reason)),
+        };
+        emit_event(
+            events,
+            CompactionEvent::Planned {
+                data_files_count: input_file_scan_tasks.data_files.len(),
+                position_delete_files_count: input_file_scan_tasks.position_delete_files.len(),
+                equality_delete_files_count: input_file_scan_tasks.equality_delete_files.len(),
+                file_groups_count: file_groups.len(),
+            },
+        );
+        let max_commits = if self.config.partial_progress_enabled {
+            self.config.partial_progress_max_commits
+        } else {
+            1
+        };
+        let commits = split_tasks_for_commits(
+            input_file_scan_tasks,
+            planner::batch_file_groups(file_groups, max_commits),
+        );
 
         let file_io = table.file_io().clone();
         let schema = table.metadata().current_schema();
@@ -239,31 +1075,6 @@ impl Compaction {
         // TODO: support check partition spec
         let default_location_generator =
             DefaultLocationGenerator::new(table.metadata().clone()).unwrap();
-        let rewrite_files_request = RewriteFilesRequest {
-            file_io: file_io.clone(),
-            schema: schema.clone(),
-            input_file_scan_tasks: if self.config.enable_validate_compaction {
-                input_file_scan_tasks.clone().unwrap()
-            } else {
-                input_file_scan_tasks.take().unwrap()
-            },
-            config: self.config.clone(),
-            dir_path: default_location_generator.dir_path,
-            partition_spec: table.metadata().default_partition_spec().clone(),
-        };
-        let RewriteFilesResponse {
-            data_files: mut output_data_files,
-            stat,
-        } = match self.executor.rewrite_files(rewrite_files_request).await {
-            Ok(response) => response,
-            Err(e) => {
-                self.metrics
-                    .compaction_executor_error_counter
-                    .counter(&label_vec)
-                    .increase(1);
-                return Err(e);
-            }
-        };
 
         let consistency_params = CommitConsistencyParams {
             starting_snapshot_id: table.metadata().current_snapshot_id().unwrap(),
@@ -278,56 +1089,145 @@ impl Compaction {
             self.catalog_name.clone(),
             self.metrics.clone(),
             consistency_params,
-        );
+        )
+        .with_commit_hooks(self.commit_hooks.clone())
+        .with_tenant(self.tenant.clone());
 
-        let commit_now = std::time::Instant::now();
-        let output_data_files = if self.config.enable_validate_compaction {
-            output_data_files.clone()
-        } else {
-            std::mem::take(&mut output_data_files)
-        };
-        let committed_table = commit_manager
-            .rewrite_files(
-                output_data_files.clone(),
-                data_files.into_iter().chain(delete_files.into_iter()),
-            )
-            .await?;
+        let mut stats = RewriteFilesStat::default();
+        let mut committed_table = table.clone();
+        let mut removed_file_paths = HashSet::new();
+        let mut validated_tasks = vec![];
+        let mut validated_output_data_files = vec![];
+        let mut group_id = 0;
+        // Each commit is made on its own, so a failure only loses the commit in flight.
+        for (group_tasks_list, retained_file_paths) in commits {
+            let mut commit_output_data_files = vec![];
+            for group_tasks in group_tasks_list {
+                if self.config.enable_validate_compaction {
+                    validated_tasks.push(group_tasks.clone());
+                }
+                let group_data_file_paths: Vec<String> = group_tasks
+                    .data_files
+                    .iter()
+                    .map(|task| task.data_file_path.clone())
+                    .collect();
+                let rewrite_files_request = RewriteFilesRequest::builder()
+                    .with_file_io(file_io.clone())
+                    .with_schema(schema.clone())
+                    .with_input_file_scan_tasks(group_tasks)
+                    .with_config(self.config.clone())
+                    .with_dir_path(default_location_generator.dir_path.clone())
+                    .with_partition_spec(table.metadata().default_partition_spec().clone())
+                    .with_sort_order(
+                        self.config
+                            .enable_sort_order
+                            .then(|| table.metadata().default_sort_order().clone())
+                            .filter(|sort_order| !sort_order.is_unsorted()),
+                    )
+                    .build()?;
+                let RewriteFilesResponse {
+                    data_files: output_data_files,
+                    stat,
+                } = match self.executor.rewrite_files(rewrite_files_request).await {
+                    Ok(response) => response,
+                    Err(e) => {
+                        self.metrics
+                            .compaction_executor_error_counter
+                            .counter(&label_vec)
+                            .increase(1);
+                        tracing::error!(
+                            "Rewriting file group {} of table '{}' failed, data files: {:?}",
+                            group_id,
+                            self.table_ident,
+                            group_data_file_paths
+                        );
+                        return Err(CompactionError::FileGroup {
+                            group_id,
+                            data_file_paths: group_data_file_paths,
+                            source: Box::new(e),
+                        });
+                    }
+                };
+                group_id += 1;
+                emit_event(events, CompactionEvent::Rewritten(stat.clone()));
+                commit_output_data_files.extend(output_data_files);
 
-        self.metrics
-            .compaction_commit_duration
-            .histogram(&label_vec)
-            .record(commit_now.elapsed().as_secs_f64());
+                self.metrics
+                    .compaction_rewritten_bytes
+                    .counter(&label_vec)
+                    .increase(stat.rewritten_bytes);
 
-        self.metrics
-            .compaction_duration
-            .histogram(&label_vec)
-            .record(now.elapsed().as_secs_f64());
+                self.metrics
+                    .compaction_rewritten_files_count
+                    .counter(&label_vec)
+                    .increase(stat.rewritten_files_count as u64);
 
-        self.metrics
-            .compaction_rewritten_bytes
-            .counter(&label_vec)
-            .increase(stat.rewritten_bytes);
+                self.metrics
+                    .compaction_added_files_count
+                    .counter(&label_vec)
+                    .increase(stat.added_files_count as u64);
 
-        self.metrics
-            .compaction_rewritten_files_count
-            .counter(&label_vec)
-            .increase(stat.rewritten_files_count as u64);
+                self.metrics
+                    .compaction_failed_data_files_count
+                    .counter(&label_vec)
+                    .increase(stat.failed_data_files_count as u64);
 
-        self.metrics
-            .compaction_added_files_count
-            .counter(&label_vec)
-            .increase(stat.added_files_count as u64);
+                stats.rewritten_files_count += stat.rewritten_files_count;
+                stats.added_files_count += stat.added_files_count;
+                stats.rewritten_bytes += stat.rewritten_bytes;
+                stats.failed_data_files_count += stat.failed_data_files_count;
+            }
+
+            let files_to_remove: Vec<DataFile> = data_files
+                .iter()
+                .chain(delete_files.iter())
+                .filter(|f| {
+                    !retained_files.contains_key(f.file_path())
+                        && !retained_file_paths.contains(f.file_path())
+                        && !removed_file_paths.contains(f.file_path())
+                })
+                .cloned()
+                .collect();
+            removed_file_paths.extend(files_to_remove.iter().map(|f| f.file_path().to_owned()));
+            if self.config.enable_validate_compaction {
+                validated_output_data_files.extend(commit_output_data_files.iter().cloned());
+            }
+            if self.config.verify_output_files {
+                output_check::verify_data_files(
+                    &file_io,
+                    &commit_output_data_files,
+                    self.config.batch_parallelism,
+                )
+                .await?;
+            }
+
+            let commit_now = std::time::Instant::now();
+            committed_table = commit_manager
+                .rewrite_files(commit_output_data_files, files_to_remove)
+                .await?;
+            emit_event(
+                events,
+                CompactionEvent::Committed {
+                    snapshot_id: committed_table.metadata().current_snapshot_id(),
+                },
+            );
+
+            self.metrics
+                .compaction_commit_duration
+                .histogram(&label_vec)
+                .record(commit_now.elapsed().as_secs_f64());
+        }
 
         self.metrics
-            .compaction_failed_data_files_count
-            .counter(&label_vec)
-            .increase(stat.failed_data_files_count as u64);
+            .compaction_duration
+            .histogram(&label_vec)
+            .record(now.elapsed().as_secs_f64());
 
         let compaction_validator = if self.config.enable_validate_compaction {
             Some(
                 CompactionValidator::new(
-                    input_file_scan_tasks.unwrap(),
-                    output_data_files,
+                    merge_tasks(validated_tasks),
+                    validated_output_data_files,
                     self.config.clone(),
                     schema.clone(),
                     table.metadata().current_schema().clone(),
@@ -341,41 +1241,563 @@ impl Compaction {
         };
 
         Ok(CompactionResult {
-            stats: RewriteFilesStat {
-                rewritten_files_count: stat.rewritten_files_count,
-                added_files_count: stat.added_files_count,
-                rewritten_bytes: stat.rewritten_bytes,
-                failed_data_files_count: stat.failed_data_files_count,
-            },
+            stats,
             compaction_validator,
         })
     }
 
-    pub async fn expire_snapshot(&self, table_ident: TableIdent) -> Result<()> {
-        let table = self.catalog.load_table(&table_ident).await?;
-        let txn = Transaction::new(&table);
-        let txn = txn.expire_snapshot().apply().await?;
-        txn.commit(self.catalog.as_ref()).await?;
-        Ok(())
+    /// Applies `CompactionConfig::dangling_delete_policy` to position delete files referencing
+    /// only data files that are not in `live_data_files`. Returns the delete files to drop.
+    ///
+    /// A delete file still referencing a live data file is in use, even if it also references
+    /// data files rewritten since, as happens with position deletes shared by a partition.
+    async fn check_dangling_position_deletes(
+        &self,
+        table: &Table,
+        live_data_files: &HashSet<&str>,
+        position_delete_files: &[FileScanTask],
+    ) -> Result<HashSet<String>> {
+        if self.config.dangling_delete_policy == DanglingDeletePolicy::Ignore
+            || position_delete_files.is_empty()
+        {
+            return Ok(HashSet::new());
+        }
+        let rewriter = DeleteFileRewriter::new(
+            self.config.clone(),
+            table.file_io().clone(),
+            DefaultLocationGenerator::new(table.metadata().clone())?.dir_path,
+            table.metadata().default_partition_spec().spec_id(),
+        );
+        // Each delete file is read once, concurrently, and its references are kept for the
+        // policy.
+        let rewriter = &rewriter;
+        let references: Vec<(String, HashSet<String>)> =
+            futures::stream::iter(position_delete_files.iter().cloned())
+                .map(|task| async move {
+                    let path = task.data_file_path.clone();
+                    let referenced = rewriter.referenced_data_files(vec![task]).await?;
+                    Ok::<_, CompactionError>((path, referenced))
+                })
+                .buffer_unordered(self.config.batch_parallelism.max(1))
+                .try_collect()
+                .await?;
+        let dangling_delete_files: HashSet<String> = references
+            .into_iter()
+            .filter(|(_, referenced)| {
+                referenced
+                    .iter()
+                    .all(|path| !live_data_files.contains(path.as_str()))
+            })
+            .map(|(path, _)| path)
+            .collect();
+        let Some(example) = dangling_delete_files.iter().next() else {
+            return Ok(HashSet::new());
+        };
+        let description = format!(
+            "{} position delete files of table '{}' only reference missing data files, e.g. '{}'",
+            dangling_delete_files.len(),
+            self.table_ident,
+            example
+        );
+
+        match self.config.dangling_delete_policy {
+            DanglingDeletePolicy::Ignore => Ok(HashSet::new()),
+            DanglingDeletePolicy::Report => {
+                tracing::warn!("{}", description);
+                Ok(HashSet::new())
+            }
+            DanglingDeletePolicy::Fail => Err(CompactionError::DanglingDeletes(description)),
+            DanglingDeletePolicy::Clean => {
+                tracing::warn!("{}, removing them", description);
+                Ok(dangling_delete_files)
+            }
+        }
     }
-}
 
-async fn get_old_files_from_table(table: Table) -> Result<(Vec<DataFile>, Vec<DataFile>)> {
-    let manifest_list = table
-        .metadata()
-        .current_snapshot()
-        .unwrap()
-        .load_manifest_list(table.file_io(), table.metadata())
-        .await
-        .unwrap();
+    async fn compact_delete_files(
+        &self,
+        events: Option<&CompactionEventSender>,
+    ) -> Result<CompactionResult> {
+        let label_vec = self.metrics.label_values(
+            &self.catalog_name,
+            &self.table_ident,
+            self.tenant.as_deref(),
+        );
+        let now = std::time::Instant::now();
+        let mut stats = RewriteFilesStat::default();
 
-    let mut data_file = vec![];
-    let mut delete_file = vec![];
-    for manifest_file in manifest_list.entries() {
-        let a = manifest_file.load_manifest(table.file_io()).await.unwrap();
-        let (entry, _) = a.into_parts();
-        for i in entry {
-            match i.content_type() {
+        let table = match self.load_table_to_compact().await? {
+            Ok(table) => table,
+            Err(reason) => return Ok(CompactionResult::skipped(reason)),
+        };
+        let partition_spec = table.metadata().default_partition_spec().clone();
+        if !partition_spec.fields().is_empty() {
+            return Err(CompactionError::UnsupportedTableFeature(
+                "delete file compaction of partitioned tables".to_owned(),
+            ));
+        }
+        check_delete_file_specs(
+            partition_spec.spec_id(),
+            &live_delete_file_specs(&table).await?,
+        )?;
+        check_schema_width(table.metadata().current_schema(), &self.config)?;
+
+        let (_, delete_files) = get_old_files_from_table(table.clone()).await?;
+        let (input_file_scan_tasks, skipped_file_paths) =
+            get_tasks_from_table(table.clone(), self.config.unsupported_content_type_policy)
+                .await?;
+        let live_data_files: HashSet<String> = input_file_scan_tasks
+            .data_files
+            .iter()
+            .map(|task| task.data_file_path.clone())
+            .collect();
+        // A delete file shared with a skipped data file is still needed as is.
+        let is_selected = |task: &FileScanTask| !skipped_file_paths.contains(&task.data_file_path);
+        let position_delete_files: Vec<FileScanTask> = input_file_scan_tasks
+            .position_delete_files
+            .into_iter()
+            .filter(is_selected)
+            .collect();
+        // Equality deletes only apply to data files with a lower sequence number, so only files
+        // sharing their sequence number and equality ids can be merged.
+        let mut equality_delete_groups: HashMap<(i64, Vec<i32>), Vec<FileScanTask>> =
+            HashMap::new();
+        for task in input_file_scan_tasks
+            .equality_delete_files
+            .into_iter()
+            .filter(is_selected)
+        {
+            equality_delete_groups
+                .entry((task.sequence_number, task.equality_ids.clone()))
+                .or_default()
+                .push(task);
+        }
+        equality_delete_groups.retain(|_, tasks| tasks.len() > 1);
+        let position_delete_files = if position_delete_files.len() > 1 {
+            position_delete_files
+        } else {
+            vec![]
+        };
+        if position_delete_files.is_empty() && equality_delete_groups.is_empty() {
+            return Ok(CompactionResult::skipped(SkipReason::NoFilesSelected));
+        }
+        emit_event(
+            events,
+            CompactionEvent::Planned {
+                data_files_count: 0,
+                position_delete_files_count: position_delete_files.len(),
+                equality_delete_files_count: equality_delete_groups.values().map(Vec::len).sum(),
+                file_groups_count: usize::from(!position_delete_files.is_empty())
+                    + equality_delete_groups.len(),
+            },
+        );
+
+        let delete_files_by_path: HashMap<String, DataFile> = delete_files
+            .into_iter()
+            .map(|file| (file.file_path().to_owned(), file))
+            .collect();
+        let old_delete_files = |tasks: &[FileScanTask]| -> Result<Vec<DataFile>> {
+            tasks
+                .iter()
+                .map(|task| {
+                    delete_files_by_path
+                        .get(&task.data_file_path)
+                        .cloned()
+                        .ok_or_else(|| {
+                            CompactionError::Unexpected(format!(
+                                "delete file '{}' is not in the current snapshot",
+                                task.data_file_path
+                            ))
+                        })
+                })
+                .collect()
+        };
+        let schema = table.metadata().current_schema().clone();
+        let rewriter = DeleteFileRewriter::new(
+            self.config.clone(),
+            table.file_io().clone(),
+            DefaultLocationGenerator::new(table.metadata().clone())?.dir_path,
+            partition_spec.spec_id(),
+        );
+        let commit_manager = RewriteDataFilesCommitManager::new(
+            self.commit_retry_config.clone(),
+            self.catalog.clone(),
+            self.table_ident.clone(),
+            self.catalog_name.clone(),
+            self.metrics.clone(),
+            CommitConsistencyParams {
+                starting_snapshot_id: table.metadata().current_snapshot_id().unwrap(),
+                use_starting_sequence_number: true,
+                basic_schema_id: schema.schema_id(),
+            },
+        )
+        .with_commit_hooks(self.commit_hooks.clone())
+        .with_tenant(self.tenant.clone());
+
+        // Each group is committed on its own since the sequence number is set per commit.
+        let mut groups = vec![];
+        if !position_delete_files.is_empty() {
+            // Position deletes name their data file, so taking the starting snapshot's sequence
+            // number keeps them applying to exactly the same rows.
+            groups.push((None, position_delete_files));
+        }
+        for ((sequence_number, _), tasks) in equality_delete_groups {
+            groups.push((Some(sequence_number), tasks));
+        }
+        for (sequence_number, tasks) in groups {
+            let old_files = old_delete_files(&tasks)?;
+            let rewritten = match sequence_number {
+                None => {
+                    rewriter
+                        .rewrite_position_deletes(tasks, &live_data_files)
+                        .await
+                }
+                Some(_) => {
+                    let equality_ids = tasks[0].equality_ids.clone();
+                    rewriter
+                        .rewrite_equality_deletes(&schema, &equality_ids, tasks)
+                        .await
+                }
+            };
+            let new_files = match rewritten {
+                Ok(new_files) => new_files,
+                Err(e) => {
+                    self.metrics
+                        .compaction_executor_error_counter
+                        .counter(&label_vec)
+                        .increase(1);
+                    return Err(e);
+                }
+            };
+            let group_stats = RewriteFilesStat {
+                rewritten_files_count: old_files.len() as u32,
+                added_files_count: new_files.len() as u32,
+                rewritten_bytes: new_files.iter().map(|f| f.file_size_in_bytes()).sum(),
+                ..Default::default()
+            };
+            emit_event(events, CompactionEvent::Rewritten(group_stats.clone()));
+
+            let committed_table = match sequence_number {
+                None => commit_manager.rewrite_files(new_files, old_files).await?,
+                Some(sequence_number) => {
+                    commit_manager
+                        .rewrite_files_with_sequence_number(new_files, old_files, sequence_number)
+                        .await?
+                }
+            };
+            emit_event(
+                events,
+                CompactionEvent::Committed {
+                    snapshot_id: committed_table.metadata().current_snapshot_id(),
+                },
+            );
+            stats.rewritten_files_count += group_stats.rewritten_files_count;
+            stats.added_files_count += group_stats.added_files_count;
+            stats.rewritten_bytes += group_stats.rewritten_bytes;
+        }
+
+        self.metrics
+            .compaction_duration
+            .histogram(&label_vec)
+            .record(now.elapsed().as_secs_f64());
+        self.metrics
+            .compaction_rewritten_bytes
+            .counter(&label_vec)
+            .increase(stats.rewritten_bytes);
+        self.metrics
+            .compaction_rewritten_files_count
+            .counter(&label_vec)
+            .increase(stats.rewritten_files_count as u64);
+        self.metrics
+            .compaction_added_files_count
+            .counter(&label_vec)
+            .increase(stats.added_files_count as u64);
+
+        Ok(CompactionResult {
+            stats,
+            compaction_validator: None,
+        })
+    }
+
+    /// Replaces the equality delete files of the table with position delete files deleting the
+    /// same rows, so readers no longer join data files against them. Only unpartitioned tables
+    /// are supported.
+    pub async fn convert_equality_deletes(&self) -> Result<RewriteFilesStat> {
+        let table = match self.load_table_to_compact().await? {
+            Ok(table) => table,
+            Err(reason) => return Ok(RewriteFilesStat::skipped(reason)),
+        };
+        let partition_spec = table.metadata().default_partition_spec().clone();
+        if !partition_spec.fields().is_empty() {
+            return Err(CompactionError::UnsupportedTableFeature(
+                "equality delete conversion of partitioned tables".to_owned(),
+            ));
+        }
+
+        let (_, delete_files) = get_old_files_from_table(table.clone()).await?;
+        let (input_file_scan_tasks, skipped_file_paths) =
+            get_tasks_from_table(table.clone(), self.config.unsupported_content_type_policy)
+                .await?;
+        // Deletes of skipped data files can't be resolved, so they are kept as they are.
+        let equality_delete_files: Vec<FileScanTask> = input_file_scan_tasks
+            .equality_delete_files
+            .into_iter()
+            .filter(|task| !skipped_file_paths.contains(&task.data_file_path))
+            .collect();
+        if equality_delete_files.is_empty() {
+            return Ok(RewriteFilesStat::skipped(SkipReason::NoFilesSelected));
+        }
+        let converted_paths: HashSet<&str> = equality_delete_files
+            .iter()
+            .map(|task| task.data_file_path.as_str())
+            .collect();
+        let old_files: Vec<DataFile> = delete_files
+            .into_iter()
+            .filter(|file| converted_paths.contains(file.file_path()))
+            .collect();
+        let data_files: Vec<FileScanTask> = input_file_scan_tasks
+            .data_files
+            .into_iter()
+            .filter(|task| {
+                task.deletes
+                    .iter()
+                    .any(|delete| converted_paths.contains(delete.data_file_path.as_str()))
+            })
+            .collect();
+
+        let schema = table.metadata().current_schema().clone();
+        let rewriter = DeleteFileRewriter::new(
+            self.config.clone(),
+            table.file_io().clone(),
+            DefaultLocationGenerator::new(table.metadata().clone())?.dir_path,
+            partition_spec.spec_id(),
+        );
+        let new_files = rewriter
+            .convert_equality_deletes(&schema, data_files, equality_delete_files)
+            .await?;
+        let stats = RewriteFilesStat {
+            rewritten_files_count: old_files.len() as u32,
+            added_files_count: new_files.len() as u32,
+            rewritten_bytes: new_files.iter().map(|f| f.file_size_in_bytes()).sum(),
+            ..Default::default()
+        };
+
+        // Position deletes name their data file, so taking the starting snapshot's sequence
+        // number keeps them applying to exactly the same rows.
+        let commit_manager = RewriteDataFilesCommitManager::new(
+            self.commit_retry_config.clone(),
+            self.catalog.clone(),
+            self.table_ident.clone(),
+            self.catalog_name.clone(),
+            self.metrics.clone(),
+            CommitConsistencyParams {
+                starting_snapshot_id: table.metadata().current_snapshot_id().unwrap(),
+                use_starting_sequence_number: true,
+                basic_schema_id: schema.schema_id(),
+            },
+        )
+        .with_commit_hooks(self.commit_hooks.clone())
+        .with_tenant(self.tenant.clone());
+        commit_manager.rewrite_files(new_files, old_files).await?;
+        tracing::info!(
+            "Converted {} equality delete files of table '{}' into {} position delete files",
+            stats.rewritten_files_count,
+            self.table_ident,
+            stats.added_files_count
+        );
+        Ok(stats)
+    }
+
+    /// Verifies config sanity, the table's schema width, catalog access and object store
+    /// read/write/delete permissions on the table location, without touching any table data.
+    ///
+    /// Commit rights cannot be probed without producing a snapshot, so they are not checked here.
+    pub async fn preflight(&self, table_ident: TableIdent) -> Result<()> {
+        check_config(&self.config)?;
+
+        let table = self.catalog.load_table(&table_ident).await.map_err(|e| {
+            CompactionError::Preflight(format!("failed to load table '{}': {}", table_ident, e))
+        })?;
+        check_compatibility(&table_ident, table.metadata())?;
+        check_schema_width(table.metadata().current_schema(), &self.config)?;
+        preflight::check_table_location(&table).await?;
+
+        tracing::info!(
+            "Preflight check passed for catalog '{}' table_ident '{}'",
+            self.catalog_name,
+            table_ident
+        );
+        Ok(())
+    }
+
+    /// Expires the snapshots selected by `options` and returns their ids along with the files
+    /// that are no longer reachable from any retained snapshot, so callers can delete them.
+    pub async fn expire_snapshot(
+        &self,
+        table_ident: TableIdent,
+        options: &ExpireSnapshotsOptions,
+    ) -> Result<ExpiredFiles> {
+        let table = self.catalog.load_table(&table_ident).await?;
+        let mut action = Transaction::new(&table).expire_snapshot();
+        if let Some(older_than) = options.older_than {
+            action = action.expire_older_than(cutoff_ms(now_ms()?, older_than)?);
+        }
+        if let Some(retain_last) = options.retain_last {
+            action = action.retain_last(retain_last.try_into().map_err(|_| {
+                CompactionError::Config(format!("retain_last {} is too large", retain_last))
+            })?);
+        }
+        let txn = action.apply().await?;
+        let expired_table = txn.commit(self.catalog.as_ref()).await?;
+
+        let now = std::time::Instant::now();
+        let expired_files = collect_expired_files(
+            table.file_io(),
+            table.metadata(),
+            expired_table.metadata(),
+            self.config.manifest_io_parallelism,
+        )
+        .await?;
+        tracing::info!(
+            "Expired {} snapshots for table '{}', {} files became unreferenced, collected in {} seconds",
+            expired_files.expired_snapshot_ids.len(),
+            table_ident,
+            expired_files.files_count(),
+            now.elapsed().as_secs_f64()
+        );
+        Ok(expired_files)
+    }
+
+    /// Detects data files referenced by more than one live manifest entry and, unless `dry_run`
+    /// is set, commits a repair keeping a single entry per file. Returns what was found.
+    ///
+    /// Files are committed in one group per kept sequence number, and the committed groups are
+    /// listed in `repaired_sequence_numbers`. Fails with `CommitConflict` if a file was removed,
+    /// or a delete that may apply to it was added, after the duplicates were found. A failure
+    /// after some groups were committed is reported as `PartialRepair`.
+    pub async fn repair_duplicate_data_files(
+        &self,
+        table_ident: TableIdent,
+        dry_run: bool,
+    ) -> Result<DuplicateDataFiles> {
+        let table = self.catalog.load_table(&table_ident).await?;
+        let duplicates =
+            find_duplicate_data_files(&table, self.config.manifest_io_parallelism).await?;
+        for duplicate in &duplicates.files {
+            tracing::warn!(
+                "Data file '{}' is referenced {} times in table '{}'",
+                duplicate.data_file.file_path(),
+                duplicate.occurrences,
+                table_ident
+            );
+        }
+        let Some(snapshot_id) = duplicates.snapshot_id else {
+            return Ok(duplicates);
+        };
+        if dry_run || duplicates.is_empty() {
+            return Ok(duplicates);
+        }
+
+        let commit_manager = RewriteDataFilesCommitManager::new(
+            self.commit_retry_config.clone(),
+            self.catalog.clone(),
+            table_ident.clone(),
+            self.catalog_name.clone(),
+            self.metrics.clone(),
+            CommitConsistencyParams {
+                starting_snapshot_id: snapshot_id,
+                use_starting_sequence_number: false,
+                basic_schema_id: table.metadata().current_schema().schema_id(),
+            },
+        )
+        .with_commit_hooks(self.commit_hooks.clone())
+        .with_tenant(self.tenant.clone());
+
+        // The sequence number is set per commit, so each group is committed separately. The
+        // repair adds the files back, so the commit manager must not revert a concurrent removal.
+        let mut duplicates = duplicates;
+        for (sequence_number, data_files) in duplicates.files_by_sequence_number() {
+            let committed = match sequence_number {
+                Some(sequence_number) => {
+                    commit_manager
+                        .rewrite_files_with_sequence_number(
+                            data_files.clone(),
+                            data_files,
+                            sequence_number,
+                        )
+                        .await
+                }
+                None => {
+                    commit_manager
+                        .rewrite_files(data_files.clone(), data_files)
+                        .await
+                }
+            };
+            if let Err(e) = committed {
+                if duplicates.repaired_sequence_numbers.is_empty() {
+                    return Err(e);
+                }
+                return Err(CompactionError::PartialRepair {
+                    committed_sequence_numbers: duplicates.repaired_sequence_numbers,
+                    source: Box::new(e),
+                });
+            }
+            duplicates.repaired_sequence_numbers.push(sequence_number);
+        }
+        tracing::info!(
+            "Repaired {} duplicate data files in table '{}'",
+            duplicates.files.len(),
+            table_ident
+        );
+        Ok(duplicates)
+    }
+
+    /// Detects delete files that can't apply to any live data file and, unless `dry_run` is
+    /// set, removes them with a commit that rewrites no data. Returns what was found.
+    pub async fn remove_dangling_delete_files(
+        &self,
+        table_ident: TableIdent,
+        dry_run: bool,
+    ) -> Result<DanglingDeleteFiles> {
+        let table = self.catalog.load_table(&table_ident).await?;
+        let dangling =
+            find_dangling_delete_files(&table, self.config.manifest_io_parallelism).await?;
+        if dry_run || dangling.is_empty() {
+            return Ok(dangling);
+        }
+
+        let txn = build_rewrite_transaction(&table, vec![], dangling.files.clone(), None).await?;
+        commit_hook::before_commit(&self.commit_hooks, &table, &txn).await?;
+        let table = txn.commit(self.catalog.as_ref()).await?;
+        commit_hook::after_commit(&self.commit_hooks, &table).await;
+        tracing::info!(
+            "Removed {} dangling delete files from table '{}'",
+            dangling.files.len(),
+            table_ident
+        );
+        Ok(dangling)
+    }
+}
+
+/// Loads the live data files and delete files referenced by the current snapshot of `table`.
+pub async fn get_old_files_from_table(table: Table) -> Result<(Vec<DataFile>, Vec<DataFile>)> {
+    let current_snapshot = table.metadata().current_snapshot().ok_or_else(|| {
+        CompactionError::Execution(format!(
+            "Table '{}' has no current snapshot",
+            table.identifier()
+        ))
+    })?;
+    let manifest_list = current_snapshot
+        .load_manifest_list(table.file_io(), table.metadata())
+        .await?;
+
+    let mut data_file = vec![];
+    let mut delete_file = vec![];
+    for manifest_file in manifest_list.entries() {
+        let a = manifest_file.load_manifest(table.file_io()).await?;
+        let (entry, _) = a.into_parts();
+        for i in entry.into_iter().filter(|entry| entry.is_alive()) {
+            match i.content_type() {
                 iceberg::spec::DataContentType::Data => {
                     data_file.push(i.data_file().clone());
                 }
@@ -391,98 +1813,845 @@ async fn get_old_files_from_table(table: Table) -> Result<(Vec<DataFile>, Vec<Da
     Ok((data_file, delete_file))
 }
 
-async fn get_tasks_from_table(table: Table) -> Result<InputFileScanTasks> {
-    let snapshot_id = table.metadata().current_snapshot_id().unwrap();
+/// Returns the paths of live data files added by the snapshots in
+/// `(from_snapshot_id, to_snapshot_id]`.
+pub async fn get_data_files_added_between(
+    table: &Table,
+    from_snapshot_id: i64,
+    to_snapshot_id: i64,
+) -> Result<HashSet<String>> {
+    let metadata = table.metadata();
+    let mut snapshot_ids = HashSet::new();
+    let mut snapshot_id = Some(to_snapshot_id);
+    while snapshot_id != Some(from_snapshot_id) {
+        let snapshot = snapshot_id
+            .and_then(|id| metadata.snapshot_by_id(id))
+            .ok_or_else(|| {
+                CompactionError::Config(format!(
+                    "snapshot {} is not an ancestor of snapshot {} in table '{}'",
+                    from_snapshot_id,
+                    to_snapshot_id,
+                    table.identifier()
+                ))
+            })?;
+        snapshot_ids.insert(snapshot.snapshot_id());
+        snapshot_id = snapshot.parent_snapshot_id();
+    }
+
+    get_live_data_files_added_by(table, |snapshot_id| {
+        snapshot_id.is_some_and(|id| snapshot_ids.contains(&id))
+    })
+    .await
+}
+
+/// Returns the paths of live data files added at or before `timestamp_ms`. Files added by
+/// snapshots that have since expired count as old.
+pub async fn get_data_files_added_before(
+    table: &Table,
+    timestamp_ms: i64,
+) -> Result<HashSet<String>> {
+    let metadata = table.metadata();
+    get_live_data_files_added_by(table, |snapshot_id| {
+        snapshot_id
+            .and_then(|id| metadata.snapshot_by_id(id))
+            .is_none_or(|snapshot| snapshot.timestamp_ms() <= timestamp_ms)
+    })
+    .await
+}
+
+/// Returns the ids of the append snapshots among the last `snapshots` snapshots of the current
+/// snapshot's lineage, with the lowest sequence number among them.
+fn recent_append_snapshots(metadata: &TableMetadata, snapshots: usize) -> (HashSet<i64>, i64) {
+    let mut append_snapshot_ids = HashSet::new();
+    let mut min_sequence_number = i64::MAX;
+    let mut snapshot_id = metadata.current_snapshot_id();
+    for _ in 0..snapshots {
+        let Some(snapshot) = snapshot_id.and_then(|id| metadata.snapshot_by_id(id)) else {
+            break;
+        };
+        if snapshot.summary().operation == iceberg::spec::Operation::Append {
+            append_snapshot_ids.insert(snapshot.snapshot_id());
+            min_sequence_number = min_sequence_number.min(snapshot.sequence_number());
+        }
+        snapshot_id = snapshot.parent_snapshot_id();
+    }
+    (append_snapshot_ids, min_sequence_number)
+}
+
+/// Partition spec ids and paths of the live delete files of the current snapshot of `table`.
+async fn live_delete_file_specs(table: &Table) -> Result<Vec<(i32, String)>> {
+    let Some(current_snapshot) = table.metadata().current_snapshot() else {
+        return Ok(vec![]);
+    };
+    let manifest_list = current_snapshot
+        .load_manifest_list(table.file_io(), table.metadata())
+        .await?;
+    let mut specs = vec![];
+    for manifest_file in manifest_list.entries() {
+        if manifest_file.content != iceberg::spec::ManifestContentType::Deletes {
+            continue;
+        }
+        let manifest = manifest_file.load_manifest(table.file_io()).await?;
+        specs.extend(
+            manifest
+                .entries()
+                .iter()
+                .filter(|entry| entry.is_alive())
+                .map(|entry| {
+                    (
+                        manifest_file.partition_spec_id,
+                        entry.file_path().to_owned(),
+                    )
+                }),
+        );
+    }
+    Ok(specs)
+}
+
+/// Refuses delete files written under a partition spec other than `default_spec_id`. They only
+/// apply within a partition of their spec, which rewriting them under the default spec would
+/// widen to the whole table.
+fn check_delete_file_specs(
+    default_spec_id: i32,
+    delete_file_specs: &[(i32, String)],
+) -> Result<()> {
+    match delete_file_specs
+        .iter()
+        .find(|(spec_id, _)| *spec_id != default_spec_id)
+    {
+        Some((spec_id, path)) => Err(CompactionError::UnsupportedTableFeature(format!(
+            "delete file compaction of delete files of an older partition spec, '{}' has spec \
+             id {} while the default is {}",
+            path, spec_id, default_spec_id
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// Live entries of the manifests of the current snapshot written at or after
+/// `min_sequence_number`, with the partition spec id of their manifest. A manifest listing a file
+/// added at some sequence number was written at or after it, so older manifests are not read.
+async fn live_entries_since(
+    table: &Table,
+    min_sequence_number: i64,
+) -> Result<Vec<(i32, iceberg::spec::ManifestEntryRef)>> {
+    let Some(current_snapshot) = table.metadata().current_snapshot() else {
+        return Ok(vec![]);
+    };
+    let manifest_list = current_snapshot
+        .load_manifest_list(table.file_io(), table.metadata())
+        .await?;
+    let mut entries = vec![];
+    for manifest_file in manifest_list.entries() {
+        if manifest_file.sequence_number < min_sequence_number {
+            continue;
+        }
+        let manifest = manifest_file.load_manifest(table.file_io()).await?;
+        entries.extend(
+            manifest
+                .entries()
+                .iter()
+                .filter(|entry| entry.is_alive())
+                .map(|entry| (manifest_file.partition_spec_id, entry.clone())),
+        );
+    }
+    Ok(entries)
+}
+
+/// Returns the paths of live data files appended by the last `snapshots` snapshots of the current
+/// snapshot's lineage, grouped by partition. Manifests written before the oldest of those
+/// snapshots are not read.
+pub async fn get_recently_appended_data_files(
+    table: &Table,
+    snapshots: usize,
+) -> Result<Vec<HashSet<String>>> {
+    let (append_snapshot_ids, min_sequence_number) =
+        recent_append_snapshots(table.metadata(), snapshots);
+    if append_snapshot_ids.is_empty() {
+        return Ok(vec![]);
+    }
+    let mut partitions: HashMap<(i32, iceberg::spec::Struct), HashSet<String>> = HashMap::new();
+    for (spec_id, entry) in live_entries_since(table, min_sequence_number).await? {
+        if entry.content_type() == iceberg::spec::DataContentType::Data
+            && entry
+                .snapshot_id()
+                .is_some_and(|id| append_snapshot_ids.contains(&id))
+        {
+            partitions
+                .entry((spec_id, entry.data_file().partition().clone()))
+                .or_default()
+                .insert(entry.file_path().to_owned());
+        }
+    }
+    Ok(partitions.into_values().collect())
+}
+
+/// Plans the data files smaller than `small_file_threshold` appended by the last `snapshots`
+/// snapshots, reading only the manifests written since the oldest of them. Returns their tasks,
+/// their paths grouped by partition, and the files themselves.
+///
+/// Delete files that may apply to a data file have a sequence number at least as high, so they
+/// are listed by those manifests too. A position delete applies to the data files of its
+/// partition with a lower or equal sequence number, an equality delete to those with a lower one,
+/// in any partition if its own spec is unpartitioned.
+async fn plan_recent_appends(
+    table: &Table,
+    snapshots: usize,
+    small_file_threshold: u64,
+) -> Result<(InputFileScanTasks, Vec<HashSet<String>>, Vec<DataFile>)> {
+    let empty_tasks = InputFileScanTasks {
+        data_files: vec![],
+        position_delete_files: vec![],
+        equality_delete_files: vec![],
+    };
+    let (append_snapshot_ids, min_sequence_number) =
+        recent_append_snapshots(table.metadata(), snapshots);
+    if append_snapshot_ids.is_empty() {
+        return Ok((empty_tasks, vec![], vec![]));
+    }
+
+    let mut appended_files = vec![];
+    let mut delete_files = vec![];
+    for (spec_id, entry) in live_entries_since(table, min_sequence_number).await? {
+        let sequence_number = entry.sequence_number().unwrap_or(0);
+        let data_file = entry.data_file().clone();
+        if entry.content_type() != iceberg::spec::DataContentType::Data {
+            delete_files.push((spec_id, sequence_number, data_file));
+        } else if entry
+            .snapshot_id()
+            .is_some_and(|id| append_snapshot_ids.contains(&id))
+            && data_file.file_size_in_bytes() < small_file_threshold
+        {
+            appended_files.push((spec_id, sequence_number, data_file));
+        }
+    }
+
+    let schema = table.metadata().current_schema().clone();
+    let project_field_ids: Vec<i32> = schema.as_struct().fields().iter().map(|f| f.id).collect();
+    let to_task =
+        |data_file: &DataFile, sequence_number: i64, project_field_ids: Vec<i32>| FileScanTask {
+            start: 0,
+            length: data_file.file_size_in_bytes(),
+            record_count: Some(data_file.record_count()),
+            data_file_path: data_file.file_path().to_owned(),
+            data_file_content: data_file.content_type(),
+            data_file_format: data_file.file_format(),
+            schema: schema.clone(),
+            project_field_ids,
+            predicate: None,
+            deletes: vec![],
+            sequence_number,
+            equality_ids: data_file.equality_ids().to_vec(),
+            file_size_in_bytes: data_file.file_size_in_bytes(),
+        };
+
+    let mut tasks = empty_tasks;
+    let mut position_delete_files = HashMap::new();
+    let mut equality_delete_files = HashMap::new();
+    let mut partitions: HashMap<(i32, iceberg::spec::Struct), HashSet<String>> = HashMap::new();
+    for (spec_id, sequence_number, data_file) in &appended_files {
+        let mut task = to_task(data_file, *sequence_number, project_field_ids.clone());
+        for (delete_spec_id, delete_sequence_number, delete_file) in &delete_files {
+            let same_partition =
+                delete_spec_id == spec_id && delete_file.partition() == data_file.partition();
+            let delete_task = match delete_file.content_type() {
+                iceberg::spec::DataContentType::PositionDeletes
+                    if same_partition && delete_sequence_number >= sequence_number =>
+                {
+                    let delete_task = to_task(delete_file, *delete_sequence_number, vec![]);
+                    position_delete_files
+                        .insert(delete_task.data_file_path.clone(), delete_task.clone());
+                    delete_task
+                }
+                iceberg::spec::DataContentType::EqualityDeletes
+                    if (same_partition || delete_file.partition().iter().next().is_none())
+                        && delete_sequence_number > sequence_number =>
+                {
+                    let equality_ids = delete_file.equality_ids().to_vec();
+                    let delete_task = to_task(delete_file, *delete_sequence_number, equality_ids);
+                    equality_delete_files
+                        .insert(delete_task.data_file_path.clone(), delete_task.clone());
+                    delete_task
+                }
+                _ => continue,
+            };
+            task.deletes.push(delete_task);
+        }
+        partitions
+            .entry((*spec_id, data_file.partition().clone()))
+            .or_default()
+            .insert(task.data_file_path.clone());
+        tasks.data_files.push(task);
+    }
+    tasks.position_delete_files = position_delete_files.into_values().collect();
+    tasks.equality_delete_files = equality_delete_files.into_values().collect();
+
+    let data_files = appended_files
+        .into_iter()
+        .map(|(_, _, data_file)| data_file)
+        .collect();
+    Ok((tasks, partitions.into_values().collect(), data_files))
+}
+
+/// Returns the paths of live data files of the current snapshot whose adding snapshot id is
+/// accepted by `is_selected`.
+async fn get_live_data_files_added_by(
+    table: &Table,
+    is_selected: impl Fn(Option<i64>) -> bool,
+) -> Result<HashSet<String>> {
+    let current_snapshot = table.metadata().current_snapshot().ok_or_else(|| {
+        CompactionError::Execution(format!(
+            "Table '{}' has no current snapshot",
+            table.identifier()
+        ))
+    })?;
+    let manifest_list = current_snapshot
+        .load_manifest_list(table.file_io(), table.metadata())
+        .await?;
+
+    let mut file_paths = HashSet::new();
+    for manifest_file in manifest_list.entries() {
+        if manifest_file.content != iceberg::spec::ManifestContentType::Data {
+            continue;
+        }
+        let manifest = manifest_file.load_manifest(table.file_io()).await?;
+        file_paths.extend(
+            manifest
+                .entries()
+                .iter()
+                .filter(|entry| {
+                    entry.is_alive()
+                        && entry.content_type() == iceberg::spec::DataContentType::Data
+                        && is_selected(entry.snapshot_id())
+                })
+                .map(|entry| entry.file_path().to_owned()),
+        );
+    }
+    Ok(file_paths)
+}
+
+/// Collects the file scan tasks to rewrite, along with the paths of files that were skipped
+/// under `UnsupportedContentTypePolicy::Skip` and therefore must be left in the table.
+/// Delete files of a skipped data file are still applied to the other data files sharing them.
+pub async fn get_tasks_from_table(
+    table: Table,
+    unsupported_content_type_policy: UnsupportedContentTypePolicy,
+) -> Result<(InputFileScanTasks, HashSet<String>)> {
+    get_tasks_from_table_with_filter(table, unsupported_content_type_policy, None).await
+}
+
+/// Like `get_tasks_from_table`, but only plans the data files whose metrics may match `filter`.
+/// The filter is only used for pruning, so planned files are still read in full.
+pub async fn get_tasks_from_table_with_filter(
+    table: Table,
+    unsupported_content_type_policy: UnsupportedContentTypePolicy,
+    filter: Option<&Predicate>,
+) -> Result<(InputFileScanTasks, HashSet<String>)> {
+    let snapshot_id = table.metadata().current_snapshot_id().ok_or_else(|| {
+        CompactionError::Execution(format!(
+            "Table '{}' has no current snapshot",
+            table.identifier()
+        ))
+    })?;
 
-    let scan = table
+    let mut scan_builder = table
         .scan()
         .snapshot_id(snapshot_id)
-        .with_delete_file_processing_enabled(true)
-        .build()?;
+        .with_delete_file_processing_enabled(true);
+    if let Some(filter) = filter {
+        scan_builder = scan_builder.with_filter(filter.clone());
+    }
+    let scan = scan_builder.build()?;
     let file_scan_stream = scan.plan_files().await?;
 
     let mut position_delete_files = HashMap::new();
     let mut data_files = vec![];
     let mut equality_delete_files = HashMap::new();
+    let mut skipped_file_paths = HashSet::new();
 
     #[for_await]
     for task in file_scan_stream {
-        let task: FileScanTask = task?;
+        let mut task: FileScanTask = task?;
+        // The reader would otherwise drop the rows not matching the filter from the output.
+        task.predicate = None;
+        for delete_task in task.deletes.iter_mut() {
+            delete_task.predicate = None;
+        }
         match task.data_file_content {
             iceberg::spec::DataContentType::Data => {
+                let mut task_position_delete_files = vec![];
+                let mut task_equality_delete_files = vec![];
+                let mut unsupported_delete_task = None;
                 for delete_task in task.deletes.iter() {
-                    match &delete_task.data_file_content {
+                    let mut delete_task = delete_task.clone();
+                    match delete_task.data_file_content {
                         iceberg::spec::DataContentType::PositionDeletes => {
-                            let mut delete_task = delete_task.clone();
                             delete_task.project_field_ids = vec![];
-                            position_delete_files
-                                .insert(delete_task.data_file_path.clone(), delete_task);
+                            task_position_delete_files.push(delete_task);
                         }
                         iceberg::spec::DataContentType::EqualityDeletes => {
-                            let mut delete_task = delete_task.clone();
                             delete_task.project_field_ids = delete_task.equality_ids.clone();
-                            equality_delete_files
-                                .insert(delete_task.data_file_path.clone(), delete_task);
+                            task_equality_delete_files.push(delete_task);
                         }
-                        _ => {
-                            unreachable!()
+                        iceberg::spec::DataContentType::Data => {
+                            unsupported_delete_task = Some(delete_task);
+                            break;
                         }
                     }
                 }
+                if let Some(delete_task) = unsupported_delete_task {
+                    handle_unsupported_content_type(
+                        unsupported_content_type_policy,
+                        format!(
+                            "delete file '{}' of data file '{}' has content type {:?}",
+                            delete_task.data_file_path,
+                            task.data_file_path,
+                            delete_task.data_file_content
+                        ),
+                    )?;
+                    skipped_file_paths.insert(task.data_file_path.clone());
+                    skipped_file_paths.extend(
+                        task.deletes
+                            .iter()
+                            .map(|delete_task| delete_task.data_file_path.clone()),
+                    );
+                    continue;
+                }
+
+                position_delete_files.extend(
+                    task_position_delete_files
+                        .into_iter()
+                        .map(|delete_task| (delete_task.data_file_path.clone(), delete_task)),
+                );
+                equality_delete_files.extend(
+                    task_equality_delete_files
+                        .into_iter()
+                        .map(|delete_task| (delete_task.data_file_path.clone(), delete_task)),
+                );
                 data_files.push(task);
             }
-            _ => {
-                unreachable!()
+            content_type => {
+                handle_unsupported_content_type(
+                    unsupported_content_type_policy,
+                    format!(
+                        "scan task for file '{}' has content type {:?}",
+                        task.data_file_path, content_type
+                    ),
+                )?;
+                skipped_file_paths.insert(task.data_file_path.clone());
             }
         }
     }
-    Ok(InputFileScanTasks {
-        data_files,
-        position_delete_files: position_delete_files.into_values().collect(),
-        equality_delete_files: equality_delete_files.into_values().collect(),
-    })
+
+    Ok((
+        InputFileScanTasks {
+            data_files,
+            position_delete_files: position_delete_files.into_values().collect(),
+            equality_delete_files: equality_delete_files.into_values().collect(),
+        },
+        skipped_file_paths,
+    ))
 }
 
-/// Configuration for the commit manager, including retry strategies.
-#[derive(Debug, Clone)]
-pub struct RewriteDataFilesCommitManagerRetryConfig {
-    pub max_retries: u32, // This can be used to configure the backon strategy
-    pub retry_initial_delay: Duration, // For exponential backoff
-    pub retry_max_delay: Duration, // For exponential backoff
+/// Keeps the data file tasks accepted by `is_selected` along with the delete files they reference.
+/// Also returns the paths of files that must stay in the table: the unselected data files and
+/// every delete file still referenced by one of them.
+pub fn select_tasks(
+    tasks: InputFileScanTasks,
+    is_selected: impl Fn(&FileScanTask) -> bool,
+) -> (InputFileScanTasks, HashSet<String>) {
+    let (selected, unselected): (Vec<FileScanTask>, Vec<FileScanTask>) = tasks
+        .data_files
+        .into_iter()
+        .partition(|task| is_selected(task));
+    let referenced_delete_paths = |tasks: &[FileScanTask]| -> HashSet<String> {
+        tasks
+            .iter()
+            .flat_map(|task| task.deletes.iter())
+            .map(|delete_task| delete_task.data_file_path.clone())
+            .collect()
+    };
+
+    let selected_delete_paths = referenced_delete_paths(&selected);
+    let mut retained_file_paths = referenced_delete_paths(&unselected);
+    retained_file_paths.extend(unselected.into_iter().map(|task| task.data_file_path));
+
+    let selected_tasks = InputFileScanTasks {
+        data_files: selected,
+        position_delete_files: tasks
+            .position_delete_files
+            .into_iter()
+            .filter(|task| selected_delete_paths.contains(&task.data_file_path))
+            .collect(),
+        equality_delete_files: tasks
+            .equality_delete_files
+            .into_iter()
+            .filter(|task| selected_delete_paths.contains(&task.data_file_path))
+            .collect(),
+    };
+    (selected_tasks, retained_file_paths)
 }
 
-impl Default for RewriteDataFilesCommitManagerRetryConfig {
-    fn default() -> Self {
-        Self {
-            max_retries: 3,
-            retry_initial_delay: Duration::from_secs(1),
-            retry_max_delay: Duration::from_secs(10),
+fn handle_unsupported_content_type(
+    policy: UnsupportedContentTypePolicy,
+    message: String,
+) -> Result<()> {
+    match policy {
+        UnsupportedContentTypePolicy::Fail => {
+            Err(CompactionError::UnsupportedTableFeature(message))
+        }
+        UnsupportedContentTypePolicy::Skip => {
+            tracing::warn!("Skipping unsupported file during compaction: {}", message);
+            Ok(())
         }
     }
 }
 
-// Manages the commit process with retries
-pub struct RewriteDataFilesCommitManager {
-    config: RewriteDataFilesCommitManagerRetryConfig,
-    catalog: Arc<dyn Catalog>,
-    table_ident: TableIdent,
-    starting_snapshot_id: i64, // The snapshot ID to start from, used for consistency
-    use_starting_sequence_number: bool, // Whether to use the starting sequence number for commits
-
-    catalog_name: String,  // Catalog name for metrics
-    metrics: Arc<Metrics>, // Metrics for tracking commit operations
+/// Builds an uncommitted transaction that replaces `delete_files` with `data_files` in `table`.
+///
+/// When `starting_snapshot_id` is set, the added data files take the sequence number of that
+/// snapshot, so that deletes committed after it keep applying to the rewritten rows.
+pub async fn build_rewrite_transaction(
+    table: &Table,
+    data_files: Vec<DataFile>,
+    delete_files: Vec<DataFile>,
+    starting_snapshot_id: Option<i64>,
+) -> iceberg::Result<Transaction<'_>> {
+    let sequence_number = match starting_snapshot_id {
+        Some(starting_snapshot_id) => {
+            let snapshot = table
+                .metadata()
+                .snapshot_by_id(starting_snapshot_id)
+                .ok_or_else(|| {
+                    iceberg::Error::new(
+                        ErrorKind::Unexpected,
+                        format!(
+                            "No snapshot found with the given snapshot_id {}",
+                            starting_snapshot_id
+                        ),
+                    )
+                })?;
+            Some(snapshot.sequence_number())
+        }
+        None => None,
+    };
+    build_rewrite_transaction_with_sequence_number(table, data_files, delete_files, sequence_number)
+        .await
+}
 
-    basic_schema_id: i32, // Schema ID for the table, used for validation
+/// Builds an uncommitted transaction that replaces `delete_files` with `data_files` in `table`,
+/// giving the added files `sequence_number` if set.
+pub async fn build_rewrite_transaction_with_sequence_number(
+    table: &Table,
+    data_files: Vec<DataFile>,
+    delete_files: Vec<DataFile>,
+    sequence_number: Option<i64>,
+) -> iceberg::Result<Transaction<'_>> {
+    let txn = Transaction::new(table);
+    let rewrite_action = match sequence_number {
+        Some(sequence_number) => txn
+            .rewrite_files(None, vec![])?
+            .add_data_files(data_files)?
+            .delete_files(delete_files)?
+            .new_data_file_sequence_number(sequence_number)?,
+        None => txn
+            .rewrite_files(None, vec![])?
+            .add_data_files(data_files)?
+            .delete_files(delete_files)?,
+    };
+    rewrite_action.apply().await
 }
 
-pub struct CommitConsistencyParams {
-    pub starting_snapshot_id: i64,
-    pub use_starting_sequence_number: bool,
-    pub basic_schema_id: i32,
+/// Splits `tasks` into commits of file groups, given as the data file paths of each group, to
+/// be rewritten and committed one after another. Each commit comes with the tasks of its file
+/// groups and the paths of files it must keep: the data files of later commits and the delete
+/// files they still need.
+fn split_tasks_for_commits(
+    tasks: InputFileScanTasks,
+    commits: Vec<Vec<HashSet<String>>>,
+) -> Vec<(Vec<InputFileScanTasks>, HashSet<String>)> {
+    let mut split = Vec::with_capacity(commits.len());
+    let mut remaining = tasks;
+    for file_groups in commits {
+        let in_commit = |task: &FileScanTask| {
+            file_groups
+                .iter()
+                .any(|paths| paths.contains(&task.data_file_path))
+        };
+        let (rest, _) = select_tasks(remaining.clone(), |task| !in_commit(task));
+        let (commit_tasks, retained_file_paths) = select_tasks(remaining, in_commit);
+        let group_tasks_list = file_groups
+            .iter()
+            .map(|paths| {
+                select_tasks(commit_tasks.clone(), |task| {
+                    paths.contains(&task.data_file_path)
+                })
+                .0
+            })
+            .collect();
+        split.push((group_tasks_list, retained_file_paths));
+        remaining = rest;
+    }
+    split
 }
 
-/// Manages the commit process with retries
-impl RewriteDataFilesCommitManager {
+/// Combines the tasks of several groups, listing delete files shared between groups once.
+fn merge_tasks(groups: Vec<InputFileScanTasks>) -> InputFileScanTasks {
+    let mut merged = InputFileScanTasks {
+        data_files: vec![],
+        position_delete_files: vec![],
+        equality_delete_files: vec![],
+    };
+    let mut delete_file_paths = HashSet::new();
+    for group in groups {
+        merged.data_files.extend(group.data_files);
+        for task in group.position_delete_files {
+            if delete_file_paths.insert(task.data_file_path.clone()) {
+                merged.position_delete_files.push(task);
+            }
+        }
+        for task in group.equality_delete_files {
+            if delete_file_paths.insert(task.data_file_path.clone()) {
+                merged.equality_delete_files.push(task);
+            }
+        }
+    }
+    merged
+}
+
+/// Returns why rewriting `tasks` would achieve nothing, if it would not. Sorting or clustering
+/// the output of a full compaction is worth a rewrite on its own.
+fn no_op_reason(
+    tasks: &InputFileScanTasks,
+    config: &CompactionConfig,
+    full: bool,
+) -> Option<SkipReason> {
+    if tasks.data_files.is_empty() {
+        return Some(SkipReason::NoFilesSelected);
+    }
+    if !tasks.position_delete_files.is_empty() || !tasks.equality_delete_files.is_empty() {
+        return None;
+    }
+    let reorders_rows = config.enable_sort_order || !config.clustering_columns.is_empty();
+    if tasks.data_files.len() == 1 && !(full && reorders_rows) {
+        return Some(SkipReason::SingleFile);
+    }
+    if !reorders_rows
+        && tasks
+            .data_files
+            .iter()
+            .all(|task| task.file_size_in_bytes >= config.target_file_size)
+    {
+        return Some(SkipReason::FilesAtTargetSize);
+    }
+    None
+}
+
+/// Field id of the `file_path` column of position delete files.
+const POSITION_DELETE_FILE_PATH_FIELD_ID: i32 = 2147483546;
+
+/// Fails with `CommitConflict` if a rewrite removing `removed_files`, planned against
+/// `starting_snapshot_id`, can't be committed to `table` anymore: one of the files is no longer
+/// live, or a delete added since may apply to a removed data file. Equality deletes added since
+/// keep applying to the output when `keeps_sequence_number` is set, as the output then takes a
+/// sequence number from before them.
+async fn check_concurrent_changes(
+    table: &Table,
+    starting_snapshot_id: i64,
+    removed_files: &[DataFile],
+    keeps_sequence_number: bool,
+) -> Result<()> {
+    let conflict = |reason: String| {
+        Err(CompactionError::CommitConflict(format!(
+            "{} in table '{}'",
+            reason,
+            table.identifier()
+        )))
+    };
+    let Some(starting_snapshot) = table.metadata().snapshot_by_id(starting_snapshot_id) else {
+        return conflict(format!("snapshot {} was expired", starting_snapshot_id));
+    };
+    let Some(current_snapshot) = table.metadata().current_snapshot() else {
+        return conflict("the current snapshot was removed".to_owned());
+    };
+    let manifest_list = current_snapshot
+        .load_manifest_list(table.file_io(), table.metadata())
+        .await?;
+    let mut live_file_paths = HashSet::new();
+    let mut added_delete_files = vec![];
+    for manifest_file in manifest_list.entries() {
+        let manifest = manifest_file.load_manifest(table.file_io()).await?;
+        for entry in manifest.entries().iter().filter(|entry| entry.is_alive()) {
+            live_file_paths.insert(entry.file_path().to_owned());
+            if entry.content_type() != iceberg::spec::DataContentType::Data
+                && entry.sequence_number() > Some(starting_snapshot.sequence_number())
+            {
+                added_delete_files.push(entry.data_file().clone());
+            }
+        }
+    }
+
+    if let Some(missing) = removed_files
+        .iter()
+        .find(|f| !live_file_paths.contains(f.file_path()))
+    {
+        return conflict(format!(
+            "file '{}' was removed by a concurrent commit",
+            missing.file_path()
+        ));
+    }
+    let removed_data_file_paths: HashSet<&str> = removed_files
+        .iter()
+        .filter(|f| f.content_type() == iceberg::spec::DataContentType::Data)
+        .map(|f| f.file_path())
+        .collect();
+    if removed_data_file_paths.is_empty() {
+        return Ok(());
+    }
+    for delete_file in added_delete_files {
+        let applies = match delete_file.content_type() {
+            iceberg::spec::DataContentType::EqualityDeletes => !keeps_sequence_number,
+            _ => may_reference_data_files(&delete_file, &removed_data_file_paths),
+        };
+        if applies {
+            return conflict(format!(
+                "delete file '{}' was added concurrently and may apply to rewritten data files",
+                delete_file.file_path()
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Whether `position_delete_file` may delete rows of one of `data_file_paths`, judging by the
+/// bounds of its `file_path` column. Without bounds it is assumed to.
+fn may_reference_data_files(
+    position_delete_file: &DataFile,
+    data_file_paths: &HashSet<&str>,
+) -> bool {
+    let file_path_bound = |bounds: &HashMap<i32, Datum>| match bounds
+        .get(&POSITION_DELETE_FILE_PATH_FIELD_ID)?
+        .literal()
+    {
+        PrimitiveLiteral::String(path) => Some(path.clone()),
+        _ => None,
+    };
+    match (
+        file_path_bound(position_delete_file.lower_bounds()),
+        file_path_bound(position_delete_file.upper_bounds()),
+    ) {
+        (Some(lower), Some(upper)) => data_file_paths
+            .iter()
+            .any(|path| lower.as_str() <= *path && *path <= upper.as_str()),
+        _ => true,
+    }
+}
+
+/// Milliseconds since the Unix epoch.
+fn now_ms() -> Result<i64> {
+    Ok(std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_err(|e| CompactionError::Unexpected(e.to_string()))?
+        .as_millis() as i64)
+}
+
+/// Milliseconds since the Unix epoch `age` before `now_ms`.
+fn cutoff_ms(now_ms: i64, age: Duration) -> Result<i64> {
+    i64::try_from(age.as_millis())
+        .ok()
+        .and_then(|age_ms| now_ms.checked_sub(age_ms))
+        .ok_or_else(|| CompactionError::Config(format!("age {:?} is too large", age)))
+}
+
+/// Records the files of `paths` as retained for `reason`, unless already retained for another.
+fn retain_files(
+    retained_files: &mut HashMap<String, RetainReason>,
+    paths: impl IntoIterator<Item = String>,
+    reason: RetainReason,
+) {
+    for path in paths {
+        retained_files.entry(path).or_insert(reason);
+    }
+}
+
+/// Removes the delete files in `paths` from `tasks`, so they are neither read nor retained.
+fn remove_delete_files(tasks: &mut InputFileScanTasks, paths: &HashSet<String>) {
+    if paths.is_empty() {
+        return;
+    }
+    tasks
+        .position_delete_files
+        .retain(|task| !paths.contains(&task.data_file_path));
+    tasks
+        .equality_delete_files
+        .retain(|task| !paths.contains(&task.data_file_path));
+    for task in &mut tasks.data_files {
+        task.deletes
+            .retain(|delete| !paths.contains(&delete.data_file_path));
+    }
+}
+
+/// Logs degraded features of the table and fails if any feature is unsupported.
+fn check_compatibility(
+    table_ident: &TableIdent,
+    metadata: &TableMetadata,
+) -> Result<CompatibilityReport> {
+    let report = compatibility::check_table_compatibility(metadata);
+    for issue in report.issues.iter().filter(|issue| !issue.blocking) {
+        tracing::warn!(
+            "Table '{}' uses {}: {}",
+            table_ident,
+            issue.feature,
+            issue.detail
+        );
+    }
+    report.ensure_compatible()?;
+    Ok(report)
+}
+
+/// Configuration for the commit manager, including retry strategies.
+#[derive(Debug, Clone)]
+pub struct RewriteDataFilesCommitManagerRetryConfig {
+    pub max_retries: u32, // This can be used to configure the backon strategy
+    pub retry_initial_delay: Duration, // For exponential backoff
+    pub retry_max_delay: Duration, // For exponential backoff
+}
+
+impl Default for RewriteDataFilesCommitManagerRetryConfig {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            retry_initial_delay: Duration::from_secs(1),
+            retry_max_delay: Duration::from_secs(10),
+        }
+    }
+}
+
+// Manages the commit process with retries
+pub struct RewriteDataFilesCommitManager {
+    config: RewriteDataFilesCommitManagerRetryConfig,
+    catalog: Arc<dyn Catalog>,
+    table_ident: TableIdent,
+    starting_snapshot_id: i64, // The snapshot ID to start from, used for consistency
+    use_starting_sequence_number: bool, // Whether to use the starting sequence number for commits
+
+    catalog_name: String,  // Catalog name for metrics
+    metrics: Arc<Metrics>, // Metrics for tracking commit operations
+
+    basic_schema_id: i32, // Schema ID for the table, used for validation
+
+    commit_hooks: Vec<Arc<dyn CommitHook>>,
+    tenant: Option<String>, // Tenant label value for metrics
+}
+
+pub struct CommitConsistencyParams {
+    pub starting_snapshot_id: i64,
+    pub use_starting_sequence_number: bool,
+    pub basic_schema_id: i32,
+}
+
+/// Manages the commit process with retries
+impl RewriteDataFilesCommitManager {
     pub fn new(
         config: RewriteDataFilesCommitManagerRetryConfig,
         catalog: Arc<dyn Catalog>,
@@ -500,14 +2669,49 @@ impl RewriteDataFilesCommitManager {
             catalog_name,
             metrics,
             basic_schema_id: consistency_params.basic_schema_id,
+            commit_hooks: vec![],
+            tenant: None,
         }
     }
 
+    /// Calls `commit_hooks` around every commit, in order.
+    pub fn with_commit_hooks(mut self, commit_hooks: Vec<Arc<dyn CommitHook>>) -> Self {
+        self.commit_hooks = commit_hooks;
+        self
+    }
+
+    /// Reports commit metrics with `tenant` as the `tenant` label value.
+    pub fn with_tenant(mut self, tenant: Option<String>) -> Self {
+        self.tenant = tenant;
+        self
+    }
+
     /// Rewrites files in the table, handling retries and errors.
     pub async fn rewrite_files(
         &self,
         data_files: impl IntoIterator<Item = DataFile>,
         delete_files: impl IntoIterator<Item = DataFile>,
+    ) -> Result<Table> {
+        self.commit_rewrite(data_files, delete_files, None).await
+    }
+
+    /// Like `rewrite_files`, but the added files take `sequence_number` instead of the one of
+    /// the starting snapshot.
+    pub async fn rewrite_files_with_sequence_number(
+        &self,
+        data_files: impl IntoIterator<Item = DataFile>,
+        delete_files: impl IntoIterator<Item = DataFile>,
+        sequence_number: i64,
+    ) -> Result<Table> {
+        self.commit_rewrite(data_files, delete_files, Some(sequence_number))
+            .await
+    }
+
+    async fn commit_rewrite(
+        &self,
+        data_files: impl IntoIterator<Item = DataFile>,
+        delete_files: impl IntoIterator<Item = DataFile>,
+        sequence_number: Option<i64>,
     ) -> Result<Table> {
         let data_files: Vec<DataFile> = data_files.into_iter().collect();
         let delete_files: Vec<DataFile> = delete_files.into_iter().collect();
@@ -520,52 +2724,58 @@ impl RewriteDataFilesCommitManager {
             let starting_snapshot_id = self.starting_snapshot_id;
             let metrics = self.metrics.clone();
 
-            let table_label: std::borrow::Cow<'static, str> = self.table_ident.to_string().into();
-            let catalog_name_label: std::borrow::Cow<'static, str> =
-                self.catalog_name.clone().into();
-            let label_vec: [std::borrow::Cow<'static, str>; 2] = [catalog_name_label, table_label];
+            let label_vec = self.metrics.label_values(
+                &self.catalog_name,
+                &self.table_ident,
+                self.tenant.as_deref(),
+            );
 
             async move {
                 // reload the table to get the latest state
                 let table = catalog.load_table(&table_ident).await?;
 
+                // Output files were written against the basic schema, so a schema change can't
+                // be fixed by retrying.
                 let schema_id = table.metadata().current_schema().schema_id();
                 if schema_id != self.basic_schema_id {
-                    return Err(iceberg::Error::new(
-                        ErrorKind::DataInvalid,
-                        format!(
-                            "Schema ID mismatch: expected {}, found {}",
-                            self.basic_schema_id, schema_id
-                        ),
-                    ));
+                    return Err(CompactionError::SchemaChangedDuringCompaction {
+                        expected: self.basic_schema_id,
+                        found: schema_id,
+                    });
+                }
+                // Concurrent commits may have removed files this rewrite replaces, or added
+                // deletes for them, which can't be fixed by retrying either.
+                if table.metadata().current_snapshot_id() != Some(starting_snapshot_id) {
+                    check_concurrent_changes(
+                        &table,
+                        starting_snapshot_id,
+                        &delete_files,
+                        use_starting_sequence_number || sequence_number.is_some(),
+                    )
+                    .await?;
                 }
 
-                let txn = Transaction::new(&table);
-
-                // TODO: support validation of data files and delete files with starting snapshot before applying the rewrite
-                let rewrite_action = if use_starting_sequence_number {
-                    // TODO: avoid retry if the snapshot_id is not found
-                    if let Some(snapshot) = table.metadata().snapshot_by_id(starting_snapshot_id) {
-                        txn.rewrite_files(None, vec![])?
-                            .add_data_files(data_files)?
-                            .delete_files(delete_files)?
-                            .new_data_file_sequence_number(snapshot.sequence_number())?
-                    } else {
-                        return Err(iceberg::Error::new(
-                            ErrorKind::Unexpected,
-                            format!(
-                                "No snapshot found with the given snapshot_id {}",
-                                starting_snapshot_id
-                            ),
-                        ));
+                let txn = match sequence_number {
+                    Some(sequence_number) => {
+                        build_rewrite_transaction_with_sequence_number(
+                            &table,
+                            data_files,
+                            delete_files,
+                            Some(sequence_number),
+                        )
+                        .await?
+                    }
+                    None => {
+                        build_rewrite_transaction(
+                            &table,
+                            data_files,
+                            delete_files,
+                            use_starting_sequence_number.then_some(starting_snapshot_id),
+                        )
+                        .await?
                     }
-                } else {
-                    txn.rewrite_files(None, vec![])?
-                        .add_data_files(data_files)?
-                        .delete_files(delete_files)?
                 };
-
-                let txn = rewrite_action.apply().await?;
+                commit_hook::before_commit(&self.commit_hooks, &table, &txn).await?;
                 match txn.commit(catalog.as_ref()).await {
                     Ok(table) => {
                         // Update metrics after a successful commit
@@ -586,7 +2796,7 @@ impl RewriteDataFilesCommitManager {
                             table_ident,
                             commit_err
                         );
-                        Err(commit_err)
+                        Err(commit_err.into())
                     }
                 }
             }
@@ -597,31 +2807,55 @@ impl RewriteDataFilesCommitManager {
             .with_max_delay(self.config.retry_max_delay)
             .with_max_times(self.config.max_retries as usize);
 
-        operation
+        let table = operation
             .retry(retry_strategy)
-            .when(|e| {
-                matches!(e.kind(), iceberg::ErrorKind::DataInvalid)
-                    || matches!(e.kind(), iceberg::ErrorKind::Unexpected)
+            .when(|e| match e {
+                CompactionError::Iceberg(e) => {
+                    matches!(e.kind(), iceberg::ErrorKind::DataInvalid)
+                        || matches!(e.kind(), iceberg::ErrorKind::Unexpected)
+                }
+                _ => false,
             })
             .notify(|e, d| {
                 // Notify the user about the error
                 // TODO: add metrics
                 tracing::info!("Retrying Compaction failed {:?} after {:?}", e, d);
             })
-            .await
-            .map_err(|e: iceberg::Error| CompactionError::from(e)) // Convert backon::Error to your CompactionError
+            .await?;
+        commit_hook::after_commit(&self.commit_hooks, &table).await;
+        Ok(table)
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::compaction::CompactionBuilder;
-    use crate::config::CompactionConfigBuilder;
+    use crate::common::{Metrics, MetricsLabelConfig, OVERFLOW_LABEL_VALUE};
+    use crate::compaction::build_rewrite_transaction;
+    use crate::compaction::commit_hook::CommitHook;
+    use crate::compaction::{
+        check_delete_file_specs, cutoff_ms, get_old_files_from_table,
+        get_recently_appended_data_files, get_tasks_from_table, golden, history, merge_tasks,
+        no_op_reason, remove_delete_files, select_tasks, split_tasks_for_commits, Compaction,
+        CompactionBuilder, CompactionType, SkipReason,
+    };
+    use crate::config::{CompactionConfigBuilder, UnsupportedContentTypePolicy};
+    use crate::executor::datafusion::datafusion_processor::{
+        DataFusionTaskContext, DatafusionProcessor,
+    };
+    use crate::executor::InputFileScanTasks;
+    use crate::maintenance::{find_duplicate_data_files, ExpireSnapshotsOptions};
+    use crate::test_utils::file_scan_task;
+    use crate::CompactionError;
     use datafusion::arrow::array::{Int32Array, StringArray};
     use datafusion::arrow::record_batch::RecordBatch;
+    use futures::StreamExt;
     use iceberg::arrow::schema_to_arrow_schema;
+    use iceberg::expr::Reference;
     use iceberg::io::FileIOBuilder;
-    use iceberg::spec::{NestedField, PrimitiveType, Schema, Type};
+    use iceberg::scan::FileScanTask;
+    use iceberg::spec::{
+        DataContentType, DataFile, Datum, NestedField, PrimitiveType, Schema, Snapshot, Type,
+    };
     use iceberg::table::Table;
     use iceberg::transaction::Transaction;
     use iceberg::writer::base_writer::equality_delete_writer::{
@@ -643,9 +2877,11 @@ mod tests {
     use iceberg::{Catalog, NamespaceIdent, TableCreation, TableIdent};
     use iceberg_catalog_memory::MemoryCatalog;
     use itertools::Itertools;
+    use mixtrics::registry::noop::NoopMetricsRegistry;
     use parquet::file::properties::WriterProperties;
-    use std::collections::HashMap;
-    use std::sync::Arc;
+    use std::collections::{HashMap, HashSet};
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
     use tempfile::TempDir;
     use uuid::Uuid;
 
@@ -804,6 +3040,321 @@ mod tests {
         delta_builder.build().await.unwrap()
     }
 
+    /// A table `test_namespace.test_table` of `simple_table_schema` in a memory catalog.
+    struct TestTable {
+        // Removes the warehouse when dropped.
+        _temp_dir: TempDir,
+        warehouse_location: String,
+        catalog: Arc<MemoryCatalog>,
+        table_ident: TableIdent,
+    }
+
+    impl TestTable {
+        async fn load(&self) -> Table {
+            self.catalog.load_table(&self.table_ident).await.unwrap()
+        }
+
+        /// A builder of compactions of the table with the default config.
+        fn compaction(&self) -> CompactionBuilder {
+            CompactionBuilder::new()
+                .with_catalog(self.catalog.clone())
+                .with_table_ident(self.table_ident.clone())
+                .with_config(CompactionConfigBuilder::default().build().unwrap())
+        }
+
+        /// Writes each of `writer_batches` with its own equality delta writer keyed on `id`, and
+        /// appends all written files in a single commit. Returns the written files.
+        async fn append(&self, writer_batches: Vec<Vec<RecordBatch>>) -> Vec<DataFile> {
+            let table = self.load().await;
+            let mut data_files = vec![];
+            for batches in writer_batches {
+                let mut writer =
+                    build_equality_delta_writer(&table, self.warehouse_location.clone(), vec![1])
+                        .await;
+                for batch in batches {
+                    writer.write(batch).await.unwrap();
+                }
+                data_files.extend(writer.close().await.unwrap());
+            }
+            let transaction = Transaction::new(&table);
+            let mut append_action = transaction.fast_append(None, None, vec![]).unwrap();
+            append_action.add_data_files(data_files.clone()).unwrap();
+            let tx = append_action.apply().await.unwrap();
+            tx.commit(self.catalog.as_ref()).await.unwrap();
+            data_files
+        }
+
+        /// Appends a commit inserting rows 1, 2 and 3, deleting them and inserting them again,
+        /// i.e. a data file of six rows and a position delete file of the first three.
+        async fn append_rows(&self) -> Vec<DataFile> {
+            let schema = simple_table_schema_with_pos();
+            let insert_batch = create_test_record_batch_with_pos(&schema, true);
+            let delete_batch = create_test_record_batch_with_pos(&schema, false);
+            self.append(vec![vec![insert_batch.clone(), delete_batch, insert_batch]])
+                .await
+        }
+    }
+
+    /// Creates an empty table in a new memory catalog.
+    async fn setup_table() -> TestTable {
+        let temp_dir = TempDir::new().unwrap();
+        let warehouse_location = temp_dir.path().to_str().unwrap().to_string();
+        let file_io = FileIOBuilder::new_fs_io().build().unwrap();
+        let catalog = Arc::new(MemoryCatalog::new(
+            file_io,
+            Some(warehouse_location.clone()),
+        ));
+
+        let namespace_ident = NamespaceIdent::new("test_namespace".into());
+        create_namespace(catalog.as_ref(), &namespace_ident).await;
+        let table_ident = TableIdent::new(namespace_ident, "test_table".into());
+        create_table(catalog.as_ref(), &table_ident).await;
+        TestTable {
+            _temp_dir: temp_dir,
+            warehouse_location,
+            catalog,
+            table_ident,
+        }
+    }
+
+    /// Creates a table with the rows of a single `TestTable::append_rows` commit.
+    async fn setup_table_with_rows() -> TestTable {
+        let test_table = setup_table().await;
+        test_table.append_rows().await;
+        test_table
+    }
+
+    /// Rows of the current snapshot of `table` with its deletes applied, as sorted `(id, name)`.
+    async fn scan_table_rows(table: &Table) -> Vec<(i32, String)> {
+        let (tasks, _) = get_tasks_from_table(table.clone(), UnsupportedContentTypePolicy::Fail)
+            .await
+            .unwrap();
+        if tasks.data_files.is_empty() {
+            return vec![];
+        }
+        let task_ctx = DataFusionTaskContext::builder()
+            .unwrap()
+            .with_schema(table.metadata().current_schema().clone())
+            .with_input_data_files(tasks)
+            .build()
+            .unwrap();
+        let config = Arc::new(CompactionConfigBuilder::default().build().unwrap());
+        let (streams, _) = DatafusionProcessor::new(config, table.file_io().clone())
+            .execute(task_ctx)
+            .await
+            .unwrap();
+
+        let mut rows = vec![];
+        for mut stream in streams {
+            while let Some(batch) = stream.next().await {
+                let batch = batch.unwrap();
+                let ids = batch.column_by_name("id").unwrap();
+                let ids = ids.as_any().downcast_ref::<Int32Array>().unwrap();
+                let names = batch.column_by_name("name").unwrap();
+                let names = names.as_any().downcast_ref::<StringArray>().unwrap();
+                rows.extend(
+                    ids.iter()
+                        .zip(names.iter())
+                        .map(|(id, name)| (id.unwrap(), name.unwrap().to_owned())),
+                );
+            }
+        }
+        rows.sort();
+        rows
+    }
+
+    #[test]
+    fn test_select_tasks_retains_shared_deletes() {
+        let shared_delete = file_scan_task(
+            "shared-pos-del",
+            DataContentType::PositionDeletes,
+            1,
+            vec![],
+        );
+        let own_delete = file_scan_task("own-eq-del", DataContentType::EqualityDeletes, 1, vec![]);
+        let tasks = InputFileScanTasks {
+            data_files: vec![
+                file_scan_task(
+                    "small",
+                    DataContentType::Data,
+                    10,
+                    vec![shared_delete.clone(), own_delete.clone()],
+                ),
+                file_scan_task(
+                    "large",
+                    DataContentType::Data,
+                    1000,
+                    vec![shared_delete.clone()],
+                ),
+            ],
+            position_delete_files: vec![shared_delete],
+            equality_delete_files: vec![own_delete],
+        };
+
+        let (selected, retained) = select_tasks(tasks, |task| task.file_size_in_bytes < 100);
+
+        assert_eq!(selected.data_files.len(), 1);
+        assert_eq!(selected.data_files[0].data_file_path, "small");
+        // Both deletes are applied while rewriting the small file...
+        assert_eq!(selected.position_delete_files.len(), 1);
+        assert_eq!(selected.equality_delete_files.len(), 1);
+        // ...but the shared one must stay for the untouched large file.
+        assert_eq!(
+            retained,
+            HashSet::from(["large".to_owned(), "shared-pos-del".to_owned()])
+        );
+    }
+
+    #[test]
+    fn test_remove_delete_files() {
+        let dangling_delete = file_scan_task(
+            "dangling-pos-del",
+            DataContentType::PositionDeletes,
+            1,
+            vec![],
+        );
+        let live_delete =
+            file_scan_task("live-pos-del", DataContentType::PositionDeletes, 1, vec![]);
+        let mut tasks = InputFileScanTasks {
+            data_files: vec![file_scan_task(
+                "data",
+                DataContentType::Data,
+                10,
+                vec![dangling_delete.clone(), live_delete.clone()],
+            )],
+            position_delete_files: vec![dangling_delete, live_delete],
+            equality_delete_files: vec![],
+        };
+
+        remove_delete_files(&mut tasks, &HashSet::from(["dangling-pos-del".to_owned()]));
+
+        assert_eq!(tasks.position_delete_files.len(), 1);
+        assert_eq!(
+            tasks.position_delete_files[0].data_file_path,
+            "live-pos-del"
+        );
+        assert_eq!(tasks.data_files[0].deletes.len(), 1);
+        assert_eq!(
+            tasks.data_files[0].deletes[0].data_file_path,
+            "live-pos-del"
+        );
+    }
+
+    #[test]
+    fn test_split_tasks_for_commits() {
+        let shared_delete = file_scan_task(
+            "shared-pos-del",
+            DataContentType::PositionDeletes,
+            1,
+            vec![],
+        );
+        let tasks = InputFileScanTasks {
+            data_files: vec![
+                file_scan_task("a", DataContentType::Data, 10, vec![shared_delete.clone()]),
+                file_scan_task("b", DataContentType::Data, 10, vec![]),
+                file_scan_task("c", DataContentType::Data, 10, vec![shared_delete.clone()]),
+            ],
+            position_delete_files: vec![shared_delete],
+            equality_delete_files: vec![],
+        };
+
+        let paths = |paths: &[&str]| -> HashSet<String> {
+            paths.iter().map(|path| path.to_string()).collect()
+        };
+        let commits = split_tasks_for_commits(
+            tasks,
+            vec![vec![paths(&["a"]), paths(&["b"])], vec![paths(&["c"])]],
+        );
+
+        assert_eq!(commits.len(), 2);
+        let (first, first_retained) = &commits[0];
+        assert_eq!(first.len(), 2);
+        assert_eq!(first[0].data_files[0].data_file_path, "a");
+        assert_eq!(first[0].position_delete_files.len(), 1);
+        assert!(first[1].position_delete_files.is_empty());
+        // The shared delete must survive the first commit for the file in the second commit.
+        assert_eq!(
+            *first_retained,
+            HashSet::from(["c".to_owned(), "shared-pos-del".to_owned()])
+        );
+        let (second, second_retained) = &commits[1];
+        assert_eq!(second.len(), 1);
+        assert_eq!(second[0].data_files.len(), 1);
+        assert_eq!(second[0].position_delete_files.len(), 1);
+        assert!(second_retained.is_empty());
+
+        let merged = merge_tasks(commits.into_iter().flat_map(|(groups, _)| groups).collect());
+        assert_eq!(merged.data_files.len(), 3);
+        assert_eq!(merged.position_delete_files.len(), 1);
+    }
+
+    #[test]
+    fn test_cutoff_ms() {
+        assert_eq!(cutoff_ms(10_000, Duration::from_secs(3)).unwrap(), 7_000);
+        assert_eq!(
+            cutoff_ms(10_000, Duration::from_millis(i64::MAX as u64)).unwrap(),
+            10_000 - i64::MAX
+        );
+        assert!(matches!(
+            cutoff_ms(10_000, Duration::MAX),
+            Err(CompactionError::Config(_))
+        ));
+    }
+
+    #[test]
+    fn test_no_op_reason() {
+        let config = CompactionConfigBuilder::default()
+            .target_file_size(100)
+            .build()
+            .unwrap();
+        let tasks = |sizes: &[u64]| InputFileScanTasks {
+            data_files: sizes
+                .iter()
+                .enumerate()
+                .map(|(i, &size)| {
+                    file_scan_task(&i.to_string(), DataContentType::Data, size, vec![])
+                })
+                .collect(),
+            position_delete_files: vec![],
+            equality_delete_files: vec![],
+        };
+
+        assert_eq!(
+            no_op_reason(&tasks(&[]), &config, true),
+            Some(SkipReason::NoFilesSelected)
+        );
+        assert_eq!(
+            no_op_reason(&tasks(&[10]), &config, true),
+            Some(SkipReason::SingleFile)
+        );
+        assert_eq!(
+            no_op_reason(&tasks(&[100, 150]), &config, true),
+            Some(SkipReason::FilesAtTargetSize)
+        );
+        assert_eq!(no_op_reason(&tasks(&[10, 150]), &config, true), None);
+
+        // Sorting makes rewriting a single file worthwhile, but only for a full compaction.
+        let sorting_config = CompactionConfigBuilder::default()
+            .target_file_size(100)
+            .enable_sort_order(true)
+            .build()
+            .unwrap();
+        assert_eq!(no_op_reason(&tasks(&[10]), &sorting_config, true), None);
+        assert_eq!(
+            no_op_reason(&tasks(&[10]), &sorting_config, false),
+            Some(SkipReason::SingleFile)
+        );
+
+        let mut with_deletes = tasks(&[10]);
+        with_deletes.position_delete_files.push(file_scan_task(
+            "pos-del",
+            DataContentType::PositionDeletes,
+            1,
+            vec![],
+        ));
+        assert_eq!(no_op_reason(&with_deletes, &config, false), None);
+    }
+
     #[tokio::test]
     async fn test_write_commit_and_compaction() {
         // Create a temporary directory for the warehouse location
@@ -884,4 +3435,1067 @@ mod tests {
 
         assert_eq!(rewrite_files_stat.rewritten_files_count, 2);
     }
+
+    #[tokio::test]
+    async fn test_builder_with_owned_catalog_and_config() {
+        let temp_dir = TempDir::new().unwrap();
+        let warehouse_location = temp_dir.path().to_str().unwrap().to_string();
+        let file_io = FileIOBuilder::new_fs_io().build().unwrap();
+        let catalog = MemoryCatalog::new(file_io, Some(warehouse_location));
+
+        let namespace_ident = NamespaceIdent::new("test_namespace".into());
+        create_namespace(&catalog, &namespace_ident).await;
+        let table_ident = TableIdent::new(namespace_ident, "test_table".into());
+        create_table(&catalog, &table_ident).await;
+
+        let compaction = CompactionBuilder::new()
+            .with_owned_catalog(catalog)
+            .with_table_ident(table_ident.clone())
+            .with_config(
+                CompactionConfigBuilder::default()
+                    .target_file_size(1024)
+                    .build()
+                    .unwrap(),
+            )
+            .build()
+            .await
+            .unwrap();
+
+        assert_eq!(compaction.config.target_file_size, 1024);
+        assert!(compaction.catalog.table_exists(&table_ident).await.unwrap());
+        let rewrite_files_stat = compaction.compact().await.unwrap();
+        assert_eq!(rewrite_files_stat.skipped, Some(SkipReason::NoSnapshot));
+    }
+
+    #[tokio::test]
+    async fn test_full_compaction_golden_metadata() {
+        let test_table = setup_table_with_rows().await;
+
+        test_table
+            .compaction()
+            .build()
+            .await
+            .unwrap()
+            .compact()
+            .await
+            .unwrap();
+
+        let table = test_table.load().await;
+        golden::assert_golden("full_compaction", &golden::table_digest(&table).await);
+
+        let replace_snapshots = history::replace_snapshots(table.metadata());
+        assert_eq!(replace_snapshots.len(), 1);
+        assert_eq!(
+            Some(replace_snapshots[0].snapshot_id),
+            table.metadata().current_snapshot_id()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_delete_files_compaction_golden_metadata() {
+        let test_table = setup_table_with_rows().await;
+        test_table.append_rows().await;
+
+        test_table
+            .compaction()
+            .with_compaction_type(CompactionType::DeleteFiles)
+            .build()
+            .await
+            .unwrap()
+            .compact()
+            .await
+            .unwrap();
+
+        let table = test_table.load().await;
+        golden::assert_golden(
+            "delete_files_compaction",
+            &golden::table_digest(&table).await,
+        );
+    }
+
+    #[tokio::test]
+    async fn test_partial_compaction_golden_metadata() {
+        let test_table = setup_table().await;
+        for _ in 0..4 {
+            append_data_file(&test_table).await;
+        }
+
+        let config = CompactionConfigBuilder::default()
+            .max_files_per_group(2)
+            .partial_progress_enabled(true)
+            .partial_progress_max_commits(2)
+            .build()
+            .unwrap();
+        test_table
+            .compaction()
+            .with_config(config)
+            .build()
+            .await
+            .unwrap()
+            .compact()
+            .await
+            .unwrap();
+
+        let table = test_table.load().await;
+        golden::assert_golden("partial_compaction", &golden::table_digest(&table).await);
+    }
+
+    #[tokio::test]
+    async fn test_plan_does_not_commit() {
+        let test_table = setup_table_with_rows().await;
+        let table = test_table.load().await;
+
+        let compaction = test_table.compaction().build().await.unwrap();
+        let plan = compaction.plan(&CompactionType::Full).await.unwrap();
+
+        assert_eq!(plan.skipped, None);
+        assert_eq!(plan.snapshot_id, table.metadata().current_snapshot_id());
+        assert_eq!(plan.file_groups.len(), 1);
+        assert!(plan.data_files_count() > 0);
+        assert_eq!(plan.expected_output_files_count, 1);
+        assert!(plan.estimated_read_bytes >= plan.estimated_write_bytes);
+        let table = test_table.load().await;
+        assert_eq!(plan.snapshot_id, table.metadata().current_snapshot_id());
+    }
+
+    #[tokio::test]
+    async fn test_plan_file_decisions() {
+        let test_table = setup_table().await;
+        let mut data_file_paths = vec![];
+        for _ in 0..3 {
+            data_file_paths.push(append_data_file(&test_table).await);
+        }
+
+        let compaction = test_table.compaction().build().await.unwrap();
+        let plan = compaction
+            .plan(&CompactionType::Files {
+                data_file_paths: data_file_paths[..2].to_vec(),
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(plan.file_groups.len(), 1);
+        assert_eq!(plan.file_decisions.len(), 3);
+        for path in &data_file_paths[..2] {
+            assert_eq!(
+                plan.file_decisions[path],
+                FileDecision::Rewritten { file_group: 0 }
+            );
+        }
+        assert_eq!(
+            plan.file_decisions[&data_file_paths[2]],
+            FileDecision::Retained(RetainReason::NotSelected)
+        );
+
+        let explained = plan.explain();
+        assert!(explained.starts_with(&format!(
+            "CompactionPlan of snapshot {}\n",
+            plan.snapshot_id.unwrap()
+        )));
+        assert!(explained.contains("  file group 0: 2 data files, "));
+        for path in &data_file_paths[..2] {
+            assert!(explained.contains(&format!("    rewrite {}\n", path)));
+        }
+        assert!(explained.contains(&format!("  retain {} (not selected)\n", data_file_paths[2])));
+
+        let plan = compaction.plan(&CompactionType::Full).await.unwrap();
+        assert_eq!(plan.skipped, None);
+        assert!(plan
+            .file_decisions
+            .values()
+            .all(|decision| *decision == FileDecision::Rewritten { file_group: 0 }));
+    }
+
+    #[tokio::test]
+    async fn test_retry_failed() {
+        let test_table = setup_table().await;
+        let mut data_file_paths = vec![];
+        for _ in 0..3 {
+            data_file_paths.push(append_data_file(&test_table).await);
+        }
+        let failed = data_file_paths[..2].to_vec();
+        let error = CompactionError::FileGroup {
+            group_id: 1,
+            data_file_paths: failed.clone(),
+            source: Box::new(CompactionError::Execution("out of memory".to_owned())),
+        };
+
+        let table = test_table.load().await;
+        let rows_before = scan_table_rows(&table).await;
+        let files_before = live_data_file_paths(&table).await;
+        let compaction = test_table.compaction().build().await.unwrap();
+        compaction.retry_failed(&error).await.unwrap();
+
+        // Only the files of the failed group are rewritten.
+        let table = test_table.load().await;
+        assert_eq!(scan_table_rows(&table).await, rows_before);
+        let files_after = live_data_file_paths(&table).await;
+        assert_eq!(
+            files_before
+                .difference(&files_after)
+                .cloned()
+                .collect::<HashSet<_>>(),
+            failed.into_iter().collect()
+        );
+        assert!(files_after.contains(&data_file_paths[2]));
+
+        let result = compaction
+            .retry_failed(&CompactionError::Execution("out of memory".to_owned()))
+            .await;
+        assert!(matches!(result, Err(CompactionError::InvalidRequest(_))));
+    }
+
+    #[tokio::test]
+    async fn test_get_recently_appended_data_files() {
+        let test_table = setup_table().await;
+
+        let mut appended_data_file_paths = vec![];
+        for _ in 0..2 {
+            let insert_batch =
+                create_test_record_batch_with_pos(&simple_table_schema_with_pos(), true);
+            let data_files = test_table.append(vec![vec![insert_batch]]).await;
+            appended_data_file_paths.push(
+                data_files
+                    .iter()
+                    .filter(|f| f.content_type() == DataContentType::Data)
+                    .map(|f| f.file_path().to_owned())
+                    .collect::<HashSet<_>>(),
+            );
+        }
+        let table = test_table.load().await;
+
+        assert_eq!(
+            get_recently_appended_data_files(&table, 1).await.unwrap(),
+            vec![appended_data_file_paths[1].clone()]
+        );
+        let all_file_paths: HashSet<String> =
+            appended_data_file_paths.into_iter().flatten().collect();
+        assert_eq!(
+            get_recently_appended_data_files(&table, 5).await.unwrap(),
+            vec![all_file_paths]
+        );
+        assert!(get_recently_appended_data_files(&table, 0)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[derive(Default)]
+    struct RecordingCommitHook {
+        before_commit_snapshot_ids: Mutex<Vec<Option<i64>>>,
+        committed_snapshot_ids: Mutex<Vec<i64>>,
+    }
+
+    #[async_trait::async_trait]
+    impl CommitHook for RecordingCommitHook {
+        async fn before_commit(
+            &self,
+            table: &Table,
+            _transaction: &Transaction<'_>,
+        ) -> crate::Result<()> {
+            self.before_commit_snapshot_ids
+                .lock()
+                .unwrap()
+                .push(table.metadata().current_snapshot_id());
+            Ok(())
+        }
+
+        async fn after_commit(&self, _table: &Table, snapshot: &Snapshot) {
+            self.committed_snapshot_ids
+                .lock()
+                .unwrap()
+                .push(snapshot.snapshot_id());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_shared_metrics() {
+        let temp_dir = TempDir::new().unwrap();
+        let warehouse_location = temp_dir.path().to_str().unwrap().to_string();
+        let file_io = FileIOBuilder::new_fs_io().build().unwrap();
+        let catalog = MemoryCatalog::new(file_io, Some(warehouse_location));
+        let namespace_ident = NamespaceIdent::new("test_namespace".into());
+        create_namespace(&catalog, &namespace_ident).await;
+        let table_idents: Vec<_> = (0..3)
+            .map(|i| TableIdent::new(namespace_ident.clone(), format!("table_{}", i)))
+            .collect();
+        for table_ident in &table_idents {
+            create_table(&catalog, table_ident).await;
+        }
+
+        let catalog = Arc::new(catalog);
+        let metrics = Arc::new(Metrics::new_with_label_config(
+            Box::new(NoopMetricsRegistry),
+            MetricsLabelConfig {
+                max_cardinality: Some(2),
+                ..Default::default()
+            },
+        ));
+        let mut label_values = vec![];
+        for table_ident in &table_idents {
+            let compaction = CompactionBuilder::new()
+                .with_catalog(catalog.clone())
+                .with_table_ident(table_ident.clone())
+                .with_config(CompactionConfigBuilder::default().build().unwrap())
+                .with_metrics(metrics.clone())
+                .build()
+                .await
+                .unwrap();
+            assert!(Arc::ptr_eq(&compaction.metrics, &metrics));
+            label_values.push(metrics.label_values("", table_ident, None)[1].clone());
+        }
+        assert_eq!(
+            label_values,
+            vec![
+                "test_namespace.table_0",
+                "test_namespace.table_1",
+                OVERFLOW_LABEL_VALUE
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_commit_hooks() {
+        let test_table = setup_table_with_rows().await;
+        let table = test_table.load().await;
+
+        let commit_hook = Arc::new(RecordingCommitHook::default());
+        let compaction = test_table
+            .compaction()
+            .with_commit_hook(commit_hook.clone())
+            .build()
+            .await
+            .unwrap();
+        compaction.compact().await.unwrap();
+
+        let table_after = test_table.load().await;
+        assert_eq!(
+            *commit_hook.before_commit_snapshot_ids.lock().unwrap(),
+            vec![table.metadata().current_snapshot_id()]
+        );
+        assert_eq!(
+            commit_hook
+                .committed_snapshot_ids
+                .lock()
+                .unwrap()
+                .iter()
+                .copied()
+                .map(Some)
+                .collect::<Vec<_>>(),
+            vec![table_after.metadata().current_snapshot_id()]
+        );
+    }
+
+    /// Rejects every commit with an error that would otherwise be retried.
+    #[derive(Default)]
+    struct RejectingCommitHook {
+        calls: Mutex<usize>,
+    }
+
+    #[async_trait::async_trait]
+    impl CommitHook for RejectingCommitHook {
+        async fn before_commit(
+            &self,
+            _table: &Table,
+            _transaction: &Transaction<'_>,
+        ) -> crate::Result<()> {
+            *self.calls.lock().unwrap() += 1;
+            Err(iceberg::Error::new(iceberg::ErrorKind::DataInvalid, "rejected").into())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rejecting_commit_hook_is_not_retried() {
+        let test_table = setup_table_with_rows().await;
+        let snapshot_id = test_table.load().await.metadata().current_snapshot_id();
+
+        let commit_hook = Arc::new(RejectingCommitHook::default());
+        let result = test_table
+            .compaction()
+            .with_commit_hook(commit_hook.clone())
+            .build()
+            .await
+            .unwrap()
+            .compact()
+            .await;
+        assert!(matches!(result, Err(CompactionError::CommitHook(_))));
+        assert_eq!(*commit_hook.calls.lock().unwrap(), 1);
+        assert_eq!(
+            test_table.load().await.metadata().current_snapshot_id(),
+            snapshot_id
+        );
+    }
+
+    #[tokio::test]
+    async fn test_compact_delete_files() {
+        // Each commit adds a data file and a position delete file.
+        let test_table = setup_table_with_rows().await;
+        test_table.append_rows().await;
+
+        let rows_before = scan_table_rows(&test_table.load().await).await;
+        let rewrite_files_stat = test_table
+            .compaction()
+            .with_compaction_type(CompactionType::DeleteFiles)
+            .build()
+            .await
+            .unwrap()
+            .compact()
+            .await
+            .unwrap();
+
+        assert_eq!(rewrite_files_stat.rewritten_files_count, 2);
+        assert_eq!(rewrite_files_stat.added_files_count, 1);
+
+        let table = test_table.load().await;
+        assert_eq!(scan_table_rows(&table).await, rows_before);
+        let (data_files, delete_files) = get_old_files_from_table(table).await.unwrap();
+        assert_eq!(data_files.len(), 2);
+        assert_eq!(delete_files.len(), 1);
+        // Each data file had three rows deleted.
+        assert_eq!(delete_files[0].record_count(), 6);
+    }
+
+    #[test]
+    fn test_check_delete_file_specs() {
+        assert!(check_delete_file_specs(1, &[(1, "a".to_owned()), (1, "b".to_owned())]).is_ok());
+        // After the table moved from partition spec 0 to unpartitioned spec 1, delete files
+        // scoped to a partition of spec 0 must not be merged into a global one.
+        assert!(matches!(
+            check_delete_file_specs(1, &[(1, "a".to_owned()), (0, "b".to_owned())]),
+            Err(CompactionError::UnsupportedTableFeature(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_compact_delete_files_drops_stale_deletes() {
+        let test_table = setup_table().await;
+        let batch = |insert: bool| {
+            create_test_record_batch_with_pos(&simple_table_schema_with_pos(), insert)
+        };
+
+        // Deleting the row with id 2 twice, converting each equality delete on its own,
+        // leaves two position delete files deleting the same row.
+        test_table.append(vec![vec![batch(true)]]).await;
+        for _ in 0..2 {
+            test_table
+                .append(vec![vec![batch(false).slice(1, 1)]])
+                .await;
+            test_table
+                .compaction()
+                .build()
+                .await
+                .unwrap()
+                .convert_equality_deletes()
+                .await
+                .unwrap();
+        }
+        // Deleting the row with id 3 from both data files, then rewriting the second data file,
+        // leaves a position delete file also deleting a row of a removed data file.
+        let second_data_file_path = test_table
+            .append(vec![vec![batch(true)]])
+            .await
+            .iter()
+            .find(|file| file.content_type() == DataContentType::Data)
+            .unwrap()
+            .file_path()
+            .to_owned();
+        test_table
+            .append(vec![vec![batch(false).slice(2, 1)]])
+            .await;
+        test_table
+            .compaction()
+            .build()
+            .await
+            .unwrap()
+            .convert_equality_deletes()
+            .await
+            .unwrap();
+        test_table
+            .compaction()
+            .with_compaction_type(CompactionType::Files {
+                data_file_paths: vec![second_data_file_path.clone()],
+            })
+            .build()
+            .await
+            .unwrap()
+            .compact()
+            .await
+            .unwrap();
+
+        let table = test_table.load().await;
+        let rows_before = scan_table_rows(&table).await;
+        assert_eq!(
+            rows_before,
+            vec![
+                (1, "Alice".to_owned()),
+                (1, "Alice".to_owned()),
+                (2, "Bob".to_owned())
+            ]
+        );
+        let (data_files, delete_files) = get_old_files_from_table(table).await.unwrap();
+        assert!(data_files
+            .iter()
+            .all(|file| file.file_path() != second_data_file_path));
+        assert_eq!(delete_files.len(), 3);
+
+        let rewrite_files_stat = test_table
+            .compaction()
+            .with_compaction_type(CompactionType::DeleteFiles)
+            .build()
+            .await
+            .unwrap()
+            .compact()
+            .await
+            .unwrap();
+
+        assert_eq!(rewrite_files_stat.rewritten_files_count, 3);
+        assert_eq!(rewrite_files_stat.added_files_count, 1);
+        let table = test_table.load().await;
+        assert_eq!(scan_table_rows(&table).await, rows_before);
+        let (_, delete_files) = get_old_files_from_table(table).await.unwrap();
+        assert_eq!(delete_files.len(), 1);
+        // Only the deletes of rows 2 and 3 of the first data file are left.
+        assert_eq!(delete_files[0].record_count(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_compact_equality_delete_files() {
+        let test_table = setup_table().await;
+        let batch = |insert: bool| {
+            create_test_record_batch_with_pos(&simple_table_schema_with_pos(), insert)
+        };
+
+        test_table.append(vec![vec![batch(true)]]).await;
+        // Two equality delete files of the same commit, both deleting the row with id 2.
+        test_table
+            .append(vec![
+                vec![batch(false).slice(1, 1)],
+                vec![batch(false).slice(1, 2)],
+            ])
+            .await;
+        let table = test_table.load().await;
+        let delete_sequence_number = table
+            .metadata()
+            .current_snapshot()
+            .unwrap()
+            .sequence_number();
+        let rows_before = scan_table_rows(&table).await;
+        assert_eq!(rows_before, vec![(1, "Alice".to_owned())]);
+
+        let rewrite_files_stat = test_table
+            .compaction()
+            .with_compaction_type(CompactionType::DeleteFiles)
+            .build()
+            .await
+            .unwrap()
+            .compact()
+            .await
+            .unwrap();
+
+        assert_eq!(rewrite_files_stat.rewritten_files_count, 2);
+        assert_eq!(rewrite_files_stat.added_files_count, 1);
+        let table = test_table.load().await;
+        assert_eq!(scan_table_rows(&table).await, rows_before);
+
+        // The merged file keeps the sequence number of the files it replaces, so that it still
+        // only applies to the data files written before them.
+        let manifest_list = table
+            .metadata()
+            .current_snapshot()
+            .unwrap()
+            .load_manifest_list(table.file_io(), table.metadata())
+            .await
+            .unwrap();
+        let mut equality_deletes = vec![];
+        for manifest_file in manifest_list.entries() {
+            let manifest = manifest_file.load_manifest(table.file_io()).await.unwrap();
+            equality_deletes.extend(
+                manifest
+                    .entries()
+                    .iter()
+                    .filter(|entry| {
+                        entry.is_alive() && entry.content_type() == DataContentType::EqualityDeletes
+                    })
+                    .map(|entry| (entry.sequence_number(), entry.data_file().record_count())),
+            );
+        }
+        assert_eq!(equality_deletes, vec![(Some(delete_sequence_number), 2)]);
+    }
+
+    #[tokio::test]
+    async fn test_convert_equality_deletes() {
+        let test_table = setup_table().await;
+
+        // The first commit inserts rows, the second deletes one of them by key with an equality
+        // delete.
+        let batch = |insert: bool| {
+            create_test_record_batch_with_pos(&simple_table_schema_with_pos(), insert)
+        };
+        test_table.append(vec![vec![batch(true)]]).await;
+        test_table
+            .append(vec![vec![batch(false).slice(1, 1)]])
+            .await;
+        let rows_before = scan_table_rows(&test_table.load().await).await;
+        assert_eq!(
+            rows_before,
+            vec![(1, "Alice".to_owned()), (3, "Charlie".to_owned())]
+        );
+
+        let rewrite_files_stat = test_table
+            .compaction()
+            .build()
+            .await
+            .unwrap()
+            .convert_equality_deletes()
+            .await
+            .unwrap();
+
+        assert_eq!(rewrite_files_stat.rewritten_files_count, 1);
+        assert_eq!(rewrite_files_stat.added_files_count, 1);
+
+        let table = test_table.load().await;
+        assert_eq!(scan_table_rows(&table).await, rows_before);
+        let (_, delete_files) = get_old_files_from_table(table).await.unwrap();
+        assert_eq!(delete_files.len(), 1);
+        assert_eq!(
+            delete_files[0].content_type(),
+            DataContentType::PositionDeletes
+        );
+        // One position delete per row deleted by the equality delete.
+        assert_eq!(delete_files[0].record_count(), 1);
+    }
+
+    /// Paths of the manifests listed by snapshot `snapshot_id` of `table`.
+    async fn manifest_paths(table: &Table, snapshot_id: i64) -> HashSet<String> {
+        let snapshot = table.metadata().snapshot_by_id(snapshot_id).unwrap();
+        let manifest_list = snapshot
+            .load_manifest_list(table.file_io(), table.metadata())
+            .await
+            .unwrap();
+        manifest_list
+            .entries()
+            .iter()
+            .map(|manifest_file| manifest_file.manifest_path.clone())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_expire_snapshots_collects_unreferenced_files() {
+        let test_table = setup_table().await;
+        let insert_batch =
+            || create_test_record_batch_with_pos(&simple_table_schema_with_pos(), true);
+
+        // Two appends, a compaction rewriting both appended files, and another append.
+        let mut snapshot_ids = vec![];
+        let mut appended_file_paths = vec![];
+        for _ in 0..2 {
+            let data_files = test_table.append(vec![vec![insert_batch()]]).await;
+            appended_file_paths.extend(data_files.iter().map(|f| f.file_path().to_owned()));
+            let table = test_table.load().await;
+            snapshot_ids.push(table.metadata().current_snapshot_id().unwrap());
+        }
+        test_table
+            .compaction()
+            .build()
+            .await
+            .unwrap()
+            .compact()
+            .await
+            .unwrap();
+        test_table.append(vec![vec![insert_batch()]]).await;
+        let table = test_table.load().await;
+        let manifest_lists: Vec<String> = snapshot_ids
+            .iter()
+            .map(|&id| {
+                let snapshot = table.metadata().snapshot_by_id(id).unwrap();
+                snapshot.manifest_list().to_owned()
+            })
+            .collect();
+        let first_manifests = manifest_paths(&table, snapshot_ids[0]).await;
+        let second_manifests = manifest_paths(&table, snapshot_ids[1]).await;
+        // A fast append keeps the manifests of its parent.
+        assert!(first_manifests.is_subset(&second_manifests));
+
+        // Snapshots created before now expire, except for the last ones kept.
+        std::thread::sleep(Duration::from_millis(2));
+        let compaction = test_table.compaction().build().await.unwrap();
+        let options = |retain_last| ExpireSnapshotsOptions {
+            older_than: Some(Duration::ZERO),
+            retain_last: Some(retain_last),
+        };
+
+        // The manifests and files of the first snapshot are still those of the second.
+        let expired_files = compaction
+            .expire_snapshot(test_table.table_ident.clone(), &options(3))
+            .await
+            .unwrap();
+        assert_eq!(expired_files.expired_snapshot_ids, vec![snapshot_ids[0]]);
+        assert_eq!(
+            expired_files.manifest_lists,
+            vec![manifest_lists[0].clone()]
+        );
+        assert!(expired_files.manifests.is_empty());
+        assert!(expired_files.content_files.is_empty());
+
+        // The manifests of the second snapshot were all rewritten by the compaction, and the
+        // files of both appends are no longer live.
+        let expired_files = compaction
+            .expire_snapshot(test_table.table_ident.clone(), &options(2))
+            .await
+            .unwrap();
+        assert_eq!(expired_files.expired_snapshot_ids, vec![snapshot_ids[1]]);
+        assert_eq!(
+            expired_files.manifest_lists,
+            vec![manifest_lists[1].clone()]
+        );
+        assert_eq!(
+            expired_files.manifests.into_iter().collect::<HashSet<_>>(),
+            second_manifests
+        );
+        assert_eq!(
+            expired_files
+                .content_files
+                .into_iter()
+                .collect::<HashSet<_>>(),
+            appended_file_paths.into_iter().collect::<HashSet<_>>()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_repair_duplicate_data_files() {
+        let test_table = setup_table_with_rows().await;
+        let rows = scan_table_rows(&test_table.load().await).await;
+        assert_eq!(rows.len(), 3);
+
+        // Adding a live data file again leaves two live entries of it.
+        let table = test_table.load().await;
+        let sequence_number = table
+            .metadata()
+            .current_snapshot()
+            .unwrap()
+            .sequence_number();
+        let (data_files, _) = get_old_files_from_table(table.clone()).await.unwrap();
+        assert_eq!(data_files.len(), 1);
+        let data_file_path = data_files[0].file_path().to_owned();
+        build_rewrite_transaction(&table, data_files, vec![], None)
+            .await
+            .unwrap()
+            .commit(test_table.catalog.as_ref())
+            .await
+            .unwrap();
+
+        let table = test_table.load().await;
+        let duplicates = find_duplicate_data_files(&table, 4).await.unwrap();
+        assert_eq!(
+            duplicates.snapshot_id,
+            table.metadata().current_snapshot_id()
+        );
+        assert_eq!(duplicates.files.len(), 1);
+        assert_eq!(duplicates.files[0].data_file.file_path(), data_file_path);
+        assert_eq!(duplicates.files[0].occurrences, 2);
+        assert_eq!(duplicates.files[0].sequence_number, Some(sequence_number));
+
+        let compaction = test_table.compaction().build().await.unwrap();
+        let found = compaction
+            .repair_duplicate_data_files(test_table.table_ident.clone(), true)
+            .await
+            .unwrap();
+        assert_eq!(found.files.len(), 1);
+        assert!(found.repaired_sequence_numbers.is_empty());
+        assert_eq!(
+            test_table.load().await.metadata().current_snapshot_id(),
+            table.metadata().current_snapshot_id()
+        );
+
+        let repaired = compaction
+            .repair_duplicate_data_files(test_table.table_ident.clone(), false)
+            .await
+            .unwrap();
+        assert_eq!(
+            repaired.repaired_sequence_numbers,
+            vec![Some(sequence_number)]
+        );
+        let table = test_table.load().await;
+        assert!(find_duplicate_data_files(&table, 4)
+            .await
+            .unwrap()
+            .is_empty());
+        // The entry kept its sequence number, so the position deletes still apply to it.
+        assert_eq!(scan_table_rows(&table).await, rows);
+    }
+
+    /// Paths of the live data files of the current snapshot of `table`.
+    async fn live_data_file_paths(table: &Table) -> HashSet<String> {
+        let (data_files, _) = get_old_files_from_table(table.clone()).await.unwrap();
+        data_files
+            .iter()
+            .map(|data_file| data_file.file_path().to_owned())
+            .collect()
+    }
+
+    /// Appends a data file of rows 1, 2 and 3 in its own commit, and returns its path.
+    async fn append_data_file(test_table: &TestTable) -> String {
+        let insert_batch = create_test_record_batch_with_pos(&simple_table_schema_with_pos(), true);
+        append_batch(test_table, insert_batch).await
+    }
+
+    /// Like `append_data_file`, but inserts one row per id in `ids`.
+    async fn append_data_file_with_ids(test_table: &TestTable, ids: &[i32]) -> String {
+        let arrow_schema = schema_to_arrow_schema(&simple_table_schema_with_pos()).unwrap();
+        let names: Vec<String> = ids.iter().map(|id| format!("name-{}", id)).collect();
+        let insert_batch = RecordBatch::try_new(
+            Arc::new(arrow_schema),
+            vec![
+                Arc::new(Int32Array::from(ids.to_vec())),
+                Arc::new(StringArray::from(names)),
+                Arc::new(Int32Array::from(vec![INSERT_OP; ids.len()])),
+            ],
+        )
+        .unwrap();
+        append_batch(test_table, insert_batch).await
+    }
+
+    async fn append_batch(test_table: &TestTable, insert_batch: RecordBatch) -> String {
+        test_table
+            .append(vec![vec![insert_batch]])
+            .await
+            .iter()
+            .find(|data_file| data_file.content_type() == DataContentType::Data)
+            .unwrap()
+            .file_path()
+            .to_owned()
+    }
+
+    /// Runs `compaction` and checks that it replaced exactly the `replaced` data files with new
+    /// ones, kept the other data files, and left the rows of the table unchanged.
+    async fn assert_compaction_replaces(
+        test_table: &TestTable,
+        compaction: Compaction,
+        replaced: &[String],
+    ) {
+        let table = test_table.load().await;
+        let rows_before = scan_table_rows(&table).await;
+        let files_before = live_data_file_paths(&table).await;
+
+        compaction.compact().await.unwrap();
+
+        let table = test_table.load().await;
+        assert_eq!(scan_table_rows(&table).await, rows_before);
+        let files_after = live_data_file_paths(&table).await;
+        assert_eq!(
+            files_before
+                .difference(&files_after)
+                .cloned()
+                .collect::<HashSet<_>>(),
+            replaced.iter().cloned().collect()
+        );
+        assert!(files_after.difference(&files_before).next().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_incremental_compaction() {
+        let test_table = setup_table().await;
+        let mut data_file_paths = vec![];
+        let mut snapshot_ids = vec![];
+        for _ in 0..3 {
+            data_file_paths.push(append_data_file(&test_table).await);
+            let table = test_table.load().await;
+            snapshot_ids.push(table.metadata().current_snapshot_id().unwrap());
+        }
+
+        let compaction = test_table
+            .compaction()
+            .with_compaction_type(CompactionType::Incremental {
+                from_snapshot_id: snapshot_ids[0],
+                to_snapshot_id: snapshot_ids[2],
+            })
+            .build()
+            .await
+            .unwrap();
+        // The file added by the starting snapshot itself is not rewritten.
+        assert_compaction_replaces(&test_table, compaction, &data_file_paths[1..]).await;
+    }
+
+    #[tokio::test]
+    async fn test_older_than_compaction() {
+        let test_table = setup_table().await;
+        let mut data_file_paths = vec![];
+        for _ in 0..2 {
+            data_file_paths.push(append_data_file(&test_table).await);
+        }
+        let old_timestamp_ms = test_table
+            .load()
+            .await
+            .metadata()
+            .current_snapshot()
+            .unwrap()
+            .timestamp_ms();
+        // The next snapshot must be taken at a later millisecond to be told apart.
+        while now_ms().unwrap() <= old_timestamp_ms {
+            std::hint::spin_loop();
+        }
+        append_data_file(&test_table).await;
+
+        let mut compaction = test_table
+            .compaction()
+            .with_compaction_type(CompactionType::OlderThan {
+                min_file_age: Duration::from_secs(1),
+            })
+            .build()
+            .await
+            .unwrap();
+        // Planned a second after the second append, so the cutoff falls right on it.
+        compaction.now_ms = Some(old_timestamp_ms + 1000);
+        // The file appended last is too fresh to be rewritten.
+        assert_compaction_replaces(&test_table, compaction, &data_file_paths).await;
+    }
+
+    #[tokio::test]
+    async fn test_delete_ratio_compaction() {
+        // Half of the rows of the first data file are deleted, none of the others.
+        let test_table = setup_table_with_rows().await;
+        let deleted_file_paths: Vec<String> = live_data_file_paths(&test_table.load().await)
+            .await
+            .into_iter()
+            .collect();
+        for _ in 0..2 {
+            append_data_file(&test_table).await;
+        }
+
+        let compaction = test_table
+            .compaction()
+            .with_config(
+                CompactionConfigBuilder::default()
+                    .delete_ratio_threshold(0.3)
+                    .build()
+                    .unwrap(),
+            )
+            .with_compaction_type(CompactionType::DeleteRatio)
+            .build()
+            .await
+            .unwrap();
+        assert_compaction_replaces(&test_table, compaction, &deleted_file_paths).await;
+    }
+
+    #[tokio::test]
+    async fn test_files_compaction() {
+        let test_table = setup_table().await;
+        let mut data_file_paths = vec![];
+        for _ in 0..3 {
+            data_file_paths.push(append_data_file(&test_table).await);
+        }
+        let requested = vec![data_file_paths[0].clone(), data_file_paths[2].clone()];
+
+        let compaction = test_table
+            .compaction()
+            .with_compaction_type(CompactionType::Files {
+                data_file_paths: requested.clone(),
+            })
+            .build()
+            .await
+            .unwrap();
+        assert_compaction_replaces(&test_table, compaction, &requested).await;
+
+        // Files that are no longer live are refused.
+        let result = test_table
+            .compaction()
+            .with_compaction_type(CompactionType::Files {
+                data_file_paths: requested,
+            })
+            .build()
+            .await
+            .unwrap()
+            .compact()
+            .await;
+        assert!(matches!(result, Err(CompactionError::Execution(_))));
+    }
+
+    #[tokio::test]
+    async fn test_filtered_compaction() {
+        let test_table = setup_table().await;
+        append_data_file_with_ids(&test_table, &[1, 2, 3]).await;
+        let mut matching = vec![];
+        for ids in [[10, 11, 12], [20, 21, 22]] {
+            matching.push(append_data_file_with_ids(&test_table, &ids).await);
+        }
+
+        let compaction = test_table
+            .compaction()
+            .with_filter(Reference::new("id").greater_than_or_equal_to(Datum::int(10)))
+            .build()
+            .await
+            .unwrap();
+        assert_compaction_replaces(&test_table, compaction, &matching).await;
+    }
+
+    #[tokio::test]
+    async fn test_partial_progress_compaction() {
+        let test_table = setup_table().await;
+        let mut data_file_paths = vec![];
+        for _ in 0..4 {
+            data_file_paths.push(append_data_file(&test_table).await);
+        }
+        let snapshots_before = test_table.load().await.metadata().snapshots().count();
+
+        let config = CompactionConfigBuilder::default()
+            .max_files_per_group(2)
+            .partial_progress_enabled(true)
+            .partial_progress_max_commits(2)
+            .build()
+            .unwrap();
+        let compaction = test_table
+            .compaction()
+            .with_config(config)
+            .build()
+            .await
+            .unwrap();
+        assert_compaction_replaces(&test_table, compaction, &data_file_paths).await;
+
+        // Each of the two file groups is committed in its own snapshot.
+        let snapshots_after = test_table.load().await.metadata().snapshots().count();
+        assert_eq!(snapshots_after, snapshots_before + 2);
+    }
+
+    #[tokio::test]
+    async fn test_max_rewrite_bytes_compaction() {
+        let test_table = setup_table().await;
+        for _ in 0..3 {
+            append_data_file(&test_table).await;
+        }
+        let (data_files, _) = get_old_files_from_table(test_table.load().await)
+            .await
+            .unwrap();
+        let sizes: HashMap<String, u64> = data_files
+            .iter()
+            .map(|data_file| {
+                (
+                    data_file.file_path().to_owned(),
+                    data_file.file_size_in_bytes(),
+                )
+            })
+            .collect();
+        // One byte short of the total leaves room for exactly two of the three files.
+        let max_rewrite_bytes = sizes.values().sum::<u64>() - 1;
+
+        let config = CompactionConfigBuilder::default()
+            .max_rewrite_bytes(max_rewrite_bytes)
+            .build()
+            .unwrap();
+        let compaction = test_table
+            .compaction()
+            .with_config(config)
+            .build()
+            .await
+            .unwrap();
+        let plan = compaction.plan(&CompactionType::Full).await.unwrap();
+        let planned: Vec<String> = plan
+            .file_groups
+            .into_iter()
+            .flat_map(|group| group.data_file_paths)
+            .collect();
+        assert_eq!(planned.len(), 2);
+        assert!(planned.iter().map(|path| sizes[path]).sum::<u64>() <= max_rewrite_bytes);
+
+        assert_compaction_replaces(&test_table, compaction, &planned).await;
+    }
 }
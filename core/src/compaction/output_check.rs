@@ -0,0 +1,148 @@
+/*
+ * Copyright 2025 iceberg-compaction
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Read-back checks of written data files, catching files corrupted or truncated on upload
+//! before they are committed.
+
+use futures::{StreamExt, TryStreamExt};
+use iceberg::io::{FileIO, FileRead};
+use iceberg::spec::DataFile;
+use parquet::file::metadata::{ParquetMetaData, ParquetMetaDataReader};
+use parquet::file::FOOTER_SIZE;
+
+use crate::error::{CompactionError, Result};
+
+/// Reads back the Parquet footer of every file in `data_files` from object storage, with at
+/// most `parallelism` concurrent reads, and checks it against the file's metadata.
+pub async fn verify_data_files(
+    file_io: &FileIO,
+    data_files: &[DataFile],
+    parallelism: usize,
+) -> Result<()> {
+    futures::stream::iter(data_files)
+        .map(|data_file| verify_data_file(file_io, data_file))
+        .buffer_unordered(parallelism.max(1))
+        .try_collect::<Vec<()>>()
+        .await?;
+    Ok(())
+}
+
+async fn verify_data_file(file_io: &FileIO, data_file: &DataFile) -> Result<()> {
+    let path = data_file.file_path();
+    let input = file_io.new_input(path)?;
+    let size = input.metadata().await?.size;
+    if size != data_file.file_size_in_bytes() {
+        return Err(verification_error(
+            path,
+            format!(
+                "stored size is {} bytes, written {}",
+                size,
+                data_file.file_size_in_bytes()
+            ),
+        ));
+    }
+    if size < FOOTER_SIZE as u64 {
+        return Err(verification_error(path, "file is too small for a footer"));
+    }
+
+    let reader = input.reader().await?;
+    let footer = reader.read(size - FOOTER_SIZE as u64..size).await?;
+    let footer: &[u8; FOOTER_SIZE] = footer.as_ref().try_into().map_err(|_| {
+        verification_error(path, format!("read {} footer bytes", footer.len()))
+    })?;
+    let metadata_len = ParquetMetaDataReader::decode_footer_tail(footer)
+        .map_err(|e| verification_error(path, e))?
+        .metadata_length() as u64;
+    let metadata_end = size - FOOTER_SIZE as u64;
+    if metadata_len > metadata_end {
+        return Err(verification_error(
+            path,
+            format!("footer metadata of {} bytes exceeds the file", metadata_len),
+        ));
+    }
+    let metadata_start = metadata_end - metadata_len;
+    let metadata = reader.read(metadata_start..metadata_end).await?;
+    let metadata =
+        ParquetMetaDataReader::decode_metadata(&metadata).map_err(|e| verification_error(path, e))?;
+
+    check_metadata(&metadata, data_file.record_count(), metadata_start)
+        .map_err(|detail| verification_error(path, detail))
+}
+
+/// Checks the row count and that every column chunk lies between the leading magic and the
+/// footer metadata starting at `metadata_start`.
+fn check_metadata(
+    metadata: &ParquetMetaData,
+    record_count: u64,
+    metadata_start: u64,
+) -> std::result::Result<(), String> {
+    let num_rows = metadata.file_metadata().num_rows();
+    if num_rows < 0 || num_rows as u64 != record_count {
+        return Err(format!("footer has {} rows, written {}", num_rows, record_count));
+    }
+    for row_group in metadata.row_groups() {
+        for column in row_group.columns() {
+            let (start, len) = column.byte_range();
+            if start < 4 || start + len > metadata_start {
+                return Err(format!(
+                    "column chunk at {}..{} is outside the data pages",
+                    start,
+                    start + len
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn verification_error(path: &str, detail: impl std::fmt::Display) -> CompactionError {
+    CompactionError::OutputVerification(format!("'{}': {}", path, detail))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytes::Bytes;
+    use datafusion::arrow::array::{Int32Array, RecordBatch};
+    use parquet::arrow::ArrowWriter;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_check_metadata() {
+        let batch = RecordBatch::try_from_iter(vec![(
+            "id",
+            Arc::new(Int32Array::from(vec![1, 2, 3])) as _,
+        )])
+        .unwrap();
+        let mut buffer = vec![];
+        let mut writer = ArrowWriter::try_new(&mut buffer, batch.schema(), None).unwrap();
+        writer.write(&batch).unwrap();
+        writer.close().unwrap();
+        let file = Bytes::from(buffer);
+        let metadata = ParquetMetaDataReader::new()
+            .parse_and_finish(&file)
+            .unwrap();
+        let footer: &[u8; FOOTER_SIZE] = file[file.len() - FOOTER_SIZE..].try_into().unwrap();
+        let metadata_start = (file.len() - FOOTER_SIZE) as u64
+            - ParquetMetaDataReader::decode_footer_tail(footer)
+                .unwrap()
+                .metadata_length() as u64;
+
+        assert!(check_metadata(&metadata, 3, metadata_start).is_ok());
+        assert!(check_metadata(&metadata, 4, metadata_start).is_err());
+        assert!(check_metadata(&metadata, 3, 4).is_err());
+    }
+}
@@ -0,0 +1,162 @@
+/*
+ * Copyright 2025 iceberg-compaction
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Splits the selected data files into file groups that are rewritten independently.
+
+use std::collections::HashSet;
+
+use iceberg::scan::FileScanTask;
+
+/// Packs data files, in order, into groups of at most `max_group_size_bytes` and
+/// `max_files_per_group` files. A file larger than `max_group_size_bytes` forms a group of its
+/// own. Returns the data file paths of every group.
+///
+/// The size bound is rounded down to a multiple of `target_file_size`, if at least one, so that
+/// a full group is rewritten into files of the target size.
+pub fn plan_file_groups<'a>(
+    data_files: impl IntoIterator<Item = &'a FileScanTask>,
+    max_group_size_bytes: u64,
+    max_files_per_group: usize,
+    target_file_size: u64,
+) -> Vec<HashSet<String>> {
+    let max_group_size_bytes = if target_file_size > 0 && max_group_size_bytes >= target_file_size {
+        max_group_size_bytes - max_group_size_bytes % target_file_size
+    } else {
+        max_group_size_bytes
+    };
+    let mut groups: Vec<HashSet<String>> = vec![];
+    let mut group_size = 0;
+    for task in data_files {
+        let fits = groups.last().is_some_and(|group| {
+            group.len() < max_files_per_group
+                && group_size + task.file_size_in_bytes <= max_group_size_bytes
+        });
+        if !fits {
+            groups.push(HashSet::new());
+            group_size = 0;
+        }
+        group_size += task.file_size_in_bytes;
+        groups
+            .last_mut()
+            .unwrap()
+            .insert(task.data_file_path.clone());
+    }
+    groups
+}
+
+/// Picks data files, in order, as long as their total size stays within `max_rewrite_bytes`.
+/// Files that would exceed the remaining budget are passed over in favor of later, smaller ones.
+pub fn limit_rewrite_bytes<'a>(
+    data_files: impl IntoIterator<Item = &'a FileScanTask>,
+    max_rewrite_bytes: u64,
+) -> HashSet<String> {
+    let mut remaining = max_rewrite_bytes;
+    let mut selected = HashSet::new();
+    for task in data_files {
+        if task.file_size_in_bytes <= remaining {
+            remaining -= task.file_size_in_bytes;
+            selected.insert(task.data_file_path.clone());
+        }
+    }
+    selected
+}
+
+/// Distributes consecutive file groups over at most `max_commits` commits, as evenly as
+/// possible.
+pub fn batch_file_groups(
+    file_groups: Vec<HashSet<String>>,
+    max_commits: usize,
+) -> Vec<Vec<HashSet<String>>> {
+    if file_groups.is_empty() {
+        return vec![];
+    }
+    let groups_per_commit = file_groups.len().div_ceil(max_commits.max(1));
+    let mut commits = vec![];
+    let mut file_groups = file_groups.into_iter().peekable();
+    while file_groups.peek().is_some() {
+        commits.push(file_groups.by_ref().take(groups_per_commit).collect());
+    }
+    commits
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::data_file;
+
+    #[test]
+    fn test_plan_file_groups() {
+        let data_files = vec![
+            data_file("a", 40),
+            data_file("b", 40),
+            data_file("c", 40),
+            data_file("huge", 500),
+            data_file("d", 10),
+            data_file("e", 10),
+            data_file("f", 10),
+        ];
+
+        let groups = plan_file_groups(&data_files, 100, 2, 1024);
+
+        let expected: Vec<HashSet<String>> = [
+            vec!["a", "b"],
+            vec!["c"],
+            vec!["huge"],
+            vec!["d", "e"],
+            vec!["f"],
+        ]
+        .into_iter()
+        .map(|paths| paths.into_iter().map(str::to_owned).collect())
+        .collect();
+        assert_eq!(groups, expected);
+    }
+
+    #[test]
+    fn test_plan_file_groups_by_target_file_size() {
+        let data_files = vec![data_file("a", 30), data_file("b", 30), data_file("c", 30)];
+
+        // The group size bound of 70 is rounded down to two target files.
+        let groups = plan_file_groups(&data_files, 70, 10, 30);
+
+        assert_eq!(
+            groups.iter().map(HashSet::len).collect::<Vec<_>>(),
+            vec![2, 1]
+        );
+    }
+
+    #[test]
+    fn test_limit_rewrite_bytes() {
+        let data_files = vec![data_file("a", 60), data_file("b", 60), data_file("c", 30)];
+
+        assert_eq!(
+            limit_rewrite_bytes(&data_files, 100),
+            HashSet::from(["a".to_owned(), "c".to_owned()])
+        );
+        assert_eq!(limit_rewrite_bytes(&data_files, u64::MAX).len(), 3);
+    }
+
+    #[test]
+    fn test_batch_file_groups() {
+        let file_groups: Vec<HashSet<String>> =
+            (0..5).map(|i| HashSet::from([i.to_string()])).collect();
+
+        let commits = batch_file_groups(file_groups.clone(), 2);
+        assert_eq!(commits.iter().map(Vec::len).collect::<Vec<_>>(), vec![3, 2]);
+
+        let commits = batch_file_groups(file_groups, 10);
+        assert_eq!(commits.len(), 5);
+    }
+}
@@ -0,0 +1,166 @@
+/*
+ * Copyright 2025 iceberg-compaction
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! File selection policies deciding which data files a compaction rewrites.
+
+use std::collections::{BTreeMap, HashSet};
+
+use iceberg::scan::FileScanTask;
+
+use crate::common::sizing::{estimated_delete_ratio, shared_delete_file_paths};
+
+/// Size-tiered selection, similar to LSM size tiering.
+///
+/// Files of at least `target_file_size` are never selected. Smaller files fall into tiers whose
+/// bounds shrink by `tier_ratio`: tier 0 holds files in `[target / ratio, target)`, tier 1 files
+/// in `[target / ratio², target / ratio)` and so on. Only tiers holding at least `fan_in` files
+/// are selected, so each row is rewritten about once per tier it climbs.
+pub fn select_size_tiers(
+    data_files: &[FileScanTask],
+    target_file_size: u64,
+    tier_ratio: u64,
+    fan_in: usize,
+) -> HashSet<String> {
+    select_size_tier_groups(data_files, target_file_size, tier_ratio, fan_in)
+        .into_iter()
+        .flatten()
+        .collect()
+}
+
+/// Like `select_size_tiers`, but returns the files of every selected tier separately, smallest
+/// tier first, so that files are only merged with files of their own tier.
+pub fn select_size_tier_groups(
+    data_files: &[FileScanTask],
+    target_file_size: u64,
+    tier_ratio: u64,
+    fan_in: usize,
+) -> Vec<HashSet<String>> {
+    let mut tiers: BTreeMap<u32, Vec<&str>> = BTreeMap::new();
+    for task in data_files {
+        if let Some(tier) = size_tier(task.file_size_in_bytes, target_file_size, tier_ratio) {
+            tiers
+                .entry(tier)
+                .or_default()
+                .push(task.data_file_path.as_str());
+        }
+    }
+    tiers
+        .into_values()
+        .rev()
+        .filter(|paths| paths.len() >= fan_in)
+        .map(|paths| paths.into_iter().map(str::to_owned).collect())
+        .collect()
+}
+
+/// Selects data files whose estimated fraction of deleted rows exceeds `threshold`, so clean
+/// files are left alone and only files with read amplification are rewritten. Delete files
+/// shared between data files don't count towards the fraction, see `estimated_delete_ratio`.
+pub fn select_by_delete_ratio(data_files: &[FileScanTask], threshold: f64) -> HashSet<String> {
+    let shared_delete_file_paths = shared_delete_file_paths(data_files);
+    data_files
+        .iter()
+        .filter(|task| estimated_delete_ratio(task, &shared_delete_file_paths) > threshold)
+        .map(|task| task.data_file_path.clone())
+        .collect()
+}
+
+/// Returns the tier of a file of `size` bytes, or `None` if it is already large enough.
+fn size_tier(size: u64, target_file_size: u64, tier_ratio: u64) -> Option<u32> {
+    if size >= target_file_size {
+        return None;
+    }
+    let tier_ratio = tier_ratio.max(2);
+    let mut tier = 0;
+    let mut lower_bound = target_file_size / tier_ratio;
+    while size < lower_bound {
+        tier += 1;
+        lower_bound /= tier_ratio;
+    }
+    Some(tier)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{data_file, data_file_with_deletes};
+
+    #[test]
+    fn test_size_tier() {
+        assert_eq!(size_tier(1024, 1024, 4), None);
+        assert_eq!(size_tier(256, 1024, 4), Some(0));
+        assert_eq!(size_tier(255, 1024, 4), Some(1));
+        assert_eq!(size_tier(64, 1024, 4), Some(1));
+        assert_eq!(size_tier(0, 1024, 4), Some(5));
+    }
+
+    #[test]
+    fn test_select_size_tiers() {
+        let data_files = vec![
+            data_file("large", 2048),
+            data_file("tier-0-a", 300),
+            data_file("tier-0-b", 500),
+            data_file("tier-1-a", 100),
+            data_file("tier-1-b", 120),
+            data_file("tier-1-c", 70),
+        ];
+
+        let selected = select_size_tiers(&data_files, 1024, 4, 3);
+        let groups = select_size_tier_groups(&data_files, 1024, 4, 2);
+
+        assert_eq!(
+            selected,
+            HashSet::from([
+                "tier-1-a".to_owned(),
+                "tier-1-b".to_owned(),
+                "tier-1-c".to_owned()
+            ])
+        );
+
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0].len(), 3);
+        assert_eq!(
+            groups[1],
+            HashSet::from(["tier-0-a".to_owned(), "tier-0-b".to_owned()])
+        );
+    }
+
+    #[test]
+    fn test_select_by_delete_ratio() {
+        let with_deletes = |path: &str, records: u64, delete_records: &[u64]| {
+            data_file_with_deletes(path, 0, records, delete_records)
+        };
+        let data_files = vec![
+            with_deletes("clean", 100, &[]),
+            with_deletes("few-deletes", 100, &[5]),
+            with_deletes("many-deletes", 100, &[10, 20]),
+            with_deletes("empty-with-deletes", 0, &[1]),
+        ];
+
+        let shared_delete_file_paths = HashSet::new();
+        assert_eq!(
+            estimated_delete_ratio(&data_files[0], &shared_delete_file_paths),
+            0.0
+        );
+        assert_eq!(
+            estimated_delete_ratio(&data_files[2], &shared_delete_file_paths),
+            0.3
+        );
+        assert_eq!(
+            select_by_delete_ratio(&data_files, 0.1),
+            HashSet::from(["many-deletes".to_owned(), "empty-with-deletes".to_owned()])
+        );
+    }
+}
@@ -0,0 +1,67 @@
+/*
+ * Copyright 2025 iceberg-compaction
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use bytes::Bytes;
+use iceberg::table::Table;
+use iceberg::writer::file_writer::location_generator::DefaultLocationGenerator;
+use uuid::Uuid;
+
+use crate::error::{CompactionError, Result};
+
+const PREFLIGHT_PROBE_PREFIX: &str = ".iceberg-compaction-preflight";
+const PREFLIGHT_PROBE_CONTENT: &[u8] = b"iceberg-compaction preflight probe";
+
+/// Writes, reads back and deletes a probe object in the directory compaction writes data files to.
+pub async fn check_table_location(table: &Table) -> Result<()> {
+    let location_generator = DefaultLocationGenerator::new(table.metadata().clone())?;
+    let probe_path = format!(
+        "{}/{}-{}",
+        location_generator.dir_path,
+        PREFLIGHT_PROBE_PREFIX,
+        Uuid::now_v7()
+    );
+    let file_io = table.file_io();
+
+    file_io
+        .new_output(&probe_path)?
+        .write(Bytes::from_static(PREFLIGHT_PROBE_CONTENT))
+        .await
+        .map_err(|e| {
+            CompactionError::Preflight(format!("failed to write probe '{}': {}", probe_path, e))
+        })?;
+
+    let content = file_io
+        .new_input(&probe_path)?
+        .read()
+        .await
+        .map_err(|e| {
+            CompactionError::Preflight(format!("failed to read probe '{}': {}", probe_path, e))
+        })?;
+    if content.as_ref() != PREFLIGHT_PROBE_CONTENT {
+        return Err(CompactionError::Preflight(format!(
+            "probe '{}' read back {} bytes, expected {}",
+            probe_path,
+            content.len(),
+            PREFLIGHT_PROBE_CONTENT.len()
+        )));
+    }
+
+    file_io.delete(&probe_path).await.map_err(|e| {
+        CompactionError::Preflight(format!("failed to delete probe '{}': {}", probe_path, e))
+    })?;
+
+    Ok(())
+}
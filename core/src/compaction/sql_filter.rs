@@ -0,0 +1,66 @@
+/*
+ * Copyright 2025 iceberg-compaction
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use datafusion::common::DFSchema;
+use datafusion::prelude::SessionContext;
+use iceberg::arrow::schema_to_arrow_schema;
+use iceberg::expr::Predicate;
+use iceberg::spec::Schema;
+use iceberg_datafusion::physical_plan::expr_to_predicate::convert_filters_to_predicate;
+
+use crate::error::{CompactionError, Result};
+
+/// Parses a SQL boolean expression over the columns of `schema`, e.g.
+/// `event_date >= '2025-01-01' AND region = 'eu'`, into a predicate for file pruning.
+///
+/// Conjuncts that have no Iceberg equivalent are dropped, which only widens the selection.
+/// Fails if no part of the expression can be used for pruning.
+pub fn parse_sql_filter(sql: &str, schema: &Schema) -> Result<Predicate> {
+    let df_schema = DFSchema::try_from(schema_to_arrow_schema(schema)?)?;
+    let expr = SessionContext::new().parse_sql_expr(sql, &df_schema)?;
+    convert_filters_to_predicate(&[expr]).ok_or_else(|| {
+        CompactionError::Config(format!(
+            "filter '{}' can't be converted to an Iceberg predicate",
+            sql
+        ))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use iceberg::spec::{NestedField, PrimitiveType, Type};
+
+    #[test]
+    fn test_parse_sql_filter() {
+        let schema = Schema::builder()
+            .with_fields(vec![
+                NestedField::required(1, "id", Type::Primitive(PrimitiveType::Long)).into(),
+                NestedField::optional(2, "region", Type::Primitive(PrimitiveType::String)).into(),
+            ])
+            .build()
+            .unwrap();
+
+        let predicate = parse_sql_filter("id > 5 AND region = 'eu'", &schema).unwrap();
+        assert!(matches!(predicate, Predicate::And(_)));
+
+        assert!(parse_sql_filter("missing > 5", &schema).is_err());
+        assert!(matches!(
+            parse_sql_filter("length(region) > 3", &schema),
+            Err(CompactionError::Config(_))
+        ));
+    }
+}
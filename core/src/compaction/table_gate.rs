@@ -0,0 +1,105 @@
+/*
+ * Copyright 2025 iceberg-compaction
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::collections::HashMap;
+
+use crate::error::{CompactionError, Result};
+
+/// Table property that disables compaction when set to `false`.
+pub const TABLE_PROPERTY_ENABLED: &str = "bergloom.enabled";
+/// Table property holding an epoch-millisecond timestamp until which compaction is paused.
+pub const TABLE_PROPERTY_PAUSED_UNTIL: &str = "compaction.paused-until";
+
+/// Returns why compaction must not run for a table with `properties` at `now_ms`, if it is
+/// disabled or paused by its table properties.
+pub fn compaction_disabled_reason(
+    properties: &HashMap<String, String>,
+    now_ms: i64,
+) -> Result<Option<String>> {
+    if let Some(enabled) = properties.get(TABLE_PROPERTY_ENABLED) {
+        let enabled: bool = enabled.trim().parse().map_err(|_| {
+            CompactionError::Config(format!(
+                "table property '{}' must be 'true' or 'false', got '{}'",
+                TABLE_PROPERTY_ENABLED, enabled
+            ))
+        })?;
+        if !enabled {
+            return Ok(Some(format!("{} is false", TABLE_PROPERTY_ENABLED)));
+        }
+    }
+
+    if let Some(paused_until) = properties.get(TABLE_PROPERTY_PAUSED_UNTIL) {
+        let paused_until: i64 = paused_until.trim().parse().map_err(|_| {
+            CompactionError::Config(format!(
+                "table property '{}' must be an epoch-millisecond timestamp, got '{}'",
+                TABLE_PROPERTY_PAUSED_UNTIL, paused_until
+            ))
+        })?;
+        if now_ms < paused_until {
+            return Ok(Some(format!(
+                "{} is {}",
+                TABLE_PROPERTY_PAUSED_UNTIL, paused_until
+            )));
+        }
+    }
+
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn properties(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_compaction_disabled_reason() {
+        assert!(compaction_disabled_reason(&properties(&[]), 0)
+            .unwrap()
+            .is_none());
+        assert!(
+            compaction_disabled_reason(&properties(&[(TABLE_PROPERTY_ENABLED, "true")]), 0)
+                .unwrap()
+                .is_none()
+        );
+        assert!(
+            compaction_disabled_reason(&properties(&[(TABLE_PROPERTY_ENABLED, "false")]), 0)
+                .unwrap()
+                .is_some()
+        );
+
+        let paused = properties(&[(TABLE_PROPERTY_PAUSED_UNTIL, "1000")]);
+        assert!(compaction_disabled_reason(&paused, 999).unwrap().is_some());
+        assert!(compaction_disabled_reason(&paused, 1000).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_compaction_disabled_reason_rejects_invalid_values() {
+        assert!(
+            compaction_disabled_reason(&properties(&[(TABLE_PROPERTY_ENABLED, "no")]), 0).is_err()
+        );
+        assert!(compaction_disabled_reason(
+            &properties(&[(TABLE_PROPERTY_PAUSED_UNTIL, "tomorrow")]),
+            0
+        )
+        .is_err());
+    }
+}
@@ -21,6 +21,10 @@
 use std::collections::HashSet;
 use std::sync::Arc;
 
+use datafusion::arrow::array::{ArrayRef, RecordBatch};
+use datafusion::arrow::row::{OwnedRow, RowConverter, SortField};
+use datafusion::arrow::util::display::array_value_to_string;
+use datafusion::error::DataFusionError;
 use futures::StreamExt;
 use iceberg::spec::{DataFile, Schema};
 use iceberg::table::Table;
@@ -33,10 +37,15 @@ use crate::executor::datafusion::datafusion_processor::{
 use crate::executor::InputFileScanTasks;
 use crate::{CompactionConfig, CompactionError};
 
+/// Number of duplicate keys listed in the validation error.
+const MAX_REPORTED_DUPLICATE_KEYS: usize = 10;
+
 pub struct CompactionValidator {
     datafusion_processor: DatafusionProcessor,
     input_datafusion_task_ctx: Option<DataFusionTaskContext>,
     output_datafusion_task_ctx: Option<DataFusionTaskContext>,
+    /// Names of the identifier fields that must be unique in the output, empty to skip the check.
+    identifier_columns: Vec<String>,
     table_ident: String,
     catalog_name: String,
 }
@@ -79,17 +88,34 @@ impl CompactionValidator {
             }
         }
 
+        let identifier_columns = if config.validate_identifier_fields {
+            output_schema
+                .identifier_field_ids()
+                .map(|field_id| {
+                    output_schema
+                        .name_by_field_id(field_id)
+                        .map(str::to_owned)
+                        .ok_or_else(|| {
+                            CompactionError::Config(format!(
+                                "identifier field id {} is not in the schema",
+                                field_id
+                            ))
+                        })
+                })
+                .collect::<Result<Vec<_>>>()?
+        } else {
+            vec![]
+        };
+
         // TODO: we can only select a single column for count validation
         let input_datafusion_task_ctx = DataFusionTaskContext::builder()?
             .with_schema(input_schema)
             .with_input_data_files(input_file_scan_tasks)
-            .with_table_prefix("input".to_owned())
             .build()?;
 
         let output_datafusion_task_ctx = DataFusionTaskContext::builder()?
             .with_schema(output_schema)
             .with_data_files(output_file_scan_tasks)
-            .with_table_prefix("output".to_owned())
             .build()?;
 
         let validator_config = Arc::new(
@@ -107,6 +133,7 @@ impl CompactionValidator {
             datafusion_processor,
             input_datafusion_task_ctx: Some(input_datafusion_task_ctx),
             output_datafusion_task_ctx: Some(output_datafusion_task_ctx),
+            identifier_columns,
             table_ident: table.identifier().to_string(),
             catalog_name,
         })
@@ -140,10 +167,13 @@ impl CompactionValidator {
         }
 
         let mut total_output_rows = 0;
+        let mut duplicate_keys = DuplicateKeyChecker::new(self.identifier_columns.clone());
         for stream_result in output_batches_streams.iter_mut() {
             // Iterate over each stream
             while let Some(batch_result) = stream_result.as_mut().next().await {
-                total_output_rows += batch_result?.num_rows();
+                let batch = batch_result?;
+                total_output_rows += batch.num_rows();
+                duplicate_keys.check(&batch)?;
             }
         }
 
@@ -153,6 +183,16 @@ impl CompactionValidator {
                 total_input_rows, total_output_rows, self.catalog_name, self.table_ident
             )));
         }
+        if duplicate_keys.duplicates_count > 0 {
+            return Err(CompactionError::CompactionValidator(format!(
+                "{} output rows repeat a key of identifier fields ({}) for catalog '{}' table_ident '{}', e.g. {}",
+                duplicate_keys.duplicates_count,
+                self.identifier_columns.join(", "),
+                self.catalog_name,
+                self.table_ident,
+                duplicate_keys.reported_keys.join(", ")
+            )));
+        }
 
         tracing::info!(
             "Compaction validation completed for catalog '{}' table_ident '{}' in {} seconds",
@@ -164,3 +204,99 @@ impl CompactionValidator {
         Ok(())
     }
 }
+
+/// Finds rows repeating the values of the identifier fields of an earlier row, which means
+/// equality deletes were not applied correctly.
+struct DuplicateKeyChecker {
+    columns: Vec<String>,
+    converter: Option<RowConverter>,
+    seen: HashSet<OwnedRow>,
+    duplicates_count: usize,
+    reported_keys: Vec<String>,
+}
+
+impl DuplicateKeyChecker {
+    fn new(columns: Vec<String>) -> Self {
+        Self {
+            columns,
+            converter: None,
+            seen: HashSet::new(),
+            duplicates_count: 0,
+            reported_keys: vec![],
+        }
+    }
+
+    fn check(&mut self, batch: &RecordBatch) -> Result<()> {
+        if self.columns.is_empty() {
+            return Ok(());
+        }
+        let key_columns = self
+            .columns
+            .iter()
+            .map(|name| {
+                batch.column_by_name(name).cloned().ok_or_else(|| {
+                    CompactionError::CompactionValidator(format!(
+                        "identifier field '{}' is missing from the output",
+                        name
+                    ))
+                })
+            })
+            .collect::<Result<Vec<ArrayRef>>>()?;
+        if self.converter.is_none() {
+            let sort_fields = key_columns
+                .iter()
+                .map(|column| SortField::new(column.data_type().clone()))
+                .collect();
+            self.converter = Some(RowConverter::new(sort_fields).map_err(DataFusionError::from)?);
+        }
+        let rows = self
+            .converter
+            .as_ref()
+            .expect("converter is created above")
+            .convert_columns(&key_columns)
+            .map_err(DataFusionError::from)?;
+        for (row_index, row) in rows.iter().enumerate() {
+            if self.seen.insert(row.owned()) {
+                continue;
+            }
+            self.duplicates_count += 1;
+            if self.reported_keys.len() < MAX_REPORTED_DUPLICATE_KEYS {
+                let values = key_columns
+                    .iter()
+                    .map(|column| array_value_to_string(column.as_ref(), row_index))
+                    .collect::<std::result::Result<Vec<_>, _>>()
+                    .map_err(DataFusionError::from)?;
+                self.reported_keys.push(format!("({})", values.join(", ")));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use datafusion::arrow::array::{Int32Array, StringArray};
+
+    #[test]
+    fn test_duplicate_key_checker() {
+        let batch = RecordBatch::try_from_iter(vec![
+            ("id", Arc::new(Int32Array::from(vec![1, 2, 1, 3])) as ArrayRef),
+            ("name", Arc::new(StringArray::from(vec!["a", "b", "c", "d"])) as ArrayRef),
+        ])
+        .unwrap();
+
+        let mut checker = DuplicateKeyChecker::new(vec!["id".to_owned()]);
+        checker.check(&batch).unwrap();
+        checker.check(&batch.slice(3, 1)).unwrap();
+        assert_eq!(checker.duplicates_count, 2);
+        assert_eq!(checker.reported_keys, vec!["(1)", "(3)"]);
+
+        let mut checker = DuplicateKeyChecker::new(vec!["id".to_owned(), "name".to_owned()]);
+        checker.check(&batch).unwrap();
+        assert_eq!(checker.duplicates_count, 0);
+
+        let mut checker = DuplicateKeyChecker::new(vec!["missing".to_owned()]);
+        assert!(checker.check(&batch).is_err());
+    }
+}
@@ -14,26 +14,222 @@
  * limitations under the License.
  */
 
+use std::collections::HashMap;
+use std::str::FromStr;
+
 use derive_builder::Builder;
-use parquet::{basic::Compression, file::properties::WriterProperties};
+use parquet::{
+    basic::Compression,
+    file::properties::{EnabledStatistics, WriterProperties, WriterPropertiesBuilder},
+    schema::types::ColumnPath,
+};
 use serde::Deserialize;
 
+use crate::clustering::ClusteringFunction;
+use crate::error::CompactionError;
+
+pub(crate) mod validation;
+
 const DEFAULT_PREFIX: &str = "iceberg-compaction";
 const DEFAULT_BATCH_PARALLELISM: usize = 4;
 const DEFAULT_TARGET_PARTITIONS: usize = 4;
 const DEFAULT_TARGET_FILE_SIZE: u64 = 1024 * 1024 * 1024; // 1 GB
 const DEFAULT_VALIDATE_COMPACTION: bool = false;
+const DEFAULT_VALIDATE_IDENTIFIER_FIELDS: bool = false;
+const DEFAULT_VERIFY_OUTPUT_FILES: bool = false;
+const DEFAULT_MAX_GROUP_SIZE_BYTES: u64 = 100 * 1024 * 1024 * 1024; // 100 GB
+const DEFAULT_MAX_FILES_PER_GROUP: usize = 10_000;
+const DEFAULT_MAX_REWRITE_BYTES: u64 = u64::MAX;
+const DEFAULT_PARTIAL_PROGRESS_ENABLED: bool = false;
+const DEFAULT_PARTIAL_PROGRESS_MAX_COMMITS: usize = 10;
 const DEFAULT_MAX_RECORD_BATCH_ROWS: usize = 1024;
+const DEFAULT_MAX_RECORD_BATCH_VALUES: usize = 1024 * 1024;
+const DEFAULT_MAX_COLUMNS: usize = 10_000;
+const DEFAULT_MANIFEST_IO_PARALLELISM: usize = 16;
+const DEFAULT_SMALL_FILE_THRESHOLD: u64 = 32 * 1024 * 1024; // 32 MB
+const DEFAULT_ENABLE_SORT_ORDER: bool = false;
+const DEFAULT_SIZE_TIER_RATIO: u64 = 4;
+const DEFAULT_SIZE_TIER_FAN_IN: usize = 4;
+const DEFAULT_DELETE_RATIO_THRESHOLD: f64 = 0.1;
+const DEFAULT_UNSUPPORTED_CONTENT_TYPE_POLICY: UnsupportedContentTypePolicy =
+    UnsupportedContentTypePolicy::Fail;
+const DEFAULT_DANGLING_DELETE_POLICY: DanglingDeletePolicy = DanglingDeletePolicy::Ignore;
 
 // Helper function for the default WriterProperties
-fn default_writer_properties() -> WriterProperties {
-    WriterProperties::builder()
+fn default_writer_properties(metrics_config: Option<&MetricsConfig>) -> WriterProperties {
+    let builder = WriterProperties::builder()
         .set_compression(Compression::SNAPPY)
-        .set_created_by(concat!("iceberg-compaction version ", env!("CARGO_PKG_VERSION")).to_owned())
+        .set_created_by(
+            concat!("iceberg-compaction version ", env!("CARGO_PKG_VERSION")).to_owned(),
+        );
+    metrics_config
+        .cloned()
+        .unwrap_or_default()
+        .apply(builder)
         .build()
 }
 
-#[derive(Builder, Debug, Deserialize, Default, Clone)]
+/// Column statistics written into `DataFile` metadata, mirroring Iceberg's
+/// `write.metadata.metrics.*` modes.
+#[derive(Debug, Deserialize, Default, Clone, Copy, PartialEq, Eq)]
+pub enum MetricsMode {
+    /// No null counts or lower/upper bounds. Value counts come from the Parquet footer and are
+    /// still written.
+    None,
+    /// Counts only, no lower/upper bounds. Parquet keeps null counts only together with bounds,
+    /// so this mode is rejected by `MetricsConfig::check`.
+    Counts,
+    /// Counts and lower/upper bounds truncated to the given length.
+    Truncate(usize),
+    /// Counts and full lower/upper bounds.
+    #[default]
+    Full,
+}
+
+impl MetricsMode {
+    fn enabled_statistics(&self) -> EnabledStatistics {
+        match self {
+            MetricsMode::None | MetricsMode::Counts => EnabledStatistics::None,
+            MetricsMode::Truncate(_) | MetricsMode::Full => EnabledStatistics::Page,
+        }
+    }
+
+    fn truncate_length(&self) -> Option<usize> {
+        match self {
+            MetricsMode::Truncate(length) => Some(*length),
+            _ => None,
+        }
+    }
+}
+
+impl FromStr for MetricsMode {
+    type Err = CompactionError;
+
+    /// Parses the Iceberg property format: `none`, `counts`, `truncate(N)` or `full`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mode = s.trim().to_ascii_lowercase();
+        match mode.as_str() {
+            "none" => Ok(MetricsMode::None),
+            "counts" => Ok(MetricsMode::Counts),
+            "full" => Ok(MetricsMode::Full),
+            _ => mode
+                .strip_prefix("truncate(")
+                .and_then(|rest| rest.strip_suffix(')'))
+                .and_then(|length| length.trim().parse::<usize>().ok())
+                .filter(|length| *length > 0)
+                .map(MetricsMode::Truncate)
+                .ok_or_else(|| CompactionError::Config(format!("Invalid metrics mode: {}", s))),
+        }
+    }
+}
+
+/// Per-column statistics configuration for written data files.
+#[derive(Debug, Deserialize, Default, Clone, PartialEq, Eq)]
+pub struct MetricsConfig {
+    /// Mode for columns without an explicit entry in `column_modes`.
+    pub default_mode: MetricsMode,
+    /// Modes keyed by column name, nested fields separated by `.`.
+    pub column_modes: HashMap<String, MetricsMode>,
+}
+
+impl MetricsConfig {
+    const DEFAULT_KEY: &'static str = "write.metadata.metrics.default";
+    const COLUMN_KEY_PREFIX: &'static str = "write.metadata.metrics.column.";
+
+    /// Builds the config from Iceberg table properties (`write.metadata.metrics.default` and
+    /// `write.metadata.metrics.column.<name>`), failing if `check` rejects it.
+    pub fn from_table_properties(
+        properties: &HashMap<String, String>,
+    ) -> Result<Self, CompactionError> {
+        let mut metrics_config = MetricsConfig::default();
+        for (key, value) in properties {
+            if key == Self::DEFAULT_KEY {
+                metrics_config.default_mode = value.parse()?;
+            } else if let Some(column) = key.strip_prefix(Self::COLUMN_KEY_PREFIX) {
+                metrics_config
+                    .column_modes
+                    .insert(column.to_owned(), value.parse()?);
+            }
+        }
+        metrics_config.check()?;
+        Ok(metrics_config)
+    }
+
+    /// Checks that Parquet can write the configured statistics: no column uses
+    /// `MetricsMode::Counts`, and all columns keeping bounds share one mode, since Parquet
+    /// applies a single truncate length to every column of a file.
+    pub fn check(&self) -> Result<(), CompactionError> {
+        let modes = || std::iter::once(&self.default_mode).chain(self.column_modes.values());
+        if modes().any(|mode| *mode == MetricsMode::Counts) {
+            return Err(CompactionError::Config(
+                "metrics mode counts can't be written to Parquet, which only keeps null counts \
+                 together with bounds; use none or full instead"
+                    .to_owned(),
+            ));
+        }
+        let mut bounds_modes = modes().filter(|mode| **mode != MetricsMode::None);
+        if let Some(first) = bounds_modes.next() {
+            if let Some(other) = bounds_modes.find(|mode| *mode != first) {
+                return Err(CompactionError::Config(format!(
+                    "metrics modes {:?} and {:?} both keep bounds, but Parquet applies one truncate \
+                     length to every column",
+                    first, other
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Applies the statistics settings to a Parquet writer properties builder.
+    ///
+    /// Parquet only supports a single truncate length per file, so the shortest configured
+    /// truncate length applies to every column that keeps bounds. `check` rejects configs where
+    /// this would change a column's mode.
+    pub fn apply(&self, builder: WriterPropertiesBuilder) -> WriterPropertiesBuilder {
+        let mut builder = builder.set_statistics_enabled(self.default_mode.enabled_statistics());
+        let mut truncate_length = self.default_mode.truncate_length();
+        for (column, mode) in &self.column_modes {
+            let column_path =
+                ColumnPath::new(column.split('.').map(|part| part.to_owned()).collect());
+            builder = builder.set_column_statistics_enabled(column_path, mode.enabled_statistics());
+            truncate_length = match (truncate_length, mode.truncate_length()) {
+                (Some(current), Some(length)) => Some(current.min(length)),
+                (current, length) => current.or(length),
+            };
+        }
+        builder.set_statistics_truncate_length(truncate_length)
+    }
+}
+
+/// How to handle scan tasks whose content type compaction does not know how to rewrite.
+#[derive(Debug, Deserialize, Default, Clone, Copy, PartialEq, Eq)]
+pub enum UnsupportedContentTypePolicy {
+    /// Fail the compaction with `CompactionError::UnsupportedTableFeature`.
+    #[default]
+    Fail,
+    /// Leave the affected data file and its delete files untouched, logging a warning.
+    Skip,
+}
+
+/// How to handle position delete files referencing only data files that are not in the
+/// snapshot, e.g. after manual metadata surgery. Detecting them reads every planned position
+/// delete file once more, so it is off by default.
+#[derive(Debug, Deserialize, Default, Clone, Copy, PartialEq, Eq)]
+pub enum DanglingDeletePolicy {
+    /// Don't look for dangling position deletes.
+    #[default]
+    Ignore,
+    /// Log a warning and compact as usual.
+    Report,
+    /// Fail the compaction with `CompactionError::DanglingDeletes`.
+    Fail,
+    /// Remove position delete files referencing only missing data files in the compaction commit.
+    Clean,
+}
+
+/// Fields missing when deserializing take the same defaults as with `CompactionConfigBuilder`.
+#[derive(Builder, Debug, Deserialize, Clone)]
+#[serde(default, remote = "Self")]
 pub struct CompactionConfig {
     #[builder(default = "DEFAULT_BATCH_PARALLELISM")]
     pub batch_parallelism: usize,
@@ -45,11 +241,255 @@ pub struct CompactionConfig {
     pub target_file_size: u64,
     #[builder(default = "DEFAULT_VALIDATE_COMPACTION")]
     pub enable_validate_compaction: bool,
+    /// When validating, also check that no two output rows share the values of the table's
+    /// identifier fields. Every key of the output is held in memory during the check.
+    #[builder(default = "DEFAULT_VALIDATE_IDENTIFIER_FIELDS")]
+    pub validate_identifier_fields: bool,
+    /// Read back the footer of every written data file and check its size, row count and
+    /// column chunk offsets before committing it.
+    #[builder(default = "DEFAULT_VERIFY_OUTPUT_FILES")]
+    pub verify_output_files: bool,
+    /// Upper bound on the total size of the data files rewritten together as one file group.
+    #[builder(default = "DEFAULT_MAX_GROUP_SIZE_BYTES")]
+    pub max_group_size_bytes: u64,
+    /// Upper bound on the number of data files rewritten together as one file group.
+    #[builder(default = "DEFAULT_MAX_FILES_PER_GROUP")]
+    pub max_files_per_group: usize,
+    /// Upper bound on the total size of the data files one compaction rewrites; the remaining
+    /// files are left for later runs. Unlimited by default.
+    #[builder(default = "DEFAULT_MAX_REWRITE_BYTES")]
+    pub max_rewrite_bytes: u64,
+    /// Commit the file groups in several smaller snapshots, so that a failure keeps the groups
+    /// committed so far.
+    #[builder(default = "DEFAULT_PARTIAL_PROGRESS_ENABLED")]
+    pub partial_progress_enabled: bool,
+    /// Maximum number of commits of a compaction with partial progress enabled.
+    #[builder(default = "DEFAULT_PARTIAL_PROGRESS_MAX_COMMITS")]
+    pub partial_progress_max_commits: usize,
     #[builder(default = "DEFAULT_MAX_RECORD_BATCH_ROWS")]
     pub max_record_batch_rows: usize,
+    /// Upper bound on the values, rows times leaf columns, of a record batch. Tables with more
+    /// than `max_record_batch_values / max_record_batch_rows` columns are read in smaller batches.
+    #[builder(default = "DEFAULT_MAX_RECORD_BATCH_VALUES")]
+    pub max_record_batch_values: usize,
+    /// Tables with more leaf columns than this are refused instead of risking running out of
+    /// memory in the middle of a rewrite.
+    #[builder(default = "DEFAULT_MAX_COLUMNS")]
+    pub max_columns: usize,
+    #[builder(default = "DEFAULT_MANIFEST_IO_PARALLELISM")]
+    pub manifest_io_parallelism: usize,
+    /// Data files smaller than this are rewritten by `CompactionType::SmallFiles`.
+    #[builder(default = "DEFAULT_SMALL_FILE_THRESHOLD")]
+    pub small_file_threshold: u64,
+    /// Size ratio between the bounds of consecutive tiers of `CompactionType::SizeTiered`.
+    #[builder(default = "DEFAULT_SIZE_TIER_RATIO")]
+    pub size_tier_ratio: u64,
+    /// Minimum number of files in a tier for `CompactionType::SizeTiered` to rewrite it.
+    #[builder(default = "DEFAULT_SIZE_TIER_FAN_IN")]
+    pub size_tier_fan_in: usize,
+    /// Estimated fraction of deleted rows above which `CompactionType::DeleteRatio` rewrites a
+    /// data file.
+    #[builder(default = "DEFAULT_DELETE_RATIO_THRESHOLD")]
+    pub delete_ratio_threshold: f64,
+    /// Sort rewritten rows by the table's default sort order before writing.
+    #[builder(default = "DEFAULT_ENABLE_SORT_ORDER")]
+    pub enable_sort_order: bool,
+    /// Columns to cluster rewritten rows by with `clustering_function`. Takes precedence over
+    /// `enable_sort_order` when not empty.
+    #[builder(default)]
+    pub clustering_columns: Vec<String>,
+    /// Space-filling curve used to cluster by `clustering_columns`.
+    #[builder(default)]
+    pub clustering_function: ClusteringFunction,
+    #[builder(default = "DEFAULT_UNSUPPORTED_CONTENT_TYPE_POLICY")]
+    pub unsupported_content_type_policy: UnsupportedContentTypePolicy,
+    #[builder(default = "DEFAULT_DANGLING_DELETE_POLICY")]
+    pub dangling_delete_policy: DanglingDeletePolicy,
+    /// Column statistics for the default `write_parquet_properties`. Ignored when
+    /// `write_parquet_properties` is set explicitly, use `MetricsConfig::apply` on its builder instead.
+    /// The table's `write.metadata.metrics.*` properties are not read: callers wanting them pass
+    /// `MetricsConfig::from_table_properties(table.metadata().properties())`.
+    #[builder(default)]
+    pub metrics_config: MetricsConfig,
 
+    /// Not deserialized, but rebuilt from `metrics_config`.
     #[serde(skip)]
-    // FIXME: this is a workaround for serde not supporting default values for WriterProperties
-    #[builder(default = "default_writer_properties()")]
+    #[builder(default = "default_writer_properties(self.metrics_config.as_ref())")]
     pub write_parquet_properties: WriterProperties,
 }
+
+impl<'de> Deserialize<'de> for CompactionConfig {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let mut config = CompactionConfig::deserialize(deserializer)?;
+        config.write_parquet_properties = default_writer_properties(Some(&config.metrics_config));
+        Ok(config)
+    }
+}
+
+impl Default for CompactionConfig {
+    fn default() -> Self {
+        CompactionConfigBuilder::default()
+            .build()
+            .expect("every field of CompactionConfig has a builder default")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_config_matches_builder_defaults() {
+        let config = CompactionConfig::default();
+        assert_eq!(config.max_rewrite_bytes, DEFAULT_MAX_REWRITE_BYTES);
+        assert_eq!(config.max_columns, DEFAULT_MAX_COLUMNS);
+        assert_eq!(config.small_file_threshold, DEFAULT_SMALL_FILE_THRESHOLD);
+        assert_eq!(config.dangling_delete_policy, DanglingDeletePolicy::Ignore);
+
+        // A config written before the later options existed still deserializes.
+        let config: CompactionConfig = serde_json::from_str(
+            r#"{
+                "batch_parallelism": 8,
+                "target_partitions": 2,
+                "data_file_prefix": "compacted",
+                "target_file_size": 1048576,
+                "enable_validate_compaction": true,
+                "max_record_batch_rows": 512
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(config.batch_parallelism, 8);
+        assert_eq!(config.max_record_batch_rows, 512);
+        assert_eq!(config.max_group_size_bytes, DEFAULT_MAX_GROUP_SIZE_BYTES);
+        assert_eq!(config.max_rewrite_bytes, DEFAULT_MAX_REWRITE_BYTES);
+        assert_eq!(
+            config.max_record_batch_values,
+            DEFAULT_MAX_RECORD_BATCH_VALUES
+        );
+        assert_eq!(
+            config.dangling_delete_policy,
+            DEFAULT_DANGLING_DELETE_POLICY
+        );
+        assert!(!config.verify_output_files);
+    }
+
+    #[test]
+    fn test_deserialized_metrics_config_applied_to_writer_properties() {
+        let config: CompactionConfig = serde_json::from_str(
+            r#"{
+                "metrics_config": {
+                    "default_mode": { "Truncate": 16 },
+                    "column_modes": { "payload": "None" }
+                }
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            config.metrics_config.default_mode,
+            MetricsMode::Truncate(16)
+        );
+
+        let properties = &config.write_parquet_properties;
+        assert_eq!(
+            properties.statistics_enabled(&ColumnPath::from("payload")),
+            EnabledStatistics::None
+        );
+        assert_eq!(properties.statistics_truncate_length(), Some(16));
+    }
+
+    #[test]
+    fn test_check_metrics_config() {
+        let metrics_config = |default_mode, column_mode| MetricsConfig {
+            default_mode,
+            column_modes: HashMap::from([("payload".to_owned(), column_mode)]),
+        };
+        assert!(metrics_config(MetricsMode::Full, MetricsMode::None)
+            .check()
+            .is_ok());
+        assert!(
+            metrics_config(MetricsMode::Truncate(8), MetricsMode::Truncate(8))
+                .check()
+                .is_ok()
+        );
+        assert!(metrics_config(MetricsMode::Full, MetricsMode::Counts)
+            .check()
+            .is_err());
+        // A column keeping full bounds can't be written next to a truncated one.
+        assert!(metrics_config(MetricsMode::Truncate(8), MetricsMode::Full)
+            .check()
+            .is_err());
+        assert!(
+            metrics_config(MetricsMode::Truncate(8), MetricsMode::Truncate(16))
+                .check()
+                .is_err()
+        );
+
+        let properties = HashMap::from([(
+            "write.metadata.metrics.default".to_owned(),
+            "counts".to_owned(),
+        )]);
+        assert!(MetricsConfig::from_table_properties(&properties).is_err());
+    }
+
+    #[test]
+    fn test_parse_metrics_mode() {
+        assert_eq!("none".parse::<MetricsMode>().unwrap(), MetricsMode::None);
+        assert_eq!(
+            "Counts".parse::<MetricsMode>().unwrap(),
+            MetricsMode::Counts
+        );
+        assert_eq!("full".parse::<MetricsMode>().unwrap(), MetricsMode::Full);
+        assert_eq!(
+            "truncate(16)".parse::<MetricsMode>().unwrap(),
+            MetricsMode::Truncate(16)
+        );
+        assert!("truncate(0)".parse::<MetricsMode>().is_err());
+        assert!("truncate(abc)".parse::<MetricsMode>().is_err());
+        assert!("bounds".parse::<MetricsMode>().is_err());
+    }
+
+    #[test]
+    fn test_metrics_config_from_table_properties() {
+        let properties = HashMap::from([
+            (
+                "write.metadata.metrics.default".to_owned(),
+                "truncate(16)".to_owned(),
+            ),
+            (
+                "write.metadata.metrics.column.payload".to_owned(),
+                "none".to_owned(),
+            ),
+            ("write.format.default".to_owned(), "parquet".to_owned()),
+        ]);
+
+        let metrics_config = MetricsConfig::from_table_properties(&properties).unwrap();
+        assert_eq!(metrics_config.default_mode, MetricsMode::Truncate(16));
+        assert_eq!(metrics_config.column_modes.len(), 1);
+        assert_eq!(metrics_config.column_modes["payload"], MetricsMode::None);
+    }
+
+    #[test]
+    fn test_metrics_config_applied_to_default_writer_properties() {
+        let config = CompactionConfigBuilder::default()
+            .metrics_config(MetricsConfig {
+                default_mode: MetricsMode::Truncate(32),
+                column_modes: HashMap::from([
+                    ("payload".to_owned(), MetricsMode::None),
+                    ("name".to_owned(), MetricsMode::Truncate(8)),
+                ]),
+            })
+            .build()
+            .unwrap();
+
+        let properties = &config.write_parquet_properties;
+        assert_eq!(
+            properties.statistics_enabled(&ColumnPath::from("payload")),
+            EnabledStatistics::None
+        );
+        assert_eq!(
+            properties.statistics_enabled(&ColumnPath::from("id")),
+            EnabledStatistics::Page
+        );
+        assert_eq!(properties.statistics_truncate_length(), Some(8));
+    }
+}
@@ -0,0 +1,194 @@
+/*
+ * Copyright 2025 iceberg-compaction
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use iceberg::spec::{Schema, Type};
+
+use crate::error::{CompactionError, Result};
+use crate::CompactionConfig;
+
+/// Checks that the configuration can drive a compaction at all.
+pub fn check_config(config: &CompactionConfig) -> Result<()> {
+    if config.batch_parallelism == 0 {
+        return Err(CompactionError::Config(
+            "batch_parallelism must be greater than 0".to_owned(),
+        ));
+    }
+    if config.target_partitions == 0 {
+        return Err(CompactionError::Config(
+            "target_partitions must be greater than 0".to_owned(),
+        ));
+    }
+    if config.target_file_size == 0 {
+        return Err(CompactionError::Config(
+            "target_file_size must be greater than 0".to_owned(),
+        ));
+    }
+    if config.max_record_batch_rows == 0 || config.max_record_batch_values == 0 {
+        return Err(CompactionError::Config(
+            "max_record_batch_rows and max_record_batch_values must be greater than 0".to_owned(),
+        ));
+    }
+    if config.max_group_size_bytes == 0 || config.max_files_per_group == 0 {
+        return Err(CompactionError::Config(
+            "max_group_size_bytes and max_files_per_group must be greater than 0".to_owned(),
+        ));
+    }
+    if config.partial_progress_enabled && config.partial_progress_max_commits == 0 {
+        return Err(CompactionError::Config(
+            "partial_progress_max_commits must be greater than 0".to_owned(),
+        ));
+    }
+    if config.size_tier_ratio < 2 {
+        return Err(CompactionError::Config(
+            "size_tier_ratio must be at least 2".to_owned(),
+        ));
+    }
+    if !(0.0..=1.0).contains(&config.delete_ratio_threshold) {
+        return Err(CompactionError::Config(
+            "delete_ratio_threshold must be between 0 and 1".to_owned(),
+        ));
+    }
+    if config.data_file_prefix.is_empty() {
+        return Err(CompactionError::Config(
+            "data_file_prefix must not be empty".to_owned(),
+        ));
+    }
+    config.metrics_config.check()?;
+    Ok(())
+}
+
+/// Checks that `schema` has at most `CompactionConfig::max_columns` leaf columns.
+pub fn check_schema_width(schema: &Schema, config: &CompactionConfig) -> Result<()> {
+    let columns = leaf_columns(schema);
+    if columns > config.max_columns {
+        return Err(CompactionError::Config(format!(
+            "schema {} has {} leaf columns, more than max_columns {}; raise max_columns together \
+             with a lower max_record_batch_values if the rewrite has enough memory for it",
+            schema.schema_id(),
+            columns,
+            config.max_columns
+        )));
+    }
+    Ok(())
+}
+
+/// Rows of the record batches to read the data files of `schema` in, so that a batch holds at
+/// most `CompactionConfig::max_record_batch_values` values.
+pub fn record_batch_rows(schema: &Schema, config: &CompactionConfig) -> usize {
+    (config.max_record_batch_values / leaf_columns(schema).max(1))
+        .clamp(1, config.max_record_batch_rows)
+}
+
+/// Number of primitive columns of `schema`, including those nested in structs, lists and maps.
+fn leaf_columns(schema: &Schema) -> usize {
+    fn count(field_type: &Type) -> usize {
+        match field_type {
+            Type::Primitive(_) => 1,
+            Type::Struct(struct_type) => struct_type
+                .fields()
+                .iter()
+                .map(|field| count(&field.field_type))
+                .sum(),
+            Type::List(list_type) => count(&list_type.element_field.field_type),
+            Type::Map(map_type) => {
+                count(&map_type.key_field.field_type) + count(&map_type.value_field.field_type)
+            }
+        }
+    }
+    schema
+        .as_struct()
+        .fields()
+        .iter()
+        .map(|field| count(&field.field_type))
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::CompactionConfigBuilder;
+
+    #[test]
+    fn test_check_config_default() {
+        let config = CompactionConfigBuilder::default().build().unwrap();
+        assert!(check_config(&config).is_ok());
+    }
+
+    #[test]
+    fn test_check_config_rejects_zero_values() {
+        let config = CompactionConfigBuilder::default()
+            .batch_parallelism(0)
+            .build()
+            .unwrap();
+        assert!(matches!(
+            check_config(&config),
+            Err(CompactionError::Config(_))
+        ));
+
+        let config = CompactionConfigBuilder::default()
+            .target_file_size(0)
+            .build()
+            .unwrap();
+        assert!(matches!(
+            check_config(&config),
+            Err(CompactionError::Config(_))
+        ));
+    }
+
+    #[test]
+    fn test_schema_width() {
+        use iceberg::spec::{ListType, NestedField, PrimitiveType};
+
+        let mut fields: Vec<_> = (1..=2000)
+            .map(|id| {
+                NestedField::optional(id, format!("c{}", id), Type::Primitive(PrimitiveType::Int))
+                    .into()
+            })
+            .collect();
+        fields.push(
+            NestedField::optional(
+                2001,
+                "tags",
+                Type::List(ListType {
+                    element_field: NestedField::list_element(
+                        2002,
+                        Type::Primitive(PrimitiveType::String),
+                        true,
+                    )
+                    .into(),
+                }),
+            )
+            .into(),
+        );
+        let schema = Schema::builder().with_fields(fields).build().unwrap();
+        assert_eq!(leaf_columns(&schema), 2001);
+
+        let config = CompactionConfigBuilder::default().build().unwrap();
+        assert!(check_schema_width(&schema, &config).is_ok());
+        assert_eq!(record_batch_rows(&schema, &config), 524);
+
+        let config = CompactionConfigBuilder::default()
+            .max_columns(2000)
+            .max_record_batch_values(10)
+            .build()
+            .unwrap();
+        assert!(matches!(
+            check_schema_width(&schema, &config),
+            Err(CompactionError::Config(_))
+        ));
+        assert_eq!(record_batch_rows(&schema, &config), 1);
+    }
+}
@@ -38,6 +38,50 @@ pub enum CompactionError {
 
     #[error("Compaction unexpected failed: {0}")]
     Unexpected(String),
+
+    #[error("Preflight check failed: {0}")]
+    Preflight(String),
+
+    #[error("Unsupported table feature: {0}")]
+    UnsupportedTableFeature(String),
+
+    #[error("Schema changed during compaction: expected schema id {expected}, found {found}")]
+    SchemaChangedDuringCompaction { expected: i32, found: i32 },
+
+    #[error("Commit conflicts with a concurrent change: {0}")]
+    CommitConflict(String),
+
+    #[error("Commit hook rejected the commit: {0}")]
+    CommitHook(#[source] Box<CompactionError>),
+
+    #[error("Delete files reference missing data files: {0}")]
+    DanglingDeletes(String),
+
+    #[error("Invalid rewrite request: {0}")]
+    InvalidRequest(String),
+
+    #[error("Output file verification failed: {0}")]
+    OutputVerification(String),
+
+    #[error(
+        "Rewriting file group {group_id} of {} data files failed, first file '{}': {source}",
+        .data_file_paths.len(),
+        .data_file_paths.first().map_or("", String::as_str)
+    )]
+    FileGroup {
+        group_id: usize,
+        data_file_paths: Vec<String>,
+        source: Box<CompactionError>,
+    },
+
+    #[error(
+        "Repair failed after committing {} groups of duplicate data files: {source}",
+        .committed_sequence_numbers.len()
+    )]
+    PartialRepair {
+        committed_sequence_numbers: Vec<Option<i64>>,
+        source: Box<CompactionError>,
+    },
 }
 
 pub type Result<T> = std::result::Result<T, CompactionError>;
@@ -0,0 +1,68 @@
+/*
+ * Copyright 2025 iceberg-compaction
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::any::Any;
+use std::sync::Arc;
+
+use datafusion::arrow::datatypes::DataType;
+use datafusion::common::Result as DFResult;
+use datafusion::logical_expr::{ColumnarValue, ScalarUDF, ScalarUDFImpl, Signature, Volatility};
+
+use crate::clustering::ClusteringFunction;
+
+/// Exposes a [`ClusteringFunction`] to SQL, e.g. `ORDER BY zorder(a, b)`.
+#[derive(Debug)]
+pub struct ClusteringUdf {
+    function: ClusteringFunction,
+    signature: Signature,
+}
+
+impl ClusteringUdf {
+    pub fn new(function: ClusteringFunction) -> Self {
+        Self {
+            function,
+            signature: Signature::variadic_any(Volatility::Immutable),
+        }
+    }
+
+    pub fn into_scalar_udf(self) -> ScalarUDF {
+        ScalarUDF::new_from_impl(self)
+    }
+}
+
+impl ScalarUDFImpl for ClusteringUdf {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn name(&self) -> &str {
+        self.function.name()
+    }
+
+    fn signature(&self) -> &Signature {
+        &self.signature
+    }
+
+    fn return_type(&self, _arg_types: &[DataType]) -> DFResult<DataType> {
+        Ok(DataType::Binary)
+    }
+
+    fn invoke_batch(&self, args: &[ColumnarValue], _number_rows: usize) -> DFResult<ColumnarValue> {
+        let columns = ColumnarValue::values_to_arrays(args)?;
+        let keys = self.function.clustering_key(&columns)?;
+        Ok(ColumnarValue::Array(Arc::new(keys)))
+    }
+}
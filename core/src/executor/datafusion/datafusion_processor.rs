@@ -17,6 +17,7 @@
 use std::sync::Arc;
 
 use crate::{
+    clustering::ClusteringFunction,
     error::{CompactionError, Result},
     executor::InputFileScanTasks,
     CompactionConfig,
@@ -33,9 +34,13 @@ use iceberg::{
     arrow::schema_to_arrow_schema,
     io::FileIO,
     scan::FileScanTask,
-    spec::{NestedField, PrimitiveType, Schema, Type},
+    spec::{
+        NestedField, NullOrder, PrimitiveType, Schema, SortDirection, SortOrderRef, Transform,
+        Type,
+    },
 };
 
+use super::clustering_udf::ClusteringUdf;
 use super::file_scan_task_table_provider::IcebergFileScanTaskTableProvider;
 
 // System hidden columns used for Iceberg merge-on-read operations
@@ -57,6 +62,18 @@ impl DatafusionProcessor {
             .with_target_partitions(config.target_partitions)
             .with_batch_size(config.max_record_batch_rows);
         let ctx = Arc::new(SessionContext::new_with_config(session_config));
+        Self::new_with_context(config, file_io, ctx)
+    }
+
+    /// Creates a processor running its queries in a shared `ctx`. Tables of each task are
+    /// registered under the task's table prefix and deregistered once its plan is created, so
+    /// concurrent tasks with distinct prefixes don't collide.
+    pub fn new_with_context(
+        config: Arc<CompactionConfig>,
+        file_io: FileIO,
+        ctx: Arc<SessionContext>,
+    ) -> Self {
+        ctx.register_udf(ClusteringUdf::new(config.clustering_function).into_scalar_udf());
         let table_register = DatafusionTableRegister::new(
             file_io,
             ctx.clone(),
@@ -71,22 +88,43 @@ impl DatafusionProcessor {
     }
 
     /// Registers all necessary tables (data files, position deletes, equality deletes) with DataFusion
-    pub fn register_tables(&self, mut datafusion_task_ctx: DataFusionTaskContext) -> Result<()> {
+    ///
+    /// Returns the names of the registered tables. On error, no table is left registered.
+    pub fn register_tables(
+        &self,
+        datafusion_task_ctx: DataFusionTaskContext,
+    ) -> Result<Vec<String>> {
+        let mut table_names = vec![];
+        if let Err(e) = self.register_tables_impl(datafusion_task_ctx, &mut table_names) {
+            self.table_register.deregister_tables(&table_names)?;
+            return Err(e);
+        }
+        Ok(table_names)
+    }
+
+    fn register_tables_impl(
+        &self,
+        mut datafusion_task_ctx: DataFusionTaskContext,
+        table_names: &mut Vec<String>,
+    ) -> Result<()> {
         // Register data file table if present
         if let Some(datafile_schema) = datafusion_task_ctx.data_file_schema.take() {
+            let table_name = datafusion_task_ctx.data_file_table_name();
             self.table_register.register_data_table_provider(
                 &datafile_schema,
                 datafusion_task_ctx.data_files.take().ok_or_else(|| {
                     CompactionError::Unexpected("Data files are not set".to_owned())
                 })?,
-                &datafusion_task_ctx.data_file_table_name(),
+                &table_name,
                 datafusion_task_ctx.need_seq_num(),
                 datafusion_task_ctx.need_file_path_and_pos(),
             )?;
+            table_names.push(table_name);
         }
 
         // Register position delete table if present
         if let Some(position_delete_schema) = datafusion_task_ctx.position_delete_schema.take() {
+            let table_name = datafusion_task_ctx.position_delete_table_name();
             self.table_register.register_delete_table_provider(
                 &position_delete_schema,
                 datafusion_task_ctx
@@ -95,8 +133,9 @@ impl DatafusionProcessor {
                     .ok_or_else(|| {
                         CompactionError::Unexpected("Position delete files are not set".to_owned())
                     })?,
-                &datafusion_task_ctx.position_delete_table_name(),
+                &table_name,
             )?;
+            table_names.push(table_name);
         }
 
         // Register equality delete tables if present
@@ -114,6 +153,7 @@ impl DatafusionProcessor {
                     file_scan_tasks,
                     &equality_delete_table_name,
                 )?;
+                table_names.push(equality_delete_table_name);
             }
         }
         Ok(())
@@ -135,20 +175,22 @@ impl DatafusionProcessor {
             .take()
             .ok_or_else(|| CompactionError::Unexpected("Input schema is not set".to_owned()))?;
         let exec_sql = datafusion_task_ctx.exec_sql.clone();
+        let output_partitions = datafusion_task_ctx
+            .output_partitions
+            .unwrap_or(self.config.target_partitions);
 
-        self.register_tables(datafusion_task_ctx)?;
-
-        let df = self.ctx.sql(&exec_sql).await?;
-        let physical_plan = df.create_physical_plan().await?;
+        let table_names = self.register_tables(datafusion_task_ctx)?;
+        let physical_plan = self.create_physical_plan(&exec_sql).await;
+        // The plan keeps its own references to the table providers.
+        self.table_register.deregister_tables(&table_names)?;
+        let physical_plan = physical_plan?;
 
         // Conditionally create a new physical_plan if repartitioning is needed
         let plan_to_execute: Arc<dyn ExecutionPlan + 'static> =
-            if physical_plan.output_partitioning().partition_count()
-                != self.config.target_partitions
-            {
+            if physical_plan.output_partitioning().partition_count() != output_partitions {
                 Arc::new(RepartitionExec::try_new(
                     physical_plan,
-                    Partitioning::RoundRobinBatch(self.config.target_partitions),
+                    Partitioning::RoundRobinBatch(output_partitions),
                 )?)
             } else {
                 physical_plan
@@ -158,6 +200,11 @@ impl DatafusionProcessor {
 
         Ok((batches, input_schema))
     }
+
+    async fn create_physical_plan(&self, sql: &str) -> Result<Arc<dyn ExecutionPlan>> {
+        let df = self.ctx.sql(sql).await?;
+        Ok(df.create_physical_plan().await?)
+    }
 }
 
 pub struct DatafusionTableRegister {
@@ -233,6 +280,13 @@ impl DatafusionTableRegister {
 
         Ok(())
     }
+
+    pub fn deregister_tables(&self, table_names: &[String]) -> Result<()> {
+        for table_name in table_names {
+            self.ctx.deregister_table(table_name.as_str())?;
+        }
+        Ok(())
+    }
 }
 
 /// SQL Builder for generating merge-on-read SQL queries
@@ -251,6 +305,9 @@ struct SqlBuilder<'a> {
 
     /// Flag indicating if position delete files are needed
     need_file_path_and_pos: bool,
+
+    /// ORDER BY expressions applied to the final result
+    order_by: Vec<String>,
 }
 
 impl<'a> SqlBuilder<'a> {
@@ -268,6 +325,21 @@ impl<'a> SqlBuilder<'a> {
             data_file_table_name,
             equality_delete_metadatas,
             need_file_path_and_pos,
+            order_by: vec![],
+        }
+    }
+
+    /// Sorts the final result by the given ORDER BY expressions
+    fn with_order_by(mut self, order_by: Vec<String>) -> Self {
+        self.order_by = order_by;
+        self
+    }
+
+    fn order_by_clause(&self) -> String {
+        if self.order_by.is_empty() {
+            String::new()
+        } else {
+            format!(" ORDER BY {}", self.order_by.join(", "))
         }
     }
 
@@ -289,9 +361,10 @@ impl<'a> SqlBuilder<'a> {
         // Early return for simple case: no deletes at all
         if !need_seq_num && !need_file_path_and_pos {
             return Ok(format!(
-                "SELECT {} FROM {}",
+                "SELECT {} FROM {}{}",
                 self.project_names.join(", "),
-                data_file_table_name
+                data_file_table_name,
+                self.order_by_clause()
             ));
         }
 
@@ -388,9 +461,10 @@ impl<'a> SqlBuilder<'a> {
         // Final SELECT to return only the project columns (without hidden columns)
         if need_seq_num || need_file_path_and_pos {
             query = format!(
-                "SELECT {} FROM ({}) AS final_result",
+                "SELECT {} FROM ({}) AS final_result{}",
                 self.project_names.join(", "),
-                query
+                query,
+                self.order_by_clause()
             );
         }
 
@@ -409,6 +483,7 @@ pub struct DataFusionTaskContext {
     pub(crate) equality_delete_metadatas: Option<Vec<EqualityDeleteMetadata>>,
     pub(crate) exec_sql: String,
     pub(crate) table_prefix: String,
+    pub(crate) output_partitions: Option<usize>,
 }
 
 pub struct DataFusionTaskContextBuilder {
@@ -417,6 +492,10 @@ pub struct DataFusionTaskContextBuilder {
     position_delete_files: Vec<FileScanTask>,
     equality_delete_files: Vec<FileScanTask>,
     table_prefix: String,
+    sort_order: Option<SortOrderRef>,
+    clustering_columns: Vec<String>,
+    clustering_function: ClusteringFunction,
+    output_partitions: Option<usize>,
 }
 
 impl DataFusionTaskContextBuilder {
@@ -430,6 +509,27 @@ impl DataFusionTaskContextBuilder {
         self
     }
 
+    /// Sorts the output rows by the given sort order
+    pub fn with_sort_order(mut self, sort_order: Option<SortOrderRef>) -> Self {
+        self.sort_order = sort_order;
+        self
+    }
+
+    /// Sorts the output rows by the clustering key of `columns`, taking precedence over the
+    /// sort order when not empty
+    pub fn with_clustering(mut self, columns: Vec<String>, function: ClusteringFunction) -> Self {
+        self.clustering_columns = columns;
+        self.clustering_function = function;
+        self
+    }
+
+    /// Number of output streams, and thus of data file writers, overriding
+    /// `CompactionConfig::target_partitions`. Sorted or clustered output always has one.
+    pub fn with_output_partitions(mut self, output_partitions: usize) -> Self {
+        self.output_partitions = Some(output_partitions);
+        self
+    }
+
     pub fn with_input_data_files(mut self, input_file_scan_tasks: InputFileScanTasks) -> Self {
         self.data_files = input_file_scan_tasks.data_files;
         self.position_delete_files = input_file_scan_tasks.position_delete_files;
@@ -452,7 +552,7 @@ impl DataFusionTaskContextBuilder {
         self
     }
 
-    fn build_position_schema() -> Result<Schema> {
+    pub(crate) fn build_position_schema() -> Result<Schema> {
         let position_delete_schema = Schema::builder()
             .with_fields(vec![
                 Arc::new(NestedField::new(
@@ -561,7 +661,28 @@ impl DataFusionTaskContextBuilder {
             need_file_path_and_pos,
         );
 
-        let exec_sql = sql_builder.build_merge_on_read_sql()?;
+        let order_by = if !self.clustering_columns.is_empty() {
+            vec![build_clustering_key(
+                &self.schema,
+                &self.clustering_columns,
+                self.clustering_function,
+            )?]
+        } else {
+            match &self.sort_order {
+                Some(sort_order) => build_order_by(&self.schema, sort_order),
+                None => vec![],
+            }
+        };
+        // Dealing sorted rows out to several writers would give every output file most of the
+        // key range, so sorted output is written by a single writer.
+        let output_partitions = if order_by.is_empty() {
+            self.output_partitions
+        } else {
+            Some(1)
+        };
+        let exec_sql = sql_builder
+            .with_order_by(order_by)
+            .build_merge_on_read_sql()?;
 
         Ok(DataFusionTaskContext {
             data_file_schema: Some(data_file_schema),
@@ -589,6 +710,7 @@ impl DataFusionTaskContextBuilder {
             },
             exec_sql,
             table_prefix: self.table_prefix,
+            output_partitions,
         })
     }
 
@@ -628,7 +750,11 @@ impl DataFusionTaskContext {
             data_files: vec![],
             position_delete_files: vec![],
             equality_delete_files: vec![],
-            table_prefix: "".to_owned(),
+            table_prefix: table_name::unique_table_prefix(),
+            sort_order: None,
+            clustering_columns: vec![],
+            clustering_function: ClusteringFunction::default(),
+            output_partitions: None,
         })
     }
 
@@ -659,6 +785,87 @@ impl DataFusionTaskContext {
     }
 }
 
+/// Whether ordering rows by a transform's source column also orders them by the transform result.
+pub fn is_order_preserving(transform: &Transform) -> bool {
+    matches!(
+        transform,
+        Transform::Identity
+            | Transform::Year
+            | Transform::Month
+            | Transform::Day
+            | Transform::Hour
+            | Transform::Truncate(_)
+            | Transform::Void
+    )
+}
+
+/// Builds ORDER BY expressions for a sort order over top-level columns of `schema`.
+///
+/// Order-preserving transforms sort by their source column. Sorting stops at the first field that
+/// can't be expressed that way, such as a bucket transform or a nested column.
+fn build_order_by(schema: &Schema, sort_order: &SortOrderRef) -> Vec<String> {
+    let mut order_by = vec![];
+    for sort_field in &sort_order.fields {
+        if !is_order_preserving(&sort_field.transform) {
+            break;
+        }
+        if sort_field.transform == Transform::Void {
+            continue;
+        }
+        let Some(field) = schema
+            .as_struct()
+            .fields()
+            .iter()
+            .find(|field| field.id == sort_field.source_id)
+        else {
+            break;
+        };
+        let direction = match sort_field.direction {
+            SortDirection::Ascending => "ASC",
+            SortDirection::Descending => "DESC",
+        };
+        let null_order = match sort_field.null_order {
+            NullOrder::First => "NULLS FIRST",
+            NullOrder::Last => "NULLS LAST",
+        };
+        order_by.push(format!(
+            "{} {} {}",
+            quote_identifier(&field.name),
+            direction,
+            null_order
+        ));
+    }
+    order_by
+}
+
+/// Quotes a column name for SQL, so that mixed-case and reserved names are kept as they are.
+fn quote_identifier(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+/// Builds the `ORDER BY` expression clustering rows by `columns`.
+fn build_clustering_key(
+    schema: &Schema,
+    columns: &[String],
+    function: ClusteringFunction,
+) -> Result<String> {
+    for column in columns {
+        if !schema
+            .as_struct()
+            .fields()
+            .iter()
+            .any(|field| &field.name == column)
+        {
+            return Err(CompactionError::Config(format!(
+                "clustering column '{}' is not a top-level column of the table",
+                column
+            )));
+        }
+    }
+    let columns: Vec<String> = columns.iter().map(|c| quote_identifier(c)).collect();
+    Ok(format!("{}({})", function.name(), columns.join(", ")))
+}
+
 /// Metadata for equality delete files
 #[derive(Debug, Clone)]
 pub(crate) struct EqualityDeleteMetadata {
@@ -692,10 +899,17 @@ impl EqualityDeleteMetadata {
 }
 
 mod table_name {
+    use uuid::Uuid;
+
     pub const DATA_FILE_TABLE: &str = "data_file_table";
     pub const POSITION_DELETE_TABLE: &str = "position_delete_table";
     pub const EQUALITY_DELETE_TABLE: &str = "equality_delete_table";
 
+    /// A table prefix no other task uses, so tasks can share a `SessionContext`.
+    pub fn unique_table_prefix() -> String {
+        format!("task_{}", Uuid::now_v7().simple())
+    }
+
     pub fn build_data_file_table_name(table_prefix: &str) -> String {
         format!("{}_{}", table_prefix, DATA_FILE_TABLE)
     }
@@ -718,7 +932,7 @@ mod tests {
     };
 
     use super::*;
-    use iceberg::spec::{NestedField, PrimitiveType, Schema, Type};
+    use iceberg::spec::{NestedField, PrimitiveType, Schema, SortField, SortOrder, Type};
     use std::sync::Arc;
 
     /// Test building SQL with no delete files
@@ -744,6 +958,135 @@ mod tests {
         );
     }
 
+    /// Test building SQL sorted by the table sort order
+    #[test]
+    fn test_build_merge_on_read_sql_with_sort_order() {
+        let schema = Schema::builder()
+            .with_fields(vec![
+                Arc::new(NestedField::required(
+                    1,
+                    "id",
+                    Type::Primitive(PrimitiveType::Int),
+                )),
+                Arc::new(NestedField::required(
+                    2,
+                    "name",
+                    Type::Primitive(PrimitiveType::String),
+                )),
+            ])
+            .build()
+            .unwrap();
+        let sort_order = SortOrder::builder()
+            .with_order_id(1)
+            .with_sort_field(
+                SortField::builder()
+                    .source_id(2)
+                    .transform(Transform::Identity)
+                    .direction(SortDirection::Descending)
+                    .null_order(NullOrder::First)
+                    .build(),
+            )
+            .with_sort_field(
+                SortField::builder()
+                    .source_id(1)
+                    .transform(Transform::Bucket(16))
+                    .direction(SortDirection::Ascending)
+                    .null_order(NullOrder::Last)
+                    .build(),
+            )
+            .build(&schema)
+            .unwrap();
+        let order_by = build_order_by(&schema, &Arc::new(sort_order.clone()));
+        assert_eq!(order_by, vec!["\"name\" DESC NULLS FIRST".to_owned()]);
+
+        let project_names = vec!["id".to_owned(), "name".to_owned()];
+        let equality_join_names = Vec::new();
+        let builder = SqlBuilder::new(
+            &project_names,
+            Some(POSITION_DELETE_TABLE.to_owned()),
+            Some(DATA_FILE_TABLE.to_owned()),
+            &equality_join_names,
+            false,
+        )
+        .with_order_by(order_by);
+        assert_eq!(
+            builder.build_merge_on_read_sql().unwrap(),
+            format!(
+                "SELECT id, name FROM {} ORDER BY \"name\" DESC NULLS FIRST",
+                DATA_FILE_TABLE
+            )
+        );
+
+        let task_ctx = DataFusionTaskContext::builder()
+            .unwrap()
+            .with_schema(Arc::new(schema))
+            .with_sort_order(Some(Arc::new(sort_order)))
+            .with_output_partitions(4)
+            .build()
+            .unwrap();
+        assert_eq!(task_ctx.output_partitions, Some(1));
+    }
+
+    #[test]
+    fn test_quote_identifier() {
+        assert_eq!(quote_identifier("eventTime"), "\"eventTime\"");
+        assert_eq!(quote_identifier("a\"b"), "\"a\"\"b\"");
+    }
+
+    /// Test that every task registers its tables under its own names
+    #[test]
+    fn test_task_table_names_are_unique() {
+        let first = DataFusionTaskContext::builder().unwrap().table_prefix;
+        let second = DataFusionTaskContext::builder().unwrap().table_prefix;
+        assert_ne!(
+            table_name::build_data_file_table_name(&first),
+            table_name::build_data_file_table_name(&second)
+        );
+    }
+
+    /// Test building the clustering key of the output rows
+    #[test]
+    fn test_build_clustering_key() {
+        let schema = Schema::builder()
+            .with_fields(vec![
+                Arc::new(NestedField::required(
+                    1,
+                    "id",
+                    Type::Primitive(PrimitiveType::Int),
+                )),
+                Arc::new(NestedField::required(
+                    2,
+                    "name",
+                    Type::Primitive(PrimitiveType::String),
+                )),
+            ])
+            .build()
+            .unwrap();
+        let columns = vec!["id".to_owned(), "name".to_owned()];
+        assert_eq!(
+            build_clustering_key(&schema, &columns, ClusteringFunction::ZOrder).unwrap(),
+            "zorder(\"id\", \"name\")"
+        );
+        assert!(build_clustering_key(
+            &schema,
+            &["missing".to_owned()],
+            ClusteringFunction::ZOrder
+        )
+        .is_err());
+
+        let task_ctx = DataFusionTaskContext::builder()
+            .unwrap()
+            .with_schema(Arc::new(schema))
+            .with_clustering(columns, ClusteringFunction::Hilbert)
+            .with_output_partitions(4)
+            .build()
+            .unwrap();
+        assert_eq!(task_ctx.output_partitions, Some(1));
+        assert!(task_ctx
+            .exec_sql
+            .ends_with("ORDER BY hilbert(\"id\", \"name\")"));
+    }
+
     /// Test building SQL with position delete files
     #[test]
     fn test_build_merge_on_read_sql_with_position_deletes() {
@@ -989,6 +1332,10 @@ mod tests {
             position_delete_files: vec![],
             equality_delete_files: vec![],
             table_prefix: "".to_owned(),
+            sort_order: None,
+            clustering_columns: vec![],
+            clustering_function: ClusteringFunction::default(),
+            output_partitions: None,
         };
 
         let equality_ids = vec![1, 2];
@@ -0,0 +1,392 @@
+/*
+ * Copyright 2025 iceberg-compaction
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use datafusion::arrow::array::AsArray;
+use datafusion::arrow::compute::cast;
+use datafusion::arrow::datatypes::{DataType, Int64Type};
+use datafusion::error::DataFusionError;
+use datafusion::execution::SendableRecordBatchStream;
+use datafusion::prelude::{SessionConfig, SessionContext};
+use futures::StreamExt;
+use iceberg::io::FileIO;
+use iceberg::scan::FileScanTask;
+use iceberg::spec::{DataFile, DataFileFormat, NestedField, PrimitiveType, Schema, Type};
+use iceberg::writer::base_writer::equality_delete_writer::{
+    EqualityDeleteFileWriterBuilder, EqualityDeleteWriterConfig,
+};
+use iceberg::writer::base_writer::sort_position_delete_writer::{
+    PositionDeleteInput, SortPositionDeleteWriterBuilder, POSITION_DELETE_SCHEMA,
+};
+use iceberg::writer::file_writer::location_generator::{
+    DefaultFileNameGenerator, DefaultLocationGenerator,
+};
+use iceberg::writer::file_writer::ParquetWriterBuilder;
+use iceberg::writer::{IcebergWriter, IcebergWriterBuilder};
+use sqlx::types::Uuid;
+
+use super::datafusion_processor::{
+    DataFusionTaskContextBuilder, DatafusionTableRegister, SYS_HIDDEN_FILE_PATH, SYS_HIDDEN_POS,
+    SYS_HIDDEN_SEQ_NUM,
+};
+use crate::error::{CompactionError, Result};
+use crate::CompactionConfig;
+
+const DATA_FILE_TABLE: &str = "data_file_table";
+const DELETE_FILE_TABLE: &str = "delete_file_table";
+/// Position deletes buffered and sorted in memory before a delete file is written.
+const POSITION_DELETE_CACHE_ROWS: usize = 1024 * 1024;
+
+/// Rewrites delete files without touching the data files they apply to.
+///
+/// Output files are not partitioned, so it must only be used on unpartitioned tables.
+pub struct DeleteFileRewriter {
+    config: Arc<CompactionConfig>,
+    file_io: FileIO,
+    dir_path: String,
+    partition_spec_id: i32,
+}
+
+impl DeleteFileRewriter {
+    pub fn new(
+        config: Arc<CompactionConfig>,
+        file_io: FileIO,
+        dir_path: String,
+        partition_spec_id: i32,
+    ) -> Self {
+        Self {
+            config,
+            file_io,
+            dir_path,
+            partition_spec_id,
+        }
+    }
+
+    /// Merges position delete files, dropping duplicated deletes and deletes of data files
+    /// that are not in `live_data_files`.
+    pub async fn rewrite_position_deletes(
+        &self,
+        position_delete_files: Vec<FileScanTask>,
+        live_data_files: &HashSet<String>,
+    ) -> Result<Vec<DataFile>> {
+        let schema = DataFusionTaskContextBuilder::build_position_schema()?;
+        let columns: Vec<&str> = schema
+            .as_struct()
+            .fields()
+            .iter()
+            .map(|field| field.name.as_str())
+            .collect();
+        let (ctx, table_register) = self.session();
+        table_register.register_delete_table_provider(
+            &schema,
+            position_delete_files,
+            DELETE_FILE_TABLE,
+        )?;
+        let batches = ctx
+            .sql(&format!(
+                "SELECT DISTINCT {} FROM {}",
+                columns.join(", "),
+                DELETE_FILE_TABLE
+            ))
+            .await?
+            .execute_stream()
+            .await?;
+
+        let mut writer = self.position_delete_writer().await?;
+        write_positions(writer.as_mut(), batches, |path| live_data_files.contains(path)).await?;
+        Ok(writer.close().await?)
+    }
+
+    /// Returns the paths of the data files `position_delete_files` delete rows of.
+    pub async fn referenced_data_files(
+        &self,
+        position_delete_files: Vec<FileScanTask>,
+    ) -> Result<HashSet<String>> {
+        let schema = DataFusionTaskContextBuilder::build_position_schema()?;
+        let (ctx, table_register) = self.session();
+        table_register.register_delete_table_provider(
+            &schema,
+            position_delete_files,
+            DELETE_FILE_TABLE,
+        )?;
+        let mut batches = ctx
+            .sql(&format!(
+                "SELECT DISTINCT {} FROM {}",
+                SYS_HIDDEN_FILE_PATH, DELETE_FILE_TABLE
+            ))
+            .await?
+            .execute_stream()
+            .await?;
+
+        let mut paths = HashSet::new();
+        while let Some(batch) = batches.next().await {
+            let batch = batch?;
+            let column = cast(batch.column(0), &DataType::Utf8).map_err(DataFusionError::from)?;
+            paths.extend(column.as_string::<i32>().iter().flatten().map(str::to_owned));
+        }
+        Ok(paths)
+    }
+
+    /// Resolves equality delete files against the `data_files` they apply to and writes the
+    /// deleted rows as position deletes, which readers apply without a join.
+    pub async fn convert_equality_deletes(
+        &self,
+        table_schema: &Schema,
+        data_files: Vec<FileScanTask>,
+        equality_delete_files: Vec<FileScanTask>,
+    ) -> Result<Vec<DataFile>> {
+        let (ctx, table_register) = self.session();
+        let highest_field_id = table_schema.highest_field_id();
+        let data_file_schema = table_schema
+            .clone()
+            .into_builder()
+            .with_fields(vec![
+                Arc::new(NestedField::new(
+                    highest_field_id + 1,
+                    SYS_HIDDEN_SEQ_NUM,
+                    Type::Primitive(PrimitiveType::Long),
+                    true,
+                )),
+                Arc::new(NestedField::new(
+                    highest_field_id + 2,
+                    SYS_HIDDEN_FILE_PATH,
+                    Type::Primitive(PrimitiveType::String),
+                    true,
+                )),
+                Arc::new(NestedField::new(
+                    highest_field_id + 3,
+                    SYS_HIDDEN_POS,
+                    Type::Primitive(PrimitiveType::Long),
+                    true,
+                )),
+            ])
+            .build()?;
+        table_register.register_data_table_provider(
+            &data_file_schema,
+            data_files,
+            DATA_FILE_TABLE,
+            true,
+            true,
+        )?;
+
+        let mut groups: HashMap<Vec<i32>, Vec<FileScanTask>> = HashMap::new();
+        for task in equality_delete_files {
+            groups
+                .entry(task.equality_ids.clone())
+                .or_default()
+                .push(task);
+        }
+        let mut writer = self.position_delete_writer().await?;
+        for (table_idx, (equality_ids, tasks)) in groups.into_iter().enumerate() {
+            let table_name = format!("{}_{}", DELETE_FILE_TABLE, table_idx);
+            let (read_schema, columns) = equality_delete_read_schema(table_schema, &equality_ids)?;
+            table_register.register_delete_table_provider(&read_schema, tasks, &table_name)?;
+
+            let mut conditions: Vec<String> = columns
+                .iter()
+                .map(|column| format!("{}.{} = {}.{}", table_name, column, DATA_FILE_TABLE, column))
+                .collect();
+            conditions.push(format!(
+                "{}.{} < {}.{}",
+                DATA_FILE_TABLE, SYS_HIDDEN_SEQ_NUM, table_name, SYS_HIDDEN_SEQ_NUM
+            ));
+            let batches = ctx
+                .sql(&format!(
+                    "SELECT DISTINCT {}.{}, {}.{} FROM {} JOIN {} ON {}",
+                    DATA_FILE_TABLE,
+                    SYS_HIDDEN_FILE_PATH,
+                    DATA_FILE_TABLE,
+                    SYS_HIDDEN_POS,
+                    DATA_FILE_TABLE,
+                    table_name,
+                    conditions.join(" AND ")
+                ))
+                .await?
+                .execute_stream()
+                .await?;
+            write_positions(writer.as_mut(), batches, |_| true).await?;
+        }
+        Ok(writer.close().await?)
+    }
+
+    /// Merges equality delete files sharing the same `equality_ids` and sequence number,
+    /// dropping duplicated deletes.
+    pub async fn rewrite_equality_deletes(
+        &self,
+        table_schema: &Schema,
+        equality_ids: &[i32],
+        equality_delete_files: Vec<FileScanTask>,
+    ) -> Result<Vec<DataFile>> {
+        let (read_schema, columns) = equality_delete_read_schema(table_schema, equality_ids)?;
+        let equality_delete_schema = Arc::new(
+            Schema::builder()
+                .with_fields(
+                    read_schema
+                        .as_struct()
+                        .fields()
+                        .iter()
+                        .take(columns.len())
+                        .cloned(),
+                )
+                .build()?,
+        );
+        let (ctx, table_register) = self.session();
+        table_register.register_delete_table_provider(
+            &read_schema,
+            equality_delete_files,
+            DELETE_FILE_TABLE,
+        )?;
+        let mut batches = ctx
+            .sql(&format!(
+                "SELECT DISTINCT {}, {} FROM {}",
+                columns.join(", "),
+                SYS_HIDDEN_SEQ_NUM,
+                DELETE_FILE_TABLE
+            ))
+            .await?
+            .execute_stream()
+            .await?;
+
+        let writer_config = EqualityDeleteWriterConfig::new(
+            equality_ids.to_vec(),
+            equality_delete_schema.clone(),
+            None,
+            self.partition_spec_id,
+        )?;
+        let mut writer = EqualityDeleteFileWriterBuilder::new(
+            ParquetWriterBuilder::new(
+                self.config.write_parquet_properties.clone(),
+                equality_delete_schema,
+                self.file_io.clone(),
+                self.location_generator(),
+                self.file_name_generator("eq-del"),
+            ),
+            writer_config,
+        )
+        .build()
+        .await?;
+        let projection: Vec<usize> = (0..columns.len()).collect();
+        while let Some(batch) = batches.next().await {
+            let batch = batch?
+                .project(&projection)
+                .map_err(DataFusionError::from)?;
+            writer.write(batch).await?;
+        }
+        Ok(writer.close().await?)
+    }
+
+    fn session(&self) -> (Arc<SessionContext>, DatafusionTableRegister) {
+        let session_config =
+            SessionConfig::new().with_batch_size(self.config.max_record_batch_rows);
+        let ctx = Arc::new(SessionContext::new_with_config(session_config));
+        let table_register = DatafusionTableRegister::new(
+            self.file_io.clone(),
+            ctx.clone(),
+            self.config.batch_parallelism,
+            self.config.max_record_batch_rows,
+        );
+        (ctx, table_register)
+    }
+
+    async fn position_delete_writer(
+        &self,
+    ) -> Result<Box<dyn IcebergWriter<PositionDeleteInput>>> {
+        let writer = SortPositionDeleteWriterBuilder::new(
+            ParquetWriterBuilder::new(
+                self.config.write_parquet_properties.clone(),
+                POSITION_DELETE_SCHEMA.clone(),
+                self.file_io.clone(),
+                self.location_generator(),
+                self.file_name_generator("pos-del"),
+            ),
+            POSITION_DELETE_CACHE_ROWS,
+            None,
+            None,
+        )
+        .build()
+        .await?;
+        Ok(Box::new(writer))
+    }
+
+    fn location_generator(&self) -> DefaultLocationGenerator {
+        DefaultLocationGenerator {
+            dir_path: self.dir_path.clone(),
+        }
+    }
+
+    fn file_name_generator(&self, kind: &str) -> DefaultFileNameGenerator {
+        DefaultFileNameGenerator::new(
+            self.config.data_file_prefix.clone(),
+            Some(format!("{}-{}", kind, Uuid::now_v7())),
+            DataFileFormat::Parquet,
+        )
+    }
+}
+
+/// Returns the schema equality delete files are read with, i.e. the equality columns followed by
+/// the sequence number, and the names of the equality columns.
+fn equality_delete_read_schema(
+    table_schema: &Schema,
+    equality_ids: &[i32],
+) -> Result<(Schema, Vec<String>)> {
+    let mut fields = equality_ids
+        .iter()
+        .map(|id| {
+            table_schema
+                .field_by_id(*id)
+                .cloned()
+                .ok_or_else(|| CompactionError::Execution(format!("equality id {} not found", id)))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let columns = fields.iter().map(|field| field.name.clone()).collect();
+    fields.push(Arc::new(NestedField::new(
+        table_schema.highest_field_id() + 1,
+        SYS_HIDDEN_SEQ_NUM,
+        Type::Primitive(PrimitiveType::Long),
+        true,
+    )));
+    Ok((Schema::builder().with_fields(fields).build()?, columns))
+}
+
+/// Writes the `(file path, position)` rows of `batches` accepted by `keep_path`.
+async fn write_positions(
+    writer: &mut dyn IcebergWriter<PositionDeleteInput>,
+    mut batches: SendableRecordBatchStream,
+    keep_path: impl Fn(&str) -> bool,
+) -> Result<()> {
+    while let Some(batch) = batches.next().await {
+        let batch = batch?;
+        let paths = cast(batch.column(0), &DataType::Utf8).map_err(DataFusionError::from)?;
+        let paths = paths.as_string::<i32>();
+        let positions = batch.column(1).as_primitive::<Int64Type>();
+        for (path, position) in paths.iter().zip(positions.iter()) {
+            let (Some(path), Some(position)) = (path, position) else {
+                continue;
+            };
+            if keep_path(path) {
+                writer
+                    .write(PositionDeleteInput {
+                        path: path.into(),
+                        offset: position,
+                    })
+                    .await?;
+            }
+        }
+    }
+    Ok(())
+}
@@ -22,8 +22,8 @@ use std::vec;
 
 use async_stream::try_stream;
 use datafusion::arrow::array::{Int64Array, RecordBatch, StringArray};
-use datafusion::arrow::compute::concat_batches;
-use datafusion::arrow::datatypes::{Field, Schema, SchemaRef as ArrowSchemaRef};
+use datafusion::arrow::compute::{cast_with_options, concat_batches, CastOptions};
+use datafusion::arrow::datatypes::{DataType, Field, Schema, SchemaRef as ArrowSchemaRef};
 use datafusion::error::{DataFusionError, Result as DFResult};
 use datafusion::execution::{SendableRecordBatchStream, TaskContext};
 use datafusion::physical_expr::EquivalenceProperties;
@@ -283,6 +283,7 @@ impl ExecutionPlan for IcebergFileTaskScan {
         _context: Arc<TaskContext>,
     ) -> DFResult<SendableRecordBatchStream> {
         let fut = get_batch_stream(
+            self.schema(),
             self.file_io.clone(),
             self.file_scan_tasks_group[partition].clone(),
             self.need_seq_num,
@@ -300,6 +301,7 @@ impl ExecutionPlan for IcebergFileTaskScan {
 
 /// Gets a stream of record batches from a list of file scan tasks
 async fn get_batch_stream(
+    output_schema: ArrowSchemaRef,
     file_io: FileIO,
     file_scan_tasks: Vec<FileScanTask>,
     need_seq_num: bool,
@@ -313,14 +315,16 @@ async fn get_batch_stream(
             let data_file_content = task.data_file_content;
             let sequence_number = task.sequence_number;
             let task_stream = futures::stream::iter(vec![Ok(task)]).boxed();
-            let arrow_reader_builder = ArrowReaderBuilder::new(file_io.clone()).with_batch_size(max_record_batch_rows);
+            let arrow_reader_builder =
+                ArrowReaderBuilder::new(file_io.clone()).with_batch_size(max_record_batch_rows);
             let mut batch_stream = arrow_reader_builder.build()
                 .read(task_stream)
                 .await
                 .map_err(to_datafusion_error)?;
             let mut index_start = 0;
             while let Some(batch) = batch_stream.next().await {
-                let mut batch = batch.map_err(to_datafusion_error)?;
+                let batch = batch.map_err(to_datafusion_error)?;
+                let mut batch = cast_to_output_types(batch, &output_schema)?;
                 let batch = match data_file_content {
                     iceberg::spec::DataContentType::Data => {
                         // add sequence number if needed
@@ -353,6 +357,58 @@ async fn get_batch_stream(
     Ok(Box::pin(stream))
 }
 
+/// Casts timestamp columns whose unit or time zone differs from the same-named output column,
+/// such as INT96 timestamps that files from legacy Spark/Hive writers decode to nanoseconds while
+/// the table uses microseconds. Other mismatches are left for the writer to reject, and values out
+/// of range of the output type fail the cast instead of becoming nulls.
+fn cast_to_output_types(
+    batch: RecordBatch,
+    output_schema: &ArrowSchemaRef,
+) -> DFResult<RecordBatch> {
+    let schema = batch.schema();
+    let mut casted = false;
+    let mut fields = Vec::with_capacity(schema.fields().len());
+    let mut columns = Vec::with_capacity(batch.num_columns());
+    for (field, column) in schema.fields().iter().zip(batch.columns()) {
+        match output_schema.field_with_name(field.name()) {
+            Ok(output_field)
+                if output_field.data_type() != field.data_type()
+                    && matches!(
+                        (field.data_type(), output_field.data_type()),
+                        (DataType::Timestamp(..), DataType::Timestamp(..))
+                    ) =>
+            {
+                let cast_options = CastOptions {
+                    safe: false,
+                    ..Default::default()
+                };
+                columns.push(cast_with_options(
+                    column,
+                    output_field.data_type(),
+                    &cast_options,
+                )?);
+                fields.push(Arc::new(
+                    field
+                        .as_ref()
+                        .clone()
+                        .with_data_type(output_field.data_type().clone()),
+                ));
+                casted = true;
+            }
+            _ => {
+                columns.push(column.clone());
+                fields.push(field.clone());
+            }
+        }
+    }
+    if !casted {
+        return Ok(batch);
+    }
+    let new_schema = Arc::new(Schema::new_with_metadata(fields, schema.metadata().clone()));
+    RecordBatch::try_new(new_schema, columns)
+        .map_err(|e| datafusion::error::DataFusionError::ArrowError(e, None))
+}
+
 /// Adds a sequence number column to a record batch
 fn add_seq_num_into_batch(batch: RecordBatch, seq_num: i64) -> DFResult<RecordBatch> {
     let schema = batch.schema();
@@ -461,6 +517,78 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_cast_to_output_types_normalizes_legacy_timestamps() {
+        use datafusion::arrow::array::{
+            Array, TimestampMicrosecondArray, TimestampNanosecondArray,
+        };
+        use datafusion::arrow::datatypes::{Schema as ArrowSchema, TimeUnit};
+
+        let file_schema = Arc::new(ArrowSchema::new(vec![
+            Field::new("id", ArrowDataType::Int64, false),
+            Field::new(
+                "ts",
+                ArrowDataType::Timestamp(TimeUnit::Nanosecond, None),
+                true,
+            ),
+        ]));
+        let output_schema = Arc::new(ArrowSchema::new(vec![
+            Field::new("id", ArrowDataType::Int64, false),
+            Field::new(
+                "ts",
+                ArrowDataType::Timestamp(TimeUnit::Microsecond, None),
+                true,
+            ),
+        ]));
+        let batch = RecordBatch::try_new(
+            file_schema,
+            vec![
+                Arc::new(Int64Array::from(vec![1, 2])),
+                Arc::new(TimestampNanosecondArray::from(vec![Some(1_000_000), None])),
+            ],
+        )
+        .unwrap();
+
+        let batch = cast_to_output_types(batch, &output_schema).unwrap();
+
+        assert_eq!(
+            batch.schema().field(1).data_type(),
+            &ArrowDataType::Timestamp(TimeUnit::Microsecond, None)
+        );
+        let ts = batch
+            .column(1)
+            .as_any()
+            .downcast_ref::<TimestampMicrosecondArray>()
+            .unwrap();
+        assert_eq!(ts.value(0), 1_000);
+        assert!(ts.is_null(1));
+    }
+
+    #[test]
+    fn test_cast_to_output_types_keeps_other_mismatches() {
+        use datafusion::arrow::datatypes::Schema as ArrowSchema;
+
+        let file_schema = Arc::new(ArrowSchema::new(vec![Field::new(
+            "id",
+            ArrowDataType::Utf8,
+            false,
+        )]));
+        let output_schema = Arc::new(ArrowSchema::new(vec![Field::new(
+            "id",
+            ArrowDataType::Int32,
+            false,
+        )]));
+        let batch = RecordBatch::try_new(
+            file_schema,
+            vec![Arc::new(StringArray::from(vec!["1", "not a number"]))],
+        )
+        .unwrap();
+
+        // A real type mismatch must not be cast to nulls.
+        let batch = cast_to_output_types(batch, &output_schema).unwrap();
+        assert_eq!(batch.schema().field(0).data_type(), &ArrowDataType::Utf8);
+    }
+
     #[test]
     fn test_split_n_vecs_basic() {
         let file_scan_tasks = (1..=12)
@@ -14,34 +14,32 @@
  * limitations under the License.
  */
 
-use crate::{error::Result, executor::iceberg_writer::rolling_iceberg_writer};
-use ::datafusion::parquet::file::properties::WriterProperties;
+use crate::{error::Result, executor::iceberg_writer::build_iceberg_writer};
 use async_trait::async_trait;
 use datafusion_processor::{DataFusionTaskContext, DatafusionProcessor};
 use futures::{future::try_join_all, StreamExt};
-use iceberg::{
-    io::FileIO,
-    spec::{DataFile, PartitionSpec, Schema},
-    writer::{
-        base_writer::data_file_writer::DataFileWriterBuilder,
-        file_writer::{
-            location_generator::{DefaultFileNameGenerator, DefaultLocationGenerator},
-            ParquetWriterBuilder,
-        },
-        function_writer::fanout_partition_writer::FanoutPartitionWriterBuilder,
-        IcebergWriter, IcebergWriterBuilder,
-    },
-};
-use sqlx::types::Uuid;
+use iceberg::{spec::DataFile, writer::IcebergWriter};
 use std::sync::Arc;
 use tokio::task::JoinHandle;
 
-use crate::CompactionError;
+use crate::common::sizing::{estimated_live_bytes, expected_output_files};
+use crate::config::validation::record_batch_rows;
+use crate::{CompactionConfig, CompactionError};
 
 use super::{CompactionExecutor, RewriteFilesStat};
-pub mod datafusion_processor;
 use super::{RewriteFilesRequest, RewriteFilesResponse};
+
+// Building blocks of `DataFusionExecutor`, public for the other modules of this crate but not
+// part of its stable API.
+#[doc(hidden)]
+pub mod clustering_udf;
+#[doc(hidden)]
+pub mod datafusion_processor;
+#[doc(hidden)]
+pub mod delete_file_rewriter;
+#[doc(hidden)]
 pub mod file_scan_task_table_provider;
+#[doc(hidden)]
 pub mod iceberg_file_task_scan;
 
 #[derive(Default)]
@@ -57,14 +55,44 @@ impl CompactionExecutor for DataFusionExecutor {
             config,
             dir_path,
             partition_spec,
+            sort_order,
         } = request;
 
+        // Wide schemas are read in fewer rows per batch, to bound the memory of a batch.
+        let batch_rows = record_batch_rows(&schema, &config);
+        let config = if batch_rows < config.max_record_batch_rows {
+            tracing::info!(
+                "Reading {} rows per batch instead of {} for the wide schema {}",
+                batch_rows,
+                config.max_record_batch_rows,
+                schema.schema_id()
+            );
+            Arc::new(CompactionConfig {
+                max_record_batch_rows: batch_rows,
+                ..(*config).clone()
+            })
+        } else {
+            config
+        };
+
         let mut stat = RewriteFilesStat::default();
         let rewritten_files_count = input_file_scan_tasks.input_files_count();
+        // Each output partition has its own writer, so use no more partitions than output files
+        // are expected, to avoid leaving a small trailing file per writer. Deleted rows are not
+        // written, so they don't count towards the expected output.
+        let input_size = estimated_live_bytes(&input_file_scan_tasks.data_files);
+        let output_partitions = expected_output_files(input_size, config.target_file_size)
+            .min(config.target_partitions.max(1));
 
         let datafusion_task_ctx = DataFusionTaskContext::builder()?
             .with_schema(schema)
             .with_input_data_files(input_file_scan_tasks)
+            .with_output_partitions(output_partitions)
+            .with_sort_order(sort_order)
+            .with_clustering(
+                config.clustering_columns.clone(),
+                config.clustering_function,
+            )
             .build()?;
         let (batches, input_schema) = DatafusionProcessor::new(config.clone(), file_io.clone())
             .execute(datafusion_task_ctx)
@@ -81,7 +109,7 @@ impl CompactionExecutor for DataFusionExecutor {
             let future: JoinHandle<
                 std::result::Result<Vec<iceberg::spec::DataFile>, CompactionError>,
             > = tokio::spawn(async move {
-                let mut data_file_writer = Self::build_iceberg_writer(
+                let mut data_file_writer = build_iceberg_writer(
                     config.data_file_prefix.clone(),
                     dir_path,
                     schema,
@@ -121,52 +149,3 @@ impl CompactionExecutor for DataFusionExecutor {
         })
     }
 }
-
-impl DataFusionExecutor {
-    async fn build_iceberg_writer(
-        data_file_prefix: String,
-        dir_path: String,
-        schema: Arc<Schema>,
-        file_io: FileIO,
-        partition_spec: Arc<PartitionSpec>,
-        target_file_size: u64,
-        write_parquet_properties: WriterProperties,
-    ) -> Result<Box<dyn IcebergWriter>> {
-        let location_generator = DefaultLocationGenerator { dir_path };
-        let unique_uuid_suffix = Uuid::now_v7();
-        let file_name_generator = DefaultFileNameGenerator::new(
-            data_file_prefix,
-            Some(unique_uuid_suffix.to_string()),
-            iceberg::spec::DataFileFormat::Parquet,
-        );
-
-        let parquet_writer_builder = ParquetWriterBuilder::new(
-            write_parquet_properties,
-            schema.clone(),
-            file_io,
-            location_generator,
-            file_name_generator,
-        );
-
-        let data_file_builder =
-            DataFileWriterBuilder::new(parquet_writer_builder, None, partition_spec.spec_id());
-        let data_file_size_writer = rolling_iceberg_writer::RollingIcebergWriterBuilder::new(
-            data_file_builder,
-            target_file_size,
-        );
-        let iceberg_output_writer = if partition_spec.fields().is_empty() {
-            Box::new(data_file_size_writer.build().await?) as Box<dyn IcebergWriter>
-        } else {
-            Box::new(
-                FanoutPartitionWriterBuilder::new(
-                    data_file_size_writer,
-                    partition_spec.clone(),
-                    schema,
-                )?
-                .build()
-                .await?,
-            ) as Box<dyn IcebergWriter>
-        };
-        Ok(iceberg_output_writer)
-    }
-}
@@ -0,0 +1,253 @@
+/*
+ * Copyright 2025 iceberg-compaction
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use datafusion::arrow::array::RecordBatch;
+use datafusion::arrow::compute::concat_batches;
+use datafusion::arrow::datatypes::SchemaRef as ArrowSchemaRef;
+use datafusion::error::DataFusionError;
+use futures::{Stream, StreamExt};
+use iceberg::arrow::schema_to_arrow_schema;
+use iceberg::spec::DataFile;
+use iceberg::table::Table;
+use iceberg::writer::file_writer::location_generator::DefaultLocationGenerator;
+use iceberg::writer::IcebergWriter;
+use std::sync::Arc;
+
+use super::build_iceberg_writer;
+use crate::error::Result;
+use crate::CompactionConfig;
+
+/// Buffers small record batches, e.g. from a CDC consumer, and writes them into right-sized data
+/// files of a table using the same writer stack as compaction.
+///
+/// Batches must have the columns of the table's current schema, in order and with the same types.
+/// Their field names and metadata are replaced by the table's, so batches built without field
+/// ids are accepted. The returned data files are not committed; callers add them to the table
+/// themselves, e.g. with a fast append.
+pub struct BufferedIcebergWriter {
+    writer: Box<dyn IcebergWriter>,
+    arrow_schema: ArrowSchemaRef,
+    buffer: Vec<RecordBatch>,
+    buffered_rows: usize,
+    /// Buffered rows are written as a single batch once they reach this count.
+    flush_rows: usize,
+}
+
+impl BufferedIcebergWriter {
+    /// Creates a writer for `table`, honoring the file prefix, target file size, parquet
+    /// properties and `max_record_batch_rows` of `config`.
+    pub async fn try_new(table: &Table, config: &CompactionConfig) -> Result<Self> {
+        let metadata = table.metadata();
+        let schema = metadata.current_schema().clone();
+        let arrow_schema = Arc::new(schema_to_arrow_schema(&schema)?);
+        let location_generator = DefaultLocationGenerator::new(metadata.clone())?;
+        let writer = build_iceberg_writer(
+            config.data_file_prefix.clone(),
+            location_generator.dir_path,
+            schema,
+            table.file_io().clone(),
+            metadata.default_partition_spec().clone(),
+            config.target_file_size,
+            config.write_parquet_properties.clone(),
+        )
+        .await?;
+
+        Ok(Self {
+            writer,
+            arrow_schema,
+            buffer: vec![],
+            buffered_rows: 0,
+            flush_rows: config.max_record_batch_rows.max(1),
+        })
+    }
+
+    pub async fn write(&mut self, batch: RecordBatch) -> Result<()> {
+        let batch = if batch.schema() == self.arrow_schema {
+            batch
+        } else {
+            RecordBatch::try_new(self.arrow_schema.clone(), batch.columns().to_vec())
+                .map_err(DataFusionError::from)?
+        };
+        self.buffered_rows += batch.num_rows();
+        self.buffer.push(batch);
+        if self.buffered_rows >= self.flush_rows {
+            self.flush().await?;
+        }
+        Ok(())
+    }
+
+    /// Hands the buffered rows to the underlying writer. Files are still only closed once they
+    /// reach the target size or the writer is closed.
+    pub async fn flush(&mut self) -> Result<()> {
+        if self.buffer.is_empty() {
+            return Ok(());
+        }
+        let batch =
+            concat_batches(&self.arrow_schema, &self.buffer).map_err(DataFusionError::from)?;
+        self.buffer.clear();
+        self.buffered_rows = 0;
+        self.writer.write(batch).await?;
+        Ok(())
+    }
+
+    /// Flushes the remaining rows and returns every data file written.
+    pub async fn close(mut self) -> Result<Vec<DataFile>> {
+        self.flush().await?;
+        Ok(self.writer.close().await?)
+    }
+}
+
+/// Writes a whole stream of record batches into data files of `table`.
+pub async fn write_record_batches<S>(
+    table: &Table,
+    config: &CompactionConfig,
+    batches: S,
+) -> Result<Vec<DataFile>>
+where
+    S: Stream<Item = Result<RecordBatch>>,
+{
+    let mut writer = BufferedIcebergWriter::try_new(table, config).await?;
+    futures::pin_mut!(batches);
+    while let Some(batch) = batches.next().await {
+        writer.write(batch?).await?;
+    }
+    writer.close().await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::CompactionConfigBuilder;
+    use datafusion::arrow::array::{Int32Array, StringArray};
+    use datafusion::arrow::datatypes::{DataType, Field, Schema as ArrowSchema};
+    use iceberg::io::FileIOBuilder;
+    use iceberg::spec::{NestedField, PrimitiveType, Schema, Type};
+    use iceberg::{Catalog, NamespaceIdent, TableCreation, TableIdent};
+    use iceberg_catalog_memory::MemoryCatalog;
+    use std::collections::HashMap;
+    use tempfile::TempDir;
+
+    fn record_batch(arrow_schema: ArrowSchemaRef, ids: Vec<i32>) -> RecordBatch {
+        let names: Vec<String> = ids.iter().map(|id| format!("name-{}", id)).collect();
+        RecordBatch::try_new(
+            arrow_schema,
+            vec![
+                Arc::new(Int32Array::from(ids)),
+                Arc::new(StringArray::from(names)),
+            ],
+        )
+        .unwrap()
+    }
+
+    async fn create_table(temp_dir: &TempDir) -> (Table, Schema) {
+        let warehouse_location = temp_dir.path().to_str().unwrap().to_string();
+        let file_io = FileIOBuilder::new_fs_io().build().unwrap();
+        let catalog = MemoryCatalog::new(file_io, Some(warehouse_location));
+
+        let namespace_ident = NamespaceIdent::new("test_namespace".into());
+        catalog
+            .create_namespace(&namespace_ident, HashMap::new())
+            .await
+            .unwrap();
+        let table_ident = TableIdent::new(namespace_ident, "test_table".into());
+        let schema = Schema::builder()
+            .with_fields(vec![
+                NestedField::required(1, "id", Type::Primitive(PrimitiveType::Int)).into(),
+                NestedField::required(2, "name", Type::Primitive(PrimitiveType::String)).into(),
+            ])
+            .build()
+            .unwrap();
+        let table = catalog
+            .create_table(
+                &table_ident.namespace,
+                TableCreation::builder()
+                    .name(table_ident.name().into())
+                    .schema(schema.clone())
+                    .build(),
+            )
+            .await
+            .unwrap();
+        (table, schema)
+    }
+
+    #[tokio::test]
+    async fn test_small_batches_are_written_to_one_file() {
+        let temp_dir = TempDir::new().unwrap();
+        let (table, schema) = create_table(&temp_dir).await;
+
+        let config = CompactionConfigBuilder::default()
+            .max_record_batch_rows(4)
+            .build()
+            .unwrap();
+        let arrow_schema = Arc::new(schema_to_arrow_schema(&schema).unwrap());
+        let batches = futures::stream::iter(
+            (0..5).map(|i| Ok(record_batch(arrow_schema.clone(), vec![i * 2, i * 2 + 1]))),
+        );
+
+        let data_files = write_record_batches(&table, &config, batches)
+            .await
+            .unwrap();
+
+        assert_eq!(data_files.len(), 1);
+        assert_eq!(data_files[0].record_count(), 10);
+    }
+
+    #[tokio::test]
+    async fn test_batches_without_field_ids() {
+        let temp_dir = TempDir::new().unwrap();
+        let (table, _) = create_table(&temp_dir).await;
+
+        // A plain arrow schema, as a CDC consumer builds it, carries no field id metadata.
+        let arrow_schema = Arc::new(ArrowSchema::new(vec![
+            Field::new("id", DataType::Int32, false),
+            Field::new("name", DataType::Utf8, false),
+        ]));
+        let config = CompactionConfigBuilder::default().build().unwrap();
+        let mut writer = BufferedIcebergWriter::try_new(&table, &config)
+            .await
+            .unwrap();
+        writer
+            .write(record_batch(arrow_schema.clone(), vec![1, 2]))
+            .await
+            .unwrap();
+        writer
+            .write(record_batch(arrow_schema, vec![3]))
+            .await
+            .unwrap();
+        let data_files = writer.close().await.unwrap();
+
+        assert_eq!(data_files.len(), 1);
+        assert_eq!(data_files[0].record_count(), 3);
+
+        // Columns of other types are refused.
+        let mismatched_schema = Arc::new(ArrowSchema::new(vec![
+            Field::new("id", DataType::Utf8, false),
+            Field::new("name", DataType::Utf8, false),
+        ]));
+        let mismatched_batch = RecordBatch::try_new(
+            mismatched_schema,
+            vec![
+                Arc::new(StringArray::from(vec!["1"])),
+                Arc::new(StringArray::from(vec!["name-1"])),
+            ],
+        )
+        .unwrap();
+        let mut writer = BufferedIcebergWriter::try_new(&table, &config)
+            .await
+            .unwrap();
+        assert!(writer.write(mismatched_batch).await.is_err());
+    }
+}
@@ -14,4 +14,70 @@
  * limitations under the License.
  */
 
+pub mod buffered_iceberg_writer;
 pub mod rolling_iceberg_writer;
+
+use std::sync::Arc;
+
+use iceberg::io::FileIO;
+use iceberg::spec::{PartitionSpec, Schema};
+use iceberg::writer::base_writer::data_file_writer::DataFileWriterBuilder;
+use iceberg::writer::file_writer::location_generator::{
+    DefaultFileNameGenerator, DefaultLocationGenerator,
+};
+use iceberg::writer::file_writer::ParquetWriterBuilder;
+use iceberg::writer::function_writer::fanout_partition_writer::FanoutPartitionWriterBuilder;
+use iceberg::writer::{IcebergWriter, IcebergWriterBuilder};
+use parquet::file::properties::WriterProperties;
+use sqlx::types::Uuid;
+
+use crate::error::Result;
+
+/// Builds the writer stack used for compaction output: parquet data files rolled over at
+/// `target_file_size`, fanned out per partition when the spec is partitioned.
+pub async fn build_iceberg_writer(
+    data_file_prefix: String,
+    dir_path: String,
+    schema: Arc<Schema>,
+    file_io: FileIO,
+    partition_spec: Arc<PartitionSpec>,
+    target_file_size: u64,
+    write_parquet_properties: WriterProperties,
+) -> Result<Box<dyn IcebergWriter>> {
+    let location_generator = DefaultLocationGenerator { dir_path };
+    let unique_uuid_suffix = Uuid::now_v7();
+    let file_name_generator = DefaultFileNameGenerator::new(
+        data_file_prefix,
+        Some(unique_uuid_suffix.to_string()),
+        iceberg::spec::DataFileFormat::Parquet,
+    );
+
+    let parquet_writer_builder = ParquetWriterBuilder::new(
+        write_parquet_properties,
+        schema.clone(),
+        file_io,
+        location_generator,
+        file_name_generator,
+    );
+
+    let data_file_builder =
+        DataFileWriterBuilder::new(parquet_writer_builder, None, partition_spec.spec_id());
+    let data_file_size_writer = rolling_iceberg_writer::RollingIcebergWriterBuilder::new(
+        data_file_builder,
+        target_file_size,
+    );
+    let iceberg_output_writer = if partition_spec.fields().is_empty() {
+        Box::new(data_file_size_writer.build().await?) as Box<dyn IcebergWriter>
+    } else {
+        Box::new(
+            FanoutPartitionWriterBuilder::new(
+                data_file_size_writer,
+                partition_spec.clone(),
+                schema,
+            )?
+            .build()
+            .await?,
+        ) as Box<dyn IcebergWriter>
+    };
+    Ok(iceberg_output_writer)
+}
@@ -20,15 +20,21 @@ use async_trait::async_trait;
 use iceberg::scan::FileScanTask;
 use iceberg::{io::FileIO, spec::PartitionSpec};
 
+use crate::compaction::SkipReason;
+use crate::config::validation::{check_config, check_schema_width};
 use crate::config::CompactionConfig;
-use iceberg::spec::{DataFile, Schema};
+use crate::error::CompactionError;
+use iceberg::spec::{DataFile, Schema, SortOrderRef};
 
 pub mod mock;
 pub use mock::MockExecutor;
 pub mod datafusion;
+// Output writers of the executors, not part of the stable API besides the re-exports below.
+#[doc(hidden)]
 pub mod iceberg_writer;
 use crate::error::Result;
 pub use datafusion::DataFusionExecutor;
+pub use iceberg_writer::buffered_iceberg_writer::{write_record_batches, BufferedIcebergWriter};
 
 #[async_trait]
 pub trait CompactionExecutor: Send + Sync + 'static {
@@ -42,6 +48,137 @@ pub struct RewriteFilesRequest {
     pub config: Arc<CompactionConfig>,
     pub dir_path: String,
     pub partition_spec: Arc<PartitionSpec>,
+    /// Sort order to cluster output rows by, if any.
+    pub sort_order: Option<SortOrderRef>,
+}
+
+impl RewriteFilesRequest {
+    pub fn builder() -> RewriteFilesRequestBuilder {
+        RewriteFilesRequestBuilder::default()
+    }
+}
+
+/// Builds a `RewriteFilesRequest`, validating it up front so that mistakes surface as
+/// `CompactionError::InvalidRequest` or `CompactionError::Config` instead of failing inside
+/// the executor.
+#[derive(Default)]
+pub struct RewriteFilesRequestBuilder {
+    file_io: Option<FileIO>,
+    schema: Option<Arc<Schema>>,
+    input_file_scan_tasks: Option<InputFileScanTasks>,
+    config: Option<Arc<CompactionConfig>>,
+    dir_path: Option<String>,
+    partition_spec: Option<Arc<PartitionSpec>>,
+    sort_order: Option<SortOrderRef>,
+}
+
+impl RewriteFilesRequestBuilder {
+    pub fn with_file_io(mut self, file_io: FileIO) -> Self {
+        self.file_io = Some(file_io);
+        self
+    }
+
+    pub fn with_schema(mut self, schema: Arc<Schema>) -> Self {
+        self.schema = Some(schema);
+        self
+    }
+
+    pub fn with_input_file_scan_tasks(mut self, input_file_scan_tasks: InputFileScanTasks) -> Self {
+        self.input_file_scan_tasks = Some(input_file_scan_tasks);
+        self
+    }
+
+    pub fn with_config(mut self, config: Arc<CompactionConfig>) -> Self {
+        self.config = Some(config);
+        self
+    }
+
+    pub fn with_dir_path(mut self, dir_path: String) -> Self {
+        self.dir_path = Some(dir_path);
+        self
+    }
+
+    pub fn with_partition_spec(mut self, partition_spec: Arc<PartitionSpec>) -> Self {
+        self.partition_spec = Some(partition_spec);
+        self
+    }
+
+    pub fn with_sort_order(mut self, sort_order: Option<SortOrderRef>) -> Self {
+        self.sort_order = sort_order;
+        self
+    }
+
+    /// Checks that every field is set, the config is sane, there is at least one data file, and
+    /// the tasks, partition spec and sort order only refer to fields of the schema.
+    pub fn build(self) -> Result<RewriteFilesRequest> {
+        let file_io = self.file_io.ok_or_else(|| missing_field("file_io"))?;
+        let schema = self.schema.ok_or_else(|| missing_field("schema"))?;
+        let input_file_scan_tasks = self
+            .input_file_scan_tasks
+            .ok_or_else(|| missing_field("input_file_scan_tasks"))?;
+        let config = self.config.ok_or_else(|| missing_field("config"))?;
+        let dir_path = self.dir_path.ok_or_else(|| missing_field("dir_path"))?;
+        let partition_spec = self
+            .partition_spec
+            .ok_or_else(|| missing_field("partition_spec"))?;
+
+        check_config(&config)?;
+        check_schema_width(&schema, &config)?;
+        if dir_path.is_empty() {
+            return Err(CompactionError::InvalidRequest(
+                "dir_path must not be empty".to_owned(),
+            ));
+        }
+        if input_file_scan_tasks.data_files.is_empty() {
+            return Err(CompactionError::InvalidRequest(
+                "there are no data files to rewrite".to_owned(),
+            ));
+        }
+
+        let check_field_id = |field_id: i32, used_by: &str| {
+            if schema.field_by_id(field_id).is_none() {
+                return Err(CompactionError::InvalidRequest(format!(
+                    "field id {} of {} is not in schema {}",
+                    field_id,
+                    used_by,
+                    schema.schema_id()
+                )));
+            }
+            Ok(())
+        };
+        for task in input_file_scan_tasks
+            .data_files
+            .iter()
+            .chain(&input_file_scan_tasks.position_delete_files)
+            .chain(&input_file_scan_tasks.equality_delete_files)
+        {
+            for &field_id in task.project_field_ids.iter().chain(&task.equality_ids) {
+                check_field_id(field_id, &format!("file '{}'", task.data_file_path))?;
+            }
+        }
+        for field in partition_spec.fields() {
+            check_field_id(field.source_id, "the partition spec")?;
+        }
+        if let Some(sort_order) = &self.sort_order {
+            for field in &sort_order.fields {
+                check_field_id(field.source_id, "the sort order")?;
+            }
+        }
+
+        Ok(RewriteFilesRequest {
+            file_io,
+            schema,
+            input_file_scan_tasks,
+            config,
+            dir_path,
+            partition_spec,
+            sort_order: self.sort_order,
+        })
+    }
+}
+
+fn missing_field(name: &str) -> CompactionError {
+    CompactionError::InvalidRequest(format!("{} is required", name))
 }
 
 #[derive(Debug, Clone)]
@@ -72,6 +209,17 @@ pub struct RewriteFilesStat {
     pub added_files_count: u32,
     pub rewritten_bytes: u64,
     pub failed_data_files_count: u32,
+    /// Set when nothing was rewritten because it would have achieved nothing.
+    pub skipped: Option<SkipReason>,
+}
+
+impl RewriteFilesStat {
+    pub fn skipped(reason: SkipReason) -> Self {
+        Self {
+            skipped: Some(reason),
+            ..Default::default()
+        }
+    }
 }
 
 pub enum ExecutorType {
@@ -85,3 +233,41 @@ pub fn create_compaction_executor(executor_type: ExecutorType) -> Box<dyn Compac
         ExecutorType::Mock => Box::new(MockExecutor),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::CompactionConfigBuilder;
+    use iceberg::io::FileIOBuilder;
+
+    #[test]
+    fn test_rewrite_files_request_builder_validates() {
+        let schema = Arc::new(Schema::builder().build().unwrap());
+        let partition_spec = Arc::new(PartitionSpec::builder(schema.clone()).build().unwrap());
+        let builder = || {
+            RewriteFilesRequest::builder()
+                .with_file_io(FileIOBuilder::new_fs_io().build().unwrap())
+                .with_schema(schema.clone())
+                .with_config(Arc::new(
+                    CompactionConfigBuilder::default().build().unwrap(),
+                ))
+                .with_dir_path("/tmp/table/data".to_owned())
+                .with_partition_spec(partition_spec.clone())
+        };
+
+        assert!(matches!(
+            builder().build(),
+            Err(CompactionError::InvalidRequest(_))
+        ));
+        assert!(matches!(
+            builder()
+                .with_input_file_scan_tasks(InputFileScanTasks {
+                    data_files: vec![],
+                    position_delete_files: vec![],
+                    equality_delete_files: vec![],
+                })
+                .build(),
+            Err(CompactionError::InvalidRequest(_))
+        ));
+    }
+}
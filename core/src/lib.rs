@@ -14,14 +14,26 @@
  * limitations under the License.
  */
 
+//! Compaction and maintenance of Iceberg tables.
+//!
+//! The stable API is `compaction::CompactionBuilder` and the `Compaction` it builds, the
+//! `config`, the reports they return, the `CompactionExecutor` and `CommitHook` traits, and the
+//! `executor::BufferedIcebergWriter` for writing data files outside of compaction.
+//! Items hidden from the docs are internals of the executors and may change in any release;
+//! `tests/api_stability.rs` uses the stable API from outside the crate.
+
 #![feature(proc_macro_hygiene, stmt_expr_attributes)]
 #![feature(coroutines)]
 
+pub mod clustering;
 pub mod common;
 pub mod compaction;
 pub mod config;
 pub mod error;
 pub mod executor;
+pub mod maintenance;
+#[cfg(test)]
+mod test_utils;
 
 pub use config::CompactionConfig;
 pub use error::{CompactionError, Result};
@@ -0,0 +1,107 @@
+/*
+ * Copyright 2025 iceberg-compaction
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::collections::HashMap;
+
+use futures::{StreamExt, TryStreamExt};
+use iceberg::spec::{DataContentType, DataFile, Struct};
+use iceberg::table::Table;
+
+use crate::error::Result;
+
+/// Delete files of the current snapshot that can't apply to any live data file.
+#[derive(Debug, Clone, Default)]
+pub struct DanglingDeleteFiles {
+    pub snapshot_id: Option<i64>,
+    pub files: Vec<DataFile>,
+}
+
+impl DanglingDeleteFiles {
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+}
+
+/// Finds the delete files whose sequence number is too low for any live data file of their
+/// partition, loading at most `parallelism` manifests concurrently.
+///
+/// A position delete applies to data files with a lower or equal data sequence number, an
+/// equality delete only to lower ones. Deletes of an unpartitioned spec are compared with every
+/// live data file of the table.
+pub async fn find_dangling_delete_files(
+    table: &Table,
+    parallelism: usize,
+) -> Result<DanglingDeleteFiles> {
+    let Some(snapshot) = table.metadata().current_snapshot() else {
+        return Ok(DanglingDeleteFiles::default());
+    };
+    let file_io = table.file_io();
+    let manifest_list = snapshot
+        .load_manifest_list(file_io, table.metadata())
+        .await?;
+    let mut manifests = futures::stream::iter(manifest_list.entries().iter().cloned())
+        .map(|manifest_file| async move {
+            let spec_id = manifest_file.partition_spec_id;
+            Ok::<_, iceberg::Error>((spec_id, manifest_file.load_manifest(file_io).await?))
+        })
+        .buffer_unordered(parallelism.max(1));
+
+    // Entries without a sequence number count as the oldest, so they keep every delete.
+    let mut min_data_sequence_numbers: HashMap<(i32, Struct), i64> = HashMap::new();
+    let mut min_data_sequence_number = None;
+    let mut delete_entries = vec![];
+    while let Some((spec_id, manifest)) = manifests.try_next().await? {
+        for entry in manifest.entries().iter().filter(|entry| entry.is_alive()) {
+            let data_file = entry.data_file();
+            if entry.content_type() == DataContentType::Data {
+                let sequence_number = entry.sequence_number().unwrap_or(0);
+                min_data_sequence_numbers
+                    .entry((spec_id, data_file.partition().clone()))
+                    .and_modify(|min| *min = (*min).min(sequence_number))
+                    .or_insert(sequence_number);
+                let min = min_data_sequence_number.get_or_insert(sequence_number);
+                *min = (*min).min(sequence_number);
+            } else if let Some(sequence_number) = entry.sequence_number() {
+                delete_entries.push((spec_id, sequence_number, data_file.clone()));
+            }
+        }
+    }
+
+    let files = delete_entries
+        .into_iter()
+        .filter(|(spec_id, sequence_number, delete_file)| {
+            let partition = delete_file.partition();
+            let min_data_sequence_number = if partition.iter().next().is_none() {
+                min_data_sequence_number
+            } else {
+                min_data_sequence_numbers
+                    .get(&(*spec_id, partition.clone()))
+                    .copied()
+            };
+            match (delete_file.content_type(), min_data_sequence_number) {
+                (_, None) => true,
+                (DataContentType::EqualityDeletes, Some(min)) => min >= *sequence_number,
+                (_, Some(min)) => min > *sequence_number,
+            }
+        })
+        .map(|(_, _, delete_file)| delete_file)
+        .collect();
+
+    Ok(DanglingDeleteFiles {
+        snapshot_id: Some(snapshot.snapshot_id()),
+        files,
+    })
+}
@@ -0,0 +1,135 @@
+/*
+ * Copyright 2025 iceberg-compaction
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::collections::{BTreeMap, HashMap};
+
+use futures::{StreamExt, TryStreamExt};
+use iceberg::spec::{DataContentType, DataFile, ManifestContentType};
+use iceberg::table::Table;
+use iceberg::transaction::Transaction;
+
+use crate::error::Result;
+
+/// A data file referenced by more than one live manifest entry of the current snapshot.
+#[derive(Debug, Clone)]
+pub struct DuplicateDataFile {
+    pub data_file: DataFile,
+    /// Number of live entries referencing the file.
+    pub occurrences: usize,
+    /// Lowest data sequence number among the entries, kept when repairing.
+    pub sequence_number: Option<i64>,
+}
+
+/// Report of duplicated data files found in a snapshot.
+#[derive(Debug, Clone, Default)]
+pub struct DuplicateDataFiles {
+    pub snapshot_id: Option<i64>,
+    pub files: Vec<DuplicateDataFile>,
+    /// Sequence number groups committed by a repair, see `files_by_sequence_number`.
+    pub repaired_sequence_numbers: Vec<Option<i64>>,
+}
+
+impl DuplicateDataFiles {
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    /// Groups the duplicated files by the sequence number their repaired entry must keep.
+    pub fn files_by_sequence_number(&self) -> BTreeMap<Option<i64>, Vec<DataFile>> {
+        let mut files_by_sequence_number: BTreeMap<Option<i64>, Vec<DataFile>> = BTreeMap::new();
+        for duplicate in &self.files {
+            files_by_sequence_number
+                .entry(duplicate.sequence_number)
+                .or_default()
+                .push(duplicate.data_file.clone());
+        }
+        files_by_sequence_number
+    }
+}
+
+/// Finds data files referenced by several live entries of the current snapshot, loading at most
+/// `parallelism` manifests concurrently.
+pub async fn find_duplicate_data_files(
+    table: &Table,
+    parallelism: usize,
+) -> Result<DuplicateDataFiles> {
+    let Some(snapshot) = table.metadata().current_snapshot() else {
+        return Ok(DuplicateDataFiles::default());
+    };
+    let file_io = table.file_io();
+    let manifest_list = snapshot
+        .load_manifest_list(file_io, table.metadata())
+        .await?;
+
+    let data_manifests = manifest_list
+        .entries()
+        .iter()
+        .filter(|m| m.content == ManifestContentType::Data)
+        .cloned();
+    let mut manifests = futures::stream::iter(data_manifests)
+        .map(|manifest_file| async move { manifest_file.load_manifest(file_io).await })
+        .buffer_unordered(parallelism.max(1));
+
+    let mut entries_by_path: HashMap<String, DuplicateDataFile> = HashMap::new();
+    while let Some(manifest) = manifests.try_next().await? {
+        for entry in manifest.entries() {
+            if !entry.is_alive() || entry.content_type() != DataContentType::Data {
+                continue;
+            }
+            entries_by_path
+                .entry(entry.file_path().to_owned())
+                .and_modify(|duplicate| {
+                    duplicate.occurrences += 1;
+                    duplicate.sequence_number =
+                        match (duplicate.sequence_number, entry.sequence_number()) {
+                            (Some(a), Some(b)) => Some(a.min(b)),
+                            (a, b) => a.or(b),
+                        };
+                })
+                .or_insert_with(|| DuplicateDataFile {
+                    data_file: entry.data_file().clone(),
+                    occurrences: 1,
+                    sequence_number: entry.sequence_number(),
+                });
+        }
+    }
+
+    Ok(DuplicateDataFiles {
+        snapshot_id: Some(snapshot.snapshot_id()),
+        files: entries_by_path
+            .into_values()
+            .filter(|duplicate| duplicate.occurrences > 1)
+            .collect(),
+        repaired_sequence_numbers: vec![],
+    })
+}
+
+/// Builds a rewrite transaction that removes every entry of `data_files` and adds a single entry
+/// back for each, stamped with `sequence_number` so existing deletes keep applying to it.
+pub async fn build_duplicate_repair_transaction(
+    table: &Table,
+    data_files: Vec<DataFile>,
+    sequence_number: Option<i64>,
+) -> Result<Transaction<'_>> {
+    let mut rewrite_action = Transaction::new(table)
+        .rewrite_files(None, vec![])?
+        .add_data_files(data_files.clone())?
+        .delete_files(data_files)?;
+    if let Some(sequence_number) = sequence_number {
+        rewrite_action = rewrite_action.new_data_file_sequence_number(sequence_number)?;
+    }
+    Ok(rewrite_action.apply().await?)
+}
@@ -0,0 +1,178 @@
+/*
+ * Copyright 2025 iceberg-compaction
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::collections::HashSet;
+use std::time::Duration;
+
+use futures::{StreamExt, TryStreamExt};
+use iceberg::io::FileIO;
+use iceberg::spec::{ManifestFile, SnapshotRef, TableMetadata};
+
+use crate::error::{CompactionError, Result};
+
+/// Which snapshots `Compaction::expire_snapshot` expires. Snapshots referenced by a branch or tag
+/// are kept. The same settings apply to every ref.
+#[derive(Debug, Clone, Default)]
+pub struct ExpireSnapshotsOptions {
+    /// Expires snapshots created more than this long ago.
+    pub older_than: Option<Duration>,
+    /// Keeps at least this many of the most recent snapshots of each branch, however old.
+    pub retain_last: Option<usize>,
+}
+
+/// Files that are no longer reachable from any retained snapshot after snapshot expiration.
+#[derive(Debug, Clone, Default)]
+pub struct ExpiredFiles {
+    pub expired_snapshot_ids: Vec<i64>,
+    pub manifest_lists: Vec<String>,
+    pub manifests: Vec<String>,
+    pub content_files: Vec<String>,
+}
+
+impl ExpiredFiles {
+    pub fn files_count(&self) -> usize {
+        self.manifest_lists.len() + self.manifests.len() + self.content_files.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files_count() == 0
+    }
+}
+
+/// Computes which files become unreferenced when the table goes from `before` to `after`.
+///
+/// Snapshots present in `before` but missing from `after` are treated as expired. Manifest lists
+/// and manifests are loaded with at most `parallelism` concurrent reads. Only the candidate
+/// files reachable from expired snapshots are held in memory; manifests of retained snapshots
+/// are streamed and used to strike candidates that are still referenced.
+pub async fn collect_expired_files(
+    file_io: &FileIO,
+    before: &TableMetadata,
+    after: &TableMetadata,
+    parallelism: usize,
+) -> Result<ExpiredFiles> {
+    let parallelism = parallelism.max(1);
+    let retained_snapshot_ids: HashSet<i64> =
+        after.snapshots().map(|s| s.snapshot_id()).collect();
+    let expired_snapshots: Vec<SnapshotRef> = before
+        .snapshots()
+        .filter(|s| !retained_snapshot_ids.contains(&s.snapshot_id()))
+        .cloned()
+        .collect();
+    if expired_snapshots.is_empty() {
+        return Ok(ExpiredFiles::default());
+    }
+    let retained_snapshots: Vec<SnapshotRef> = after.snapshots().cloned().collect();
+
+    let retained_manifests = load_manifest_files(file_io, after, retained_snapshots, parallelism)
+        .await?;
+    let expired_manifests =
+        load_manifest_files(file_io, before, expired_snapshots.clone(), parallelism).await?;
+
+    let retained_manifest_paths: HashSet<&str> = retained_manifests
+        .iter()
+        .map(|m| m.manifest_path.as_str())
+        .collect();
+    let mut seen_manifest_paths = HashSet::new();
+    let expired_only_manifests: Vec<ManifestFile> = expired_manifests
+        .into_iter()
+        .filter(|m| !retained_manifest_paths.contains(m.manifest_path.as_str()))
+        .filter(|m| seen_manifest_paths.insert(m.manifest_path.clone()))
+        .collect();
+
+    // Every file an expired-only manifest mentions is a candidate, including deleted entries,
+    // since those are the files removed by the expired snapshots.
+    let mut candidate_files = futures::stream::iter(expired_only_manifests.iter().cloned())
+        .map(|manifest_file| load_manifest_paths(file_io.clone(), manifest_file, false))
+        .buffer_unordered(parallelism)
+        .try_fold(HashSet::new(), |mut candidates, paths| async move {
+            candidates.extend(paths);
+            Ok(candidates)
+        })
+        .await?;
+
+    if !candidate_files.is_empty() {
+        let mut seen_manifest_paths = HashSet::new();
+        let mut retained_paths = futures::stream::iter(
+            retained_manifests
+                .into_iter()
+                .filter(|m| seen_manifest_paths.insert(m.manifest_path.clone())),
+        )
+        .map(|manifest_file| load_manifest_paths(file_io.clone(), manifest_file, true))
+        .buffer_unordered(parallelism);
+
+        while let Some(paths) = retained_paths.try_next().await? {
+            for path in paths {
+                candidate_files.remove(&path);
+            }
+            if candidate_files.is_empty() {
+                break;
+            }
+        }
+    }
+
+    let retained_manifest_lists: HashSet<&str> =
+        after.snapshots().map(|s| s.manifest_list()).collect();
+    let manifest_lists = expired_snapshots
+        .iter()
+        .map(|s| s.manifest_list())
+        .filter(|path| !retained_manifest_lists.contains(path))
+        .map(|path| path.to_owned())
+        .collect();
+
+    Ok(ExpiredFiles {
+        expired_snapshot_ids: expired_snapshots.iter().map(|s| s.snapshot_id()).collect(),
+        manifest_lists,
+        manifests: expired_only_manifests
+            .into_iter()
+            .map(|m| m.manifest_path)
+            .collect(),
+        content_files: candidate_files.into_iter().collect(),
+    })
+}
+
+async fn load_manifest_files(
+    file_io: &FileIO,
+    metadata: &TableMetadata,
+    snapshots: Vec<SnapshotRef>,
+    parallelism: usize,
+) -> Result<Vec<ManifestFile>> {
+    futures::stream::iter(snapshots)
+        .map(|snapshot| async move {
+            let manifest_list = snapshot.load_manifest_list(file_io, metadata).await?;
+            Ok::<_, CompactionError>(manifest_list.entries().to_vec())
+        })
+        .buffer_unordered(parallelism)
+        .try_fold(vec![], |mut manifests, entries| async move {
+            manifests.extend(entries);
+            Ok(manifests)
+        })
+        .await
+}
+
+async fn load_manifest_paths(
+    file_io: FileIO,
+    manifest_file: ManifestFile,
+    alive_only: bool,
+) -> Result<Vec<String>> {
+    let manifest = manifest_file.load_manifest(&file_io).await?;
+    Ok(manifest
+        .entries()
+        .iter()
+        .filter(|entry| !alive_only || entry.is_alive())
+        .map(|entry| entry.file_path().to_owned())
+        .collect())
+}
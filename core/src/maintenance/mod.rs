@@ -0,0 +1,28 @@
+/*
+ * Copyright 2025 iceberg-compaction
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+pub mod dangling_deletes;
+pub mod duplicate_files;
+pub mod expire_snapshots;
+pub mod table_maintenance;
+
+pub use dangling_deletes::{find_dangling_delete_files, DanglingDeleteFiles};
+pub use duplicate_files::{
+    build_duplicate_repair_transaction, find_duplicate_data_files, DuplicateDataFile,
+    DuplicateDataFiles,
+};
+pub use expire_snapshots::{collect_expired_files, ExpireSnapshotsOptions, ExpiredFiles};
+pub use table_maintenance::{MaintenancePolicy, MaintenanceReport, TableMaintenance};
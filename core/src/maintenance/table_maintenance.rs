@@ -0,0 +1,184 @@
+/*
+ * Copyright 2025 iceberg-compaction
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::sync::Arc;
+
+use futures::{StreamExt, TryStreamExt};
+use iceberg::{Catalog, TableIdent};
+
+use super::{DanglingDeleteFiles, DuplicateDataFiles, ExpireSnapshotsOptions, ExpiredFiles};
+use crate::compaction::analyzer::{TableAnalyzer, TableHealth};
+use crate::compaction::{Compaction, CompactionBuilder};
+use crate::error::Result;
+use crate::executor::RewriteFilesStat;
+use crate::CompactionConfig;
+
+/// Steps run by `TableMaintenance::run_all`, in the order of the fields.
+#[derive(Debug, Clone)]
+pub struct MaintenancePolicy {
+    pub repair_duplicate_data_files: bool,
+    pub remove_dangling_delete_files: bool,
+    pub compact: bool,
+    pub expire_snapshots: bool,
+    pub expire_snapshots_options: ExpireSnapshotsOptions,
+    /// Deletes the files that became unreferenced by snapshot expiration. Off by default, since
+    /// readers of the expired snapshots may still be running.
+    pub delete_expired_files: bool,
+}
+
+impl Default for MaintenancePolicy {
+    fn default() -> Self {
+        Self {
+            repair_duplicate_data_files: false,
+            remove_dangling_delete_files: false,
+            compact: true,
+            expire_snapshots: true,
+            expire_snapshots_options: ExpireSnapshotsOptions::default(),
+            delete_expired_files: false,
+        }
+    }
+}
+
+/// Outcome of `TableMaintenance::run_all`, with `None` for the steps that did not run.
+#[derive(Debug, Clone, Default)]
+pub struct MaintenanceReport {
+    pub duplicate_data_files: Option<DuplicateDataFiles>,
+    pub dangling_delete_files: Option<DanglingDeleteFiles>,
+    pub compaction: Option<RewriteFilesStat>,
+    pub expired_files: Option<ExpiredFiles>,
+    pub deleted_files_count: usize,
+}
+
+/// Runs the maintenance operations of a single table.
+pub struct TableMaintenance {
+    compaction: Compaction,
+}
+
+impl TableMaintenance {
+    /// Creates the maintenance of `table_ident` with a full compaction and default metrics.
+    pub async fn new(
+        catalog: Arc<dyn Catalog>,
+        table_ident: TableIdent,
+        config: impl Into<Arc<CompactionConfig>>,
+    ) -> Result<Self> {
+        let compaction = CompactionBuilder::new()
+            .with_catalog(catalog)
+            .with_table_ident(table_ident)
+            .with_config(config)
+            .build()
+            .await?;
+        Ok(Self::from_compaction(compaction))
+    }
+
+    /// Uses `compaction`, and its table, for every operation.
+    pub fn from_compaction(compaction: Compaction) -> Self {
+        Self { compaction }
+    }
+
+    pub fn table_ident(&self) -> &TableIdent {
+        &self.compaction.table_ident
+    }
+
+    /// Reports the file size and delete statistics of the current snapshot of the table, with
+    /// the small file threshold and manifest parallelism of the compaction config.
+    pub async fn analyze(&self) -> Result<TableHealth> {
+        let table = self
+            .compaction
+            .catalog
+            .load_table(self.table_ident())
+            .await?;
+        TableAnalyzer::new(&table, &self.compaction.config)
+            .analyze()
+            .await
+    }
+
+    pub async fn compact(&self) -> Result<RewriteFilesStat> {
+        self.compaction.compact().await
+    }
+
+    pub async fn expire_snapshots(&self, options: &ExpireSnapshotsOptions) -> Result<ExpiredFiles> {
+        self.compaction
+            .expire_snapshot(self.table_ident().clone(), options)
+            .await
+    }
+
+    pub async fn repair_duplicate_data_files(&self, dry_run: bool) -> Result<DuplicateDataFiles> {
+        self.compaction
+            .repair_duplicate_data_files(self.table_ident().clone(), dry_run)
+            .await
+    }
+
+    pub async fn remove_dangling_delete_files(&self, dry_run: bool) -> Result<DanglingDeleteFiles> {
+        self.compaction
+            .remove_dangling_delete_files(self.table_ident().clone(), dry_run)
+            .await
+    }
+
+    /// Deletes the content files, manifests and manifest lists of `expired_files`, returning
+    /// how many were deleted.
+    pub async fn delete_expired_files(&self, expired_files: &ExpiredFiles) -> Result<usize> {
+        let table = self
+            .compaction
+            .catalog
+            .load_table(self.table_ident())
+            .await?;
+        let file_io = table.file_io();
+        let paths = expired_files
+            .content_files
+            .iter()
+            .chain(&expired_files.manifests)
+            .chain(&expired_files.manifest_lists);
+        futures::stream::iter(paths)
+            .map(|path| file_io.delete(path))
+            .buffer_unordered(self.compaction.config.manifest_io_parallelism.max(1))
+            .try_collect::<Vec<()>>()
+            .await?;
+        Ok(expired_files.files_count())
+    }
+
+    /// Runs the steps enabled by `policy`, stopping at the first failure.
+    pub async fn run_all(&self, policy: &MaintenancePolicy) -> Result<MaintenanceReport> {
+        let mut report = MaintenanceReport::default();
+        if policy.repair_duplicate_data_files {
+            report.duplicate_data_files = Some(self.repair_duplicate_data_files(false).await?);
+        }
+        if policy.remove_dangling_delete_files {
+            report.dangling_delete_files = Some(self.remove_dangling_delete_files(false).await?);
+        }
+        if policy.compact {
+            report.compaction = Some(self.compact().await?);
+        }
+        if policy.expire_snapshots {
+            let expired_files = self
+                .expire_snapshots(&policy.expire_snapshots_options)
+                .await?;
+            if policy.delete_expired_files {
+                report.deleted_files_count = self.delete_expired_files(&expired_files).await?;
+            }
+            report.expired_files = Some(expired_files);
+        }
+        tracing::info!(
+            "Maintenance of table '{}' completed, {} files expired and {} deleted",
+            self.table_ident(),
+            report
+                .expired_files
+                .as_ref()
+                .map_or(0, ExpiredFiles::files_count),
+            report.deleted_files_count
+        );
+        Ok(report)
+    }
+}
@@ -0,0 +1,78 @@
+/*
+ * Copyright 2025 iceberg-compaction
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Fixtures shared by the unit tests of this crate.
+
+use std::sync::Arc;
+
+use iceberg::scan::FileScanTask;
+use iceberg::spec::{DataContentType, DataFileFormat, Schema};
+
+/// A task reading the whole file at `path`, of no rows and an empty schema.
+pub fn file_scan_task(
+    path: &str,
+    content: DataContentType,
+    file_size_in_bytes: u64,
+    deletes: Vec<FileScanTask>,
+) -> FileScanTask {
+    FileScanTask {
+        length: file_size_in_bytes,
+        start: 0,
+        record_count: Some(0),
+        data_file_path: path.to_owned(),
+        data_file_content: content,
+        data_file_format: DataFileFormat::Parquet,
+        schema: Arc::new(Schema::builder().build().unwrap()),
+        project_field_ids: vec![],
+        predicate: None,
+        deletes,
+        sequence_number: 0,
+        equality_ids: vec![],
+        file_size_in_bytes,
+    }
+}
+
+/// A task of the data file at `path` without deletes.
+pub fn data_file(path: &str, file_size_in_bytes: u64) -> FileScanTask {
+    file_scan_task(path, DataContentType::Data, file_size_in_bytes, vec![])
+}
+
+/// A task of the data file at `path` of `records` rows, with a position delete file of each of
+/// `delete_records` rows, applying only to this data file.
+pub fn data_file_with_deletes(
+    path: &str,
+    file_size_in_bytes: u64,
+    records: u64,
+    delete_records: &[u64],
+) -> FileScanTask {
+    let deletes = delete_records
+        .iter()
+        .enumerate()
+        .map(|(i, &count)| FileScanTask {
+            record_count: Some(count),
+            ..file_scan_task(
+                &format!("{}-delete-{}", path, i),
+                DataContentType::PositionDeletes,
+                0,
+                vec![],
+            )
+        })
+        .collect();
+    FileScanTask {
+        record_count: Some(records),
+        ..file_scan_task(path, DataContentType::Data, file_size_in_bytes, deletes)
+    }
+}
@@ -0,0 +1,204 @@
+/*
+ * Copyright 2025 iceberg-compaction
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Exercises the stable public API from outside the crate, so that moving or hiding an item
+//! integrators rely on fails to compile here.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use datafusion::arrow::array::{Int32Array, RecordBatch, StringArray};
+use iceberg::arrow::schema_to_arrow_schema;
+use iceberg::io::FileIOBuilder;
+use iceberg::spec::{NestedField, PrimitiveType, Schema, Snapshot, Type};
+use iceberg::table::Table;
+use iceberg::transaction::Transaction;
+use iceberg::{Catalog, NamespaceIdent, TableCreation, TableIdent};
+use iceberg_catalog_memory::MemoryCatalog;
+use iceberg_compaction_core::compaction::commit_hook::CommitHook;
+use iceberg_compaction_core::compaction::{
+    Compaction, CompactionBuilder, CompactionPlan, CompactionType, SkipReason,
+};
+use iceberg_compaction_core::config::CompactionConfigBuilder;
+use iceberg_compaction_core::executor::{
+    write_record_batches, BufferedIcebergWriter, CompactionExecutor, RewriteFilesRequest,
+    RewriteFilesResponse, RewriteFilesStat,
+};
+use iceberg_compaction_core::maintenance::TableMaintenance;
+use iceberg_compaction_core::{CompactionConfig, CompactionError, Result};
+use tempfile::TempDir;
+
+struct NoopExecutor;
+
+#[async_trait]
+impl CompactionExecutor for NoopExecutor {
+    async fn rewrite_files(&self, _request: RewriteFilesRequest) -> Result<RewriteFilesResponse> {
+        Ok(RewriteFilesResponse::default())
+    }
+}
+
+struct NoopCommitHook;
+
+#[async_trait]
+impl CommitHook for NoopCommitHook {
+    async fn before_commit(&self, _table: &Table, _transaction: &Transaction<'_>) -> Result<()> {
+        Ok(())
+    }
+
+    async fn after_commit(&self, _table: &Table, _snapshot: &Snapshot) {}
+}
+
+/// An empty table in a memory catalog, kept alive with its warehouse directory.
+struct Fixture {
+    _warehouse: TempDir,
+    catalog: Arc<MemoryCatalog>,
+    table_ident: TableIdent,
+}
+
+impl Fixture {
+    async fn new() -> Self {
+        let warehouse = TempDir::new().unwrap();
+        let file_io = FileIOBuilder::new_fs_io().build().unwrap();
+        let catalog = MemoryCatalog::new(
+            file_io,
+            Some(warehouse.path().to_str().unwrap().to_string()),
+        );
+        let namespace_ident = NamespaceIdent::new("api_stability".into());
+        catalog
+            .create_namespace(&namespace_ident, HashMap::new())
+            .await
+            .unwrap();
+        let schema = Schema::builder()
+            .with_fields(vec![
+                NestedField::required(1, "id", Type::Primitive(PrimitiveType::Int)).into(),
+                NestedField::optional(2, "name", Type::Primitive(PrimitiveType::String)).into(),
+            ])
+            .build()
+            .unwrap();
+        catalog
+            .create_table(
+                &namespace_ident,
+                TableCreation::builder()
+                    .name("events".into())
+                    .schema(schema)
+                    .build(),
+            )
+            .await
+            .unwrap();
+
+        Self {
+            _warehouse: warehouse,
+            catalog: Arc::new(catalog),
+            table_ident: TableIdent::new(namespace_ident, "events".into()),
+        }
+    }
+
+    async fn compaction(&self) -> Result<Compaction> {
+        CompactionBuilder::new()
+            .with_catalog(self.catalog.clone())
+            .with_table_ident(self.table_ident.clone())
+            .with_config(CompactionConfigBuilder::default().build().unwrap())
+            .with_commit_hook(Arc::new(NoopCommitHook))
+            .build()
+            .await
+    }
+}
+
+#[test]
+fn test_config_builder() {
+    let config: CompactionConfig = CompactionConfigBuilder::default().build().unwrap();
+    let _: Arc<CompactionConfig> = config.into();
+}
+
+#[tokio::test]
+async fn test_compaction_of_empty_table() {
+    let fixture = Fixture::new().await;
+    let mut compaction = fixture.compaction().await.unwrap();
+    compaction.executor = Box::new(NoopExecutor);
+
+    let plan: CompactionPlan = compaction.plan(&CompactionType::Full).await.unwrap();
+    assert_eq!(plan.skipped, Some(SkipReason::NoSnapshot));
+    assert_eq!(plan.data_files_count(), 0);
+
+    let stat: RewriteFilesStat = compaction.compact().await.unwrap();
+    assert_eq!(stat.skipped, Some(SkipReason::NoSnapshot));
+    assert_eq!(stat.rewritten_files_count, 0);
+}
+
+#[tokio::test]
+async fn test_missing_table_is_an_error() {
+    let fixture = Fixture::new().await;
+    let result = CompactionBuilder::new()
+        .with_catalog(fixture.catalog.clone())
+        .with_table_ident(TableIdent::new(
+            fixture.table_ident.namespace.clone(),
+            "missing".into(),
+        ))
+        .with_config(CompactionConfigBuilder::default().build().unwrap())
+        .build()
+        .await;
+    assert!(matches!(result, Err(CompactionError::Execution(_))));
+}
+
+#[tokio::test]
+async fn test_maintenance_of_empty_table() {
+    let fixture = Fixture::new().await;
+    let maintenance = TableMaintenance::new(
+        fixture.catalog.clone(),
+        fixture.table_ident.clone(),
+        CompactionConfigBuilder::default().build().unwrap(),
+    )
+    .await
+    .unwrap();
+    let stat = maintenance.compact().await.unwrap();
+    assert_eq!(stat.skipped, Some(SkipReason::NoSnapshot));
+    let health = maintenance.analyze().await.unwrap();
+    assert_eq!(health.snapshot_id, None);
+    assert_eq!(health.data_files_count, 0);
+}
+
+#[tokio::test]
+async fn test_write_record_batches() {
+    let fixture = Fixture::new().await;
+    let table = fixture
+        .catalog
+        .load_table(&fixture.table_ident)
+        .await
+        .unwrap();
+    let config = CompactionConfigBuilder::default().build().unwrap();
+    let arrow_schema = schema_to_arrow_schema(table.metadata().current_schema()).unwrap();
+    let batch = RecordBatch::try_new(
+        Arc::new(arrow_schema),
+        vec![
+            Arc::new(Int32Array::from(vec![1, 2])),
+            Arc::new(StringArray::from(vec!["a", "b"])),
+        ],
+    )
+    .unwrap();
+
+    let batches = futures::stream::iter(vec![Ok(batch.clone())]);
+    let data_files = write_record_batches(&table, &config, batches)
+        .await
+        .unwrap();
+    assert_eq!(data_files.len(), 1);
+
+    let mut writer = BufferedIcebergWriter::try_new(&table, &config)
+        .await
+        .unwrap();
+    writer.write(batch).await.unwrap();
+    assert_eq!(writer.close().await.unwrap().len(), 1);
+}
@@ -0,0 +1,91 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use tempfile::TempDir;
+
+use futures::StreamExt;
+use iceberg::io::FileIOBuilder;
+use iceberg::spec::{NestedField, PrimitiveType, Schema, Type};
+use iceberg::{Catalog, NamespaceIdent, TableCreation, TableIdent};
+use iceberg_catalog_memory::MemoryCatalog;
+
+use iceberg_compaction_core::compaction::{CompactionBuilder, CompactionEvent};
+use iceberg_compaction_core::config::CompactionConfigBuilder;
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // 1. Set up a memory catalog backed by a temporary warehouse
+    let temp_dir = TempDir::new()?;
+    let warehouse_location = temp_dir.path().to_str().unwrap().to_string();
+    let file_io = FileIOBuilder::new_fs_io().build()?;
+    let catalog = Arc::new(MemoryCatalog::new(file_io, Some(warehouse_location)));
+
+    // 2. Create namespace and table
+    let namespace_ident = NamespaceIdent::new("warehouse".into());
+    catalog
+        .create_namespace(&namespace_ident, HashMap::new())
+        .await?;
+
+    let table_ident = TableIdent::new(namespace_ident, "events".into());
+    let schema = Schema::builder()
+        .with_fields(vec![
+            NestedField::required(1, "id", Type::Primitive(PrimitiveType::Long)).into(),
+            NestedField::required(2, "payload", Type::Primitive(PrimitiveType::String)).into(),
+        ])
+        .build()?;
+    catalog
+        .create_table(
+            &table_ident.namespace,
+            TableCreation::builder()
+                .name(table_ident.name().into())
+                .schema(schema)
+                .build(),
+        )
+        .await?;
+
+    // 3. Build the compaction with an owned config
+    let compaction = CompactionBuilder::new()
+        .with_catalog(catalog.clone())
+        .with_table_ident(table_ident.clone())
+        .with_config(CompactionConfigBuilder::default().build()?)
+        .with_catalog_name("memory_catalog".to_string())
+        .build()
+        .await?;
+
+    // 4. Check permissions and table features before touching any data
+    compaction.preflight(table_ident.clone()).await?;
+
+    // 5. Run the compaction and print its progress
+    let events = compaction.compact_stream();
+    futures::pin_mut!(events);
+    while let Some(event) = events.next().await {
+        match event? {
+            CompactionEvent::Planned {
+                data_files_count,
+                position_delete_files_count,
+                equality_delete_files_count,
+                file_groups_count,
+            } => println!(
+                "Planned {} data files, {} position deletes, {} equality deletes in {} file groups",
+                data_files_count,
+                position_delete_files_count,
+                equality_delete_files_count,
+                file_groups_count
+            ),
+            CompactionEvent::Rewritten(stat) => {
+                println!("Rewrote {} files", stat.rewritten_files_count)
+            }
+            CompactionEvent::Committed { snapshot_id } => {
+                println!("Committed snapshot {:?}", snapshot_id)
+            }
+            CompactionEvent::Validated => println!("Validation passed"),
+            CompactionEvent::Completed(stat) => {
+                println!("Compaction completed successfully!");
+                println!("  - Rewritten files: {}", stat.rewritten_files_count);
+                println!("  - Added files: {}", stat.added_files_count);
+                println!("  - Rewritten bytes: {}", stat.rewritten_bytes);
+            }
+        }
+    }
+
+    Ok(())
+}
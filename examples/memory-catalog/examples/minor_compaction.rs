@@ -0,0 +1,80 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use tempfile::TempDir;
+
+use iceberg::io::FileIOBuilder;
+use iceberg::spec::{NestedField, PrimitiveType, Schema, Type};
+use iceberg::{Catalog, NamespaceIdent, TableCreation, TableIdent};
+use iceberg_catalog_memory::MemoryCatalog;
+
+use iceberg_compaction_core::compaction::{CompactionBuilder, CompactionType};
+use iceberg_compaction_core::config::CompactionConfigBuilder;
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // 1. Set up a memory catalog backed by a temporary warehouse
+    let temp_dir = TempDir::new()?;
+    let warehouse_location = temp_dir.path().to_str().unwrap().to_string();
+    let file_io = FileIOBuilder::new_fs_io().build()?;
+    let catalog = Arc::new(MemoryCatalog::new(file_io, Some(warehouse_location)));
+
+    // 2. Create namespace and an unpartitioned table, as minor compaction requires
+    let namespace_ident = NamespaceIdent::new("warehouse".into());
+    catalog
+        .create_namespace(&namespace_ident, HashMap::new())
+        .await?;
+
+    let table_ident = TableIdent::new(namespace_ident, "orders".into());
+    let schema = Schema::builder()
+        .with_fields(vec![
+            NestedField::required(1, "id", Type::Primitive(PrimitiveType::Long)).into(),
+            NestedField::required(2, "status", Type::Primitive(PrimitiveType::String)).into(),
+        ])
+        .build()?;
+    catalog
+        .create_table(
+            &table_ident.namespace,
+            TableCreation::builder()
+                .name(table_ident.name().into())
+                .schema(schema)
+                .build(),
+        )
+        .await?;
+
+    // 3. Build a compaction that only merges delete files, leaving data files in place
+    let compaction = CompactionBuilder::new()
+        .with_catalog(catalog.clone())
+        .with_table_ident(table_ident.clone())
+        .with_config(CompactionConfigBuilder::default().build()?)
+        .with_catalog_name("memory_catalog".to_string())
+        .with_compaction_type(CompactionType::DeleteFiles)
+        .build()
+        .await?;
+
+    // 4. Merge the position and equality delete files
+    let stat = compaction.compact().await?;
+    match &stat.skipped {
+        Some(reason) => println!("Delete file compaction skipped: {:?}", reason),
+        None => {
+            println!("Delete file compaction completed successfully!");
+            println!("  - Rewritten delete files: {}", stat.rewritten_files_count);
+            println!("  - Added delete files: {}", stat.added_files_count);
+        }
+    }
+
+    // 5. Convert the equality deletes to position deletes, which are cheaper to apply on read
+    let stat = compaction.convert_equality_deletes().await?;
+    match &stat.skipped {
+        Some(reason) => println!("Equality delete conversion skipped: {:?}", reason),
+        None => {
+            println!("Equality delete conversion completed successfully!");
+            println!("  - Converted delete files: {}", stat.rewritten_files_count);
+            println!(
+                "  - Added position delete files: {}",
+                stat.added_files_count
+            );
+        }
+    }
+
+    Ok(())
+}